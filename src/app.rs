@@ -1,14 +1,320 @@
-use crate::tmux::{self, TmuxSession};
+//! The `App` state machine driven by key events.
+
+pub mod fuzzy;
+
+use crate::backend::{SystemTmux, TmuxBackend};
+use crate::clipboard;
+use crate::config::{config_dir, config_path, ensure_config_file, Config};
+use crate::events::{Changes, EventWatcher};
+use crate::input::{History, InputMode, TextInput};
+use crate::state::SavedState;
+use crate::store::SessionStore;
+use crate::tags::Tag;
+use crate::theme::Theme;
+use crate::tmux::{self, SessionDetails, SplitDirection, TmuxError, TmuxSession, WindowLayout};
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long the "attaching to …" toast shows before the attach happens
+const ATTACH_TOAST_DURATION: Duration = Duration::from_millis(700);
+
+/// How long the warning before attaching to a grouped session shows
+const GROUP_WARNING_DURATION: Duration = Duration::from_millis(1500);
+
+/// How long the result of reloading the config shows
+const CONFIG_TOAST_DURATION: Duration = Duration::from_millis(2500);
+
+/// How long the summary of a bulk kill shows
+const CLEANUP_TOAST_DURATION: Duration = Duration::from_millis(2500);
+
+/// How long the notice about a session closed elsewhere shows
+const VANISHED_TOAST_DURATION: Duration = Duration::from_millis(2500);
+
+/// How long a failed hook is reported
+const HOOK_TOAST_DURATION: Duration = Duration::from_millis(3000);
+
+/// How long the name of the color scheme picked with Ctrl-T is shown
+const THEME_TOAST_DURATION: Duration = Duration::from_millis(1500);
+
+/// How long the copied attach command is shown after `y`
+const COPY_TOAST_DURATION: Duration = Duration::from_millis(2500);
+
+/// Row actions, in order, when the config doesn't list any
+pub const DEFAULT_ACTIONS: [SessionAction; 3] = [
+    SessionAction::Enter,
+    SessionAction::Rename,
+    SessionAction::Delete,
+];
+
+/// Most entries kept in the action log; older ones are dropped
+const ACTION_LOG_CAPACITY: usize = 100;
+
+/// Poll interval while something on screen is time-based (e.g. a toast)
+const ANIMATION_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How often the live preview re-captures the selected pane
+const TAIL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Quiet time after the last keystroke in the search prompt before matches
+/// are recomputed, so fast typing doesn't rescan the list on every key
+const FILTER_DEBOUNCE: Duration = Duration::from_millis(80);
+
+/// How long a typed count waits for its j/k before it's dropped
+const COUNT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Largest count a j/k motion takes; more digits are ignored
+const MAX_COUNT: usize = 999;
+
+/// How long each page of a help bar too wide for the terminal shows
+const HELP_PAGE_INTERVAL: Duration = Duration::from_secs(3);
+
+/// How long sessions that appeared or vanished on auto-refresh stay marked
+const CHANGE_HIGHLIGHT_DURATION: Duration = Duration::from_secs(2);
+
+/// Which sessions a bulk kill targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CleanupKind {
+    /// Detached sessions with only an idle shell
+    Empty,
+    /// Every detached session
+    Detached,
+    /// Detached sessions whose start directory no longer exists
+    Zombie,
+    /// Detached sessions without activity for longer than `idle_hours`
+    Idle,
+}
+
+impl CleanupKind {
+    /// Adjective for the sessions killed, as in "Killed 3 empty sessions"
+    pub fn label(self) -> &'static str {
+        match self {
+            CleanupKind::Empty => "empty",
+            CleanupKind::Detached => "detached",
+            CleanupKind::Zombie => "zombie",
+            CleanupKind::Idle => "idle",
+        }
+    }
+}
+
+/// A bulk operation held in `AppState::ConfirmingBatch` until confirmed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchOperation {
+    /// Kill the sessions a cleanup picked out
+    Kill(CleanupKind),
+    /// Rename the sessions to `prefix-1`, `prefix-2`, … in list order
+    Renumber { prefix: String },
+}
+
+/// One session in a batch awaiting confirmation; only included ones are
+/// acted on
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchItem {
+    pub name: String,
+    /// The server `name` is on, as for `tmux -L`
+    pub socket: Option<String>,
+    pub included: bool,
+}
+
+impl BatchItem {
+    fn new(session: &TmuxSession) -> Self {
+        BatchItem {
+            name: session.name.clone(),
+            socket: session.socket.clone(),
+            included: true,
+        }
+    }
+}
+
+/// A confirmed batch being carried out one step per tick, so its progress
+/// can be drawn between sessions
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchProgress {
+    pub operation: BatchOperation,
+    /// Sessions finished with, whether or not that worked
+    pub done: usize,
+    pub total: usize,
+    pub failures: Vec<String>,
+    succeeded: usize,
+    steps: VecDeque<BatchStep>,
+}
+
+impl BatchProgress {
+    fn new(operation: BatchOperation, steps: Vec<BatchStep>) -> Self {
+        Self {
+            operation,
+            done: 0,
+            total: steps.len(),
+            failures: Vec::new(),
+            succeeded: 0,
+            steps: steps.into(),
+        }
+    }
+
+    /// What happened to the sessions that worked, e.g. "Killed 3 detached
+    /// sessions"
+    fn summary(&self) -> String {
+        let plural = if self.succeeded == 1 { "" } else { "s" };
+        match &self.operation {
+            BatchOperation::Kill(kind) => format!(
+                "Killed {} {} session{}",
+                self.succeeded,
+                kind.label(),
+                plural
+            ),
+            BatchOperation::Renumber { .. } => {
+                format!("Renamed {} session{}", self.succeeded, plural)
+            }
+        }
+    }
+}
+
+/// One tmux command of a running batch
+#[derive(Debug, Clone, PartialEq)]
+enum BatchStep {
+    Kill {
+        kind: CleanupKind,
+        name: String,
+        socket: Option<String>,
+    },
+    /// First pass of a renumber: move `old` out of the way under `temp`
+    /// until it can become `new`
+    Park {
+        old: String,
+        temp: String,
+        new: String,
+        socket: Option<String>,
+    },
+    /// Second pass: give the parked session its new name
+    Rename {
+        old: String,
+        temp: String,
+        new: String,
+        socket: Option<String>,
+    },
+}
+
+/// Session events that run the matching command from `[hooks]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    Attach,
+    Create,
+    Delete,
+}
+
+impl HookEvent {
+    /// The config key, also passed to hooks as `$URSA_EVENT`
+    pub fn key(self) -> &'static str {
+        match self {
+            HookEvent::Attach => "on_attach",
+            HookEvent::Create => "on_create",
+            HookEvent::Delete => "on_delete",
+        }
+    }
+}
 
+/// Which screen or prompt currently receives key events. Prompts that end
+/// in a tmux command keep the `socket` of the session they're about, since
+/// names are only unique per server.
 #[derive(Debug, Clone, PartialEq)]
 pub enum AppState {
     SessionList,
     CreatingSession,
-    RenamingSession { original_name: String },
+    /// Typing a `KEY=VALUE` entry for the session being created, whose name
+    /// typed so far is kept here
+    AddingEnv {
+        session_name: String,
+    },
+    /// Typing the name of a session to attach to, created first if missing
+    GoingTo,
+    /// Typing the prefix the listed sessions are renumbered under
+    NumberingSessions,
+    RenamingSession {
+        original_name: String,
+        socket: Option<String>,
+    },
+    /// Typing a prefix (`prepend`) or suffix to add to a session's name
+    AffixingName {
+        original_name: String,
+        socket: Option<String>,
+        prepend: bool,
+    },
+    CreatingWindow {
+        session_name: String,
+        socket: Option<String>,
+    },
+    /// Typing the file to save a session's scrollback to
+    SavingScrollback {
+        session_name: String,
+        socket: Option<String>,
+    },
+    /// Editing the note attached to a session
+    EditingNote {
+        session_name: String,
+    },
+    Searching,
+    /// Reviewing every session a bulk operation would touch, each of which
+    /// can be left out; `cursor` is the item Space toggles
+    ConfirmingBatch {
+        operation: BatchOperation,
+        items: Vec<BatchItem>,
+        cursor: usize,
+    },
+    /// A confirmed batch running, with a progress bar until it's done
+    RunningBatch {
+        progress: BatchProgress,
+    },
+    /// The action log overlay
+    ViewingLog,
+    /// Key overview shown once, on the first run
+    Welcome,
+    /// Typing the name of a locked session to allow renaming or deleting it
+    ConfirmingLocked {
+        name: String,
+        socket: Option<String>,
+        action: SessionAction,
+    },
+    /// Asking whether to rename a session a client is attached to
+    ConfirmingAttachedRename {
+        name: String,
+        socket: Option<String>,
+    },
+    /// Asking whether to delete the session Ursa itself runs in, which
+    /// closes Ursa's own window with it
+    ConfirmingOwnDelete {
+        name: String,
+    },
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
+impl AppState {
+    /// Whether keys are typed into `App::input`
+    pub fn takes_text(&self) -> bool {
+        matches!(
+            self,
+            AppState::CreatingSession
+                | AppState::AddingEnv { .. }
+                | AppState::GoingTo
+                | AppState::NumberingSessions
+                | AppState::RenamingSession { .. }
+                | AppState::AffixingName { .. }
+                | AppState::CreatingWindow { .. }
+                | AppState::SavingScrollback { .. }
+                | AppState::EditingNote { .. }
+                | AppState::Searching
+                | AppState::ConfirmingLocked { .. }
+        )
+    }
+}
+
+/// Action button selected on a session row
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SessionAction {
     #[default]
     Enter,
@@ -16,30 +322,215 @@ pub enum SessionAction {
     Delete,
 }
 
+/// How an active search query affects the session list
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SearchStyle {
+    /// Hide sessions that don't match the query
+    #[default]
+    Filter,
+    /// Keep the full list visible and jump between matches with n/N
+    Jump,
+}
+
+/// How sessions are arranged on screen
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LayoutMode {
+    /// One session per row
+    #[default]
+    List,
+    /// Session cards in as many columns as fit; arrows move between neighbors
+    Grid,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum FocusArea {
     #[default]
     SessionList,
     TitleBar,
+    /// The detail pane; ↑↓ scroll it instead of moving the cursor
+    Detail,
 }
 
+/// A session as tmux commands address it: its socket (as for `tmux -L`) and
+/// its name, which is only unique per server
+pub type SessionTarget = (Option<String>, String);
+
+/// What the caller should do once the TUI loop ends
 #[derive(Debug, Clone, PartialEq)]
 pub enum AppAction {
     None,
-    AttachSession(String),
+    /// Attach to `name` on the server on `socket`
+    AttachSession {
+        name: String,
+        socket: Option<String>,
+    },
+    /// Show this file in `$PAGER`, then delete it and return to the list
+    OpenPager(PathBuf),
+    /// Open the config file in `$EDITOR`, then reload it
+    EditConfig(PathBuf),
     Quit,
 }
 
+/// An operation performed during this run, listed in the action log
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub at: Instant,
+    pub message: String,
+}
+
+/// A short-lived notification drawn over the session list
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub message: String,
+    pub expires_at: Instant,
+}
+
+/// UI state for the session manager
 pub struct App {
+    /// Where session operations go: the real tmux, or a fake in tests
+    pub backend: Arc<dyn TmuxBackend>,
     pub state: AppState,
     pub focus_area: FocusArea,
     pub sessions: Vec<TmuxSession>,
+    /// Why the last session listing failed, e.g. no server running yet
+    pub list_error: Option<TmuxError>,
+    /// Position in the visible rows (see `visible_sessions`); the "Create new"
+    /// row sits right after the last visible session
     pub selected_index: usize,
+    /// First visible row of the session list, written back by the renderer
+    pub scroll_offset: Cell<usize>,
+    /// Sessions that fit in the list at once, written back by the renderer;
+    /// PageUp/PageDown move by this many rows
+    pub page_size: Cell<usize>,
+    pub layout_mode: LayoutMode,
+    /// Number rows by their distance from the cursor, for counted j/k jumps
+    pub relative_numbers: bool,
+    /// Count typed before a j/k motion (as in vim's `5j`) and when it was
+    /// last extended
+    pub pending_count: Option<(usize, Instant)>,
+    /// Cards per row in grid mode, written back by the renderer
+    pub grid_columns: Cell<usize>,
+    /// First visible row of cards in grid mode, written back by the renderer
+    pub grid_scroll: Cell<usize>,
     pub selected_action: SessionAction,
-    pub input_buffer: String,
+    /// Details of the selected session keyed by its target, loaded by `tick`
+    /// while the detail pane is shown
+    pub details: Option<(SessionTarget, Result<SessionDetails, TmuxError>)>,
+    /// Details being read on another thread, for the session they're of
+    pending_details: Option<(SessionTarget, Receiver<Result<SessionDetails, TmuxError>>)>,
+    /// The detail pane fits beside the list at the current width, written
+    /// back by the renderer
+    pub detail_pane_fits: Cell<bool>,
+    /// First visible line of the detail pane, clamped by the renderer
+    pub detail_scroll: Cell<u16>,
+    /// Show a live capture of the selected session's active pane instead of
+    /// its details
+    pub tailing: bool,
+    /// Last capture for the live preview keyed by session target, refreshed
+    /// by `tick` every `TAIL_INTERVAL` while tailing
+    pub tail: Option<(SessionTarget, Result<String, TmuxError>)>,
+    /// When the live preview was last captured
+    last_tail: Instant,
+    /// Text typed into the create, rename and window prompts
+    pub input: TextInput,
+    /// Completion candidates gathered when the create prompt opens
+    pub name_candidates: Vec<String>,
+    /// Index into `config.layouts` applied to the next created session
+    pub layout_index: usize,
+    /// `KEY=VALUE` entries set in the environment of the next created session
+    pub create_env: Vec<String>,
+    pub search_query: TextInput,
+    /// Submitted search queries, recalled with ↑/↓ in the search prompt
+    pub search_history: History,
+    /// Names sessions were created with, recalled with ↑/↓ in the create prompt
+    pub create_history: History,
+    pub search_style: SearchStyle,
+    /// Indices into `sessions` matching `search_query`
+    pub matches: Vec<usize>,
+    /// Ordinal of the current match within `matches`
+    pub match_index: usize,
+    /// `search_query` changed since `matches` was last computed
+    pub filter_dirty: bool,
+    /// When `search_query` last changed; matches follow once
+    /// `FILTER_DEBOUNCE` has passed
+    pub filter_changed_at: Instant,
     pub should_quit: bool,
     pub action: AppAction,
     pub error_message: Option<String>,
+    pub status_message: Option<String>,
+    /// Pages the key hints need at the current width, written back by the
+    /// renderer; more than one makes the help bar rotate
+    pub help_pages: Cell<usize>,
+    /// Page of the key hints shown, advanced by `tick` every
+    /// `HELP_PAGE_INTERVAL` while they don't fit
+    pub help_page: usize,
+    /// When the help bar last turned its page
+    pub help_paged_at: Instant,
+    /// When the last key was pressed, for `quit_after_idle_secs`
+    pub last_input: Instant,
+    pub toast: Option<Toast>,
+    /// Name whose validation warning was already shown; Enter again submits it
+    pub warned_name: Option<String>,
+    /// Session to attach to once the current toast expires
+    pub pending_attach: Option<SessionTarget>,
+    /// Set once the client was switched to a session while staying open
+    pub switched: bool,
+    /// When the session list was last read from tmux
+    pub last_refresh: Instant,
+    /// Whether the terminal has focus, as last reported by it
+    pub focused: bool,
+    /// Sessions that appeared on auto-refresh, highlighted until the instant
+    pub appeared: HashMap<String, Instant>,
+    /// Sessions that vanished on auto-refresh, still listed until the instant
+    pub vanished: Vec<(String, Instant)>,
+    /// Operations performed this run, oldest first
+    pub action_log: VecDeque<LogEntry>,
+    /// Report destructive operations in the status bar instead of running them
+    pub dry_run: bool,
+    pub config: Config,
+    /// Color tags by session name, persisted to `tags.toml`
+    pub tags: SessionStore<Tag>,
+    /// Sessions that need their name typed before a rename or delete,
+    /// persisted to `locks.toml`
+    pub locks: SessionStore<bool>,
+    /// Freeform notes by session name, persisted to `notes.toml`
+    pub notes: SessionStore<String>,
+    /// Window layouts saved with `s` by session name, persisted to
+    /// `snapshots.toml` and restored by attaching with `a`
+    pub snapshots: SessionStore<WindowLayout>,
+    /// Only show sessions carrying this tag
+    pub tag_filter: Option<Tag>,
+    /// List the sessions the config's `hidden` leaves out, toggled with `.`
+    pub show_hidden: bool,
+    pub theme: Theme,
+    /// How names that can't be shown as-is are displayed, by raw name
+    display_names: HashMap<String, String>,
+    /// Sessions whose start directory was gone at the last refresh
+    zombies: HashSet<String>,
+    /// The first session list, read in the background so the UI can paint
+    /// right away
+    loading: Option<Loading>,
+    /// Hooks report failures here from the threads waiting on them
+    hook_sender: Sender<String>,
+    hook_failures: Receiver<String>,
+    /// Control clients reporting changes, with `control_mode` on and while
+    /// they can run
+    watcher: Option<EventWatcher>,
+    /// tmux reported a change the list hasn't been refreshed for yet
+    changed_elsewhere: bool,
+    /// Quitting was asked for mid-renumber; it happens once that's done
+    quit_after_batch: bool,
+}
+
+/// A session list being read on another thread
+#[derive(Debug)]
+struct Loading {
+    receiver: Receiver<Result<Vec<TmuxSession>, TmuxError>>,
+    /// Why the tmux binary doesn't run, if it doesn't; answered before the
+    /// sessions are
+    binary_check: Receiver<TmuxError>,
+    /// Position to restore once the sessions are in
+    saved: SavedState,
 }
 
 impl Default for App {
@@ -49,252 +540,3241 @@ impl Default for App {
 }
 
 impl App {
+    /// Create the app with the config, the current list of tmux sessions,
+    /// saved tags and the list position from last time
     pub fn new() -> Self {
-        let sessions = tmux::list_sessions();
+        let mut app = Self::with_sessions(Vec::new());
+        // Nothing has been saved yet on the very first run
+        let first_run = config_dir().is_some_and(|dir| !dir.exists());
+        let (config, config_error) = match Config::load() {
+            Ok(config) => (config, None),
+            Err(e) => (Config::default(), Some(e)),
+        };
+        let (tags, tags_error) = match SessionStore::load("tags.toml") {
+            Ok(tags) => (tags, None),
+            Err(e) => (SessionStore::empty("tags.toml"), Some(e)),
+        };
+        let (locks, locks_error) = match SessionStore::load("locks.toml") {
+            Ok(locks) => (locks, None),
+            Err(e) => (SessionStore::empty("locks.toml"), Some(e)),
+        };
+        let (notes, notes_error) = match SessionStore::load("notes.toml") {
+            Ok(notes) => (notes, None),
+            Err(e) => (SessionStore::empty("notes.toml"), Some(e)),
+        };
+        let (snapshots, snapshots_error) = match SessionStore::load("snapshots.toml") {
+            Ok(snapshots) => (snapshots, None),
+            Err(e) => (SessionStore::empty("snapshots.toml"), Some(e)),
+        };
+        let (saved, state_error) = match SavedState::load() {
+            Ok(saved) => (saved, None),
+            Err(e) => (SavedState::default(), Some(e)),
+        };
+        app.config = config;
+        app.tags = tags;
+        app.locks = locks;
+        app.notes = notes;
+        app.snapshots = snapshots;
+        app.search_history = History::new(saved.search_history.clone());
+        app.create_history = History::new(saved.create_history.clone());
+        app.theme.scheme = saved.color_scheme;
+        app.error_message = config_error
+            .or(tags_error)
+            .or(locks_error)
+            .or(notes_error)
+            .or(snapshots_error)
+            .or(state_error);
+        tmux::set_binary(Some(&app.config.tmux_binary()));
+        // A cold tmux server can take a while to answer, so the list is read
+        // in the background and the UI starts out empty
+        let (sender, receiver) = mpsc::channel();
+        let (check_sender, binary_check) = mpsc::channel();
+        let sockets = app.config.sockets.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = tmux::check_binary() {
+                let _ = check_sender.send(e);
+            }
+            let _ = sender.send(SystemTmux.list_sessions(&sockets));
+        });
+        app.loading = Some(Loading {
+            receiver,
+            binary_check,
+            saved,
+        });
+        if first_run {
+            app.state = AppState::Welcome;
+        }
+        app
+    }
+
+    /// Create the app over `backend`'s sessions, such as a
+    /// [`FakeTmux`](crate::backend::FakeTmux) whose sessions the app's
+    /// create, rename and delete flows then change
+    pub fn with_backend(backend: Box<dyn TmuxBackend>) -> Self {
+        let mut app = Self::with_sessions(Vec::new());
+        app.backend = backend.into();
+        app.refresh_sessions();
+        app
+    }
+
+    /// Create the app over a fixed session list without querying tmux or
+    /// loading anything from disk, e.g. to drive it with synthetic key events.
+    /// Actions that run tmux commands (create, refresh, …) still do.
+    pub fn with_sessions(sessions: Vec<TmuxSession>) -> Self {
+        let display_names = display_names(sessions.iter().map(|s| s.name.as_str()));
+        let zombies = zombie_sessions(&sessions);
+        let (hook_sender, hook_failures) = mpsc::channel();
         Self {
+            backend: Arc::new(SystemTmux),
             state: AppState::SessionList,
             focus_area: FocusArea::SessionList,
             sessions,
+            list_error: None,
             selected_index: 0,
+            scroll_offset: Cell::new(0),
+            page_size: Cell::new(1),
+            layout_mode: LayoutMode::default(),
+            relative_numbers: false,
+            pending_count: None,
+            grid_columns: Cell::new(1),
+            grid_scroll: Cell::new(0),
             selected_action: SessionAction::default(),
-            input_buffer: String::new(),
+            details: None,
+            pending_details: None,
+            detail_pane_fits: Cell::new(false),
+            detail_scroll: Cell::new(0),
+            tailing: false,
+            tail: None,
+            last_tail: Instant::now(),
+            input: TextInput::default(),
+            name_candidates: Vec::new(),
+            layout_index: 0,
+            create_env: Vec::new(),
+            search_query: TextInput::default(),
+            search_history: History::default(),
+            create_history: History::default(),
+            search_style: SearchStyle::default(),
+            matches: Vec::new(),
+            match_index: 0,
+            filter_dirty: false,
+            filter_changed_at: Instant::now(),
             should_quit: false,
             action: AppAction::None,
             error_message: None,
+            status_message: None,
+            help_pages: Cell::new(1),
+            help_page: 0,
+            help_paged_at: Instant::now(),
+            last_input: Instant::now(),
+            toast: None,
+            warned_name: None,
+            pending_attach: None,
+            switched: false,
+            last_refresh: Instant::now(),
+            focused: true,
+            appeared: HashMap::new(),
+            vanished: Vec::new(),
+            action_log: VecDeque::new(),
+            dry_run: false,
+            config: Config::default(),
+            tags: SessionStore::empty("tags.toml"),
+            locks: SessionStore::empty("locks.toml"),
+            notes: SessionStore::empty("notes.toml"),
+            snapshots: SessionStore::empty("snapshots.toml"),
+            tag_filter: None,
+            show_hidden: false,
+            theme: Theme::default(),
+            display_names,
+            zombies,
+            loading: None,
+            hook_sender,
+            hook_failures,
+            watcher: None,
+            changed_elsewhere: false,
+            quit_after_batch: false,
         }
     }
 
-    pub fn refresh_sessions(&mut self) {
-        self.sessions = tmux::list_sessions();
-        // Ensure selected index is within bounds (max is sessions.len() for "Create new")
-        let max_index = self.sessions.len(); // "Create new" is at this index
-        if self.selected_index > max_index {
-            self.selected_index = max_index;
+    /// Re-read the config file and apply it without restarting: the tmux
+    /// binary, sockets and layouts take effect right away. A file that fails
+    /// to parse leaves the current config in place.
+    pub fn reload_config(&mut self) {
+        match Config::load() {
+            Ok(config) => {
+                self.config = config;
+                self.selected_action = SessionAction::Enter;
+                tmux::set_binary(Some(&self.config.tmux_binary()));
+                // The binary or sockets may have changed too
+                self.watcher = None;
+                if self.config.control_mode {
+                    self.start_watching();
+                }
+                self.layout_index = self
+                    .layout_index
+                    .min(self.config.layouts.len().saturating_sub(1));
+                // The configured sockets decide which sessions are listed
+                self.refresh_sessions();
+                self.show_toast("Config reloaded", CONFIG_TOAST_DURATION);
+            }
+            Err(e) => {
+                // TOML errors continue with a source excerpt over several lines
+                let summary = e.lines().next().unwrap_or_default();
+                self.show_toast(
+                    format!("Config not reloaded: {}", summary),
+                    CONFIG_TOAST_DURATION,
+                );
+            }
         }
     }
 
-    /// Total items = sessions + "Create new session" option (+ input row when creating)
-    pub fn total_items(&self) -> usize {
-        let base = self.sessions.len() + 1;
-        if self.state == AppState::CreatingSession {
-            base + 1
-        } else {
-            base
+    /// Ask the caller to open the config file in an editor, creating it
+    /// first so there's something to edit
+    fn edit_config(&mut self) {
+        let Some(path) = config_path() else {
+            self.error_message = Some("No config directory: set $HOME".to_string());
+            return;
+        };
+        match ensure_config_file(&path) {
+            Ok(()) => self.action = AppAction::EditConfig(path),
+            Err(e) => self.error_message = Some(e),
         }
     }
 
-    pub fn handle_key(&mut self, key: KeyEvent) {
-        // Clear error on any keypress
-        self.error_message = None;
-
-        match &self.state {
-            AppState::SessionList => self.handle_session_list_key(key),
-            AppState::CreatingSession => self.handle_creating_session_key(key),
-            AppState::RenamingSession { .. } => self.handle_renaming_session_key(key),
+    /// Put the cursor back on the session selected last time, if it still
+    /// exists, and scroll to where the list was
+    fn restore_position(&mut self, saved: &SavedState) {
+        let visible = self.visible_sessions();
+        if let Some(pos) = saved
+            .selected
+            .as_ref()
+            .and_then(|name| visible.iter().position(|&i| &self.sessions[i].name == name))
+        {
+            self.selected_index = pos;
         }
+        self.scroll_offset
+            .set(saved.scroll_offset.min(visible.len()));
     }
 
-    fn handle_session_list_key(&mut self, key: KeyEvent) {
-        match key.code {
-            KeyCode::Char('q') => {
-                self.should_quit = true;
-            }
-            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.should_quit = true;
-            }
-            KeyCode::Esc => {
-                self.should_quit = true;
-            }
-            KeyCode::Up | KeyCode::Char('k') => {
-                if self.focus_area == FocusArea::TitleBar {
-                    // Already at title bar, do nothing
-                } else if self.selected_index > 0 {
-                    self.selected_index -= 1;
-                    self.selected_action = SessionAction::Enter;
-                } else {
-                    // At top of list, move focus to title bar
-                    self.focus_area = FocusArea::TitleBar;
-                }
-            }
-            KeyCode::Down | KeyCode::Char('j') => {
-                if self.focus_area == FocusArea::TitleBar {
-                    self.focus_area = FocusArea::SessionList;
-                } else if self.selected_index < self.total_items() - 1 {
-                    self.selected_index += 1;
-                    self.selected_action = SessionAction::Enter;
-                }
-            }
-            KeyCode::Right | KeyCode::Char('l') => {
-                // Only allow action cycling for existing sessions (not "Create new")
-                if self.selected_index < self.sessions.len() {
-                    self.selected_action = match self.selected_action {
-                        SessionAction::Enter => SessionAction::Rename,
-                        SessionAction::Rename => SessionAction::Delete,
-                        SessionAction::Delete => SessionAction::Delete, // Stop at edge
-                    };
-                }
-            }
-            KeyCode::Left | KeyCode::Char('h') => {
-                // Only allow action cycling for existing sessions (not "Create new")
-                if self.selected_index < self.sessions.len() {
-                    self.selected_action = match self.selected_action {
-                        SessionAction::Enter => SessionAction::Enter, // Stop at edge
-                        SessionAction::Rename => SessionAction::Enter,
-                        SessionAction::Delete => SessionAction::Rename,
-                    };
-                }
-            }
-            KeyCode::Enter => {
-                if self.focus_area == FocusArea::TitleBar {
-                    self.refresh_sessions();
-                    self.focus_area = FocusArea::SessionList;
-                } else {
-                    self.select_current();
-                }
-            }
-            KeyCode::Char('r') => {
-                self.refresh_sessions();
-            }
-            _ => {}
+    /// The list position to remember for next time
+    pub fn saved_state(&self) -> SavedState {
+        let search_history = self.search_history.entries().to_vec();
+        let create_history = self.create_history.entries().to_vec();
+        // Quitting before the list arrived leaves the position as it was
+        if let Some(loading) = &self.loading {
+            return SavedState {
+                search_history,
+                create_history,
+                color_scheme: self.theme.scheme,
+                ..loading.saved.clone()
+            };
+        }
+        SavedState {
+            selected: self.selected_session().map(|s| s.name.clone()),
+            scroll_offset: self.scroll_offset.get(),
+            search_history,
+            create_history,
+            color_scheme: self.theme.scheme,
         }
     }
 
-    fn handle_creating_session_key(&mut self, key: KeyEvent) {
-        match key.code {
-            KeyCode::Esc => {
-                self.state = AppState::SessionList;
-                self.input_buffer.clear();
-            }
-            KeyCode::Enter => {
-                if !self.input_buffer.is_empty() {
-                    self.create_and_attach_session();
-                }
-            }
-            KeyCode::Backspace => {
-                self.input_buffer.pop();
-            }
-            KeyCode::Char(c) => {
-                // Only allow valid tmux session name characters
-                if c.is_alphanumeric() || c == '-' || c == '_' {
-                    self.input_buffer.push(c);
-                }
-            }
-            _ => {}
-        }
+    /// Re-read sessions from tmux, keeping the cursor in bounds
+    pub fn refresh_sessions(&mut self) {
+        // Newer than whatever the startup read would bring
+        self.loading = None;
+        self.set_sessions(self.list_sessions());
+        // Reloaded on the next tick, since windows and clients change too
+        self.reload_details();
+        self.last_refresh = Instant::now();
+        self.changed_elsewhere = false;
+        self.update_matches();
+        self.clamp_selection();
     }
 
-    fn select_current(&mut self) {
-        if self.selected_index == self.sessions.len() {
-            // "Create new session" selected
-            self.state = AppState::CreatingSession;
-            self.input_buffer.clear();
-        } else if let Some(session) = self.sessions.get(self.selected_index) {
-            match self.selected_action {
-                SessionAction::Enter => {
-                    // Attach to session
-                    self.action = AppAction::AttachSession(session.name.clone());
-                }
-                SessionAction::Rename => {
-                    // Enter rename mode
-                    self.state = AppState::RenamingSession {
-                        original_name: session.name.clone(),
-                    };
-                    self.input_buffer = session.name.clone();
-                }
-                SessionAction::Delete => {
-                    // Delete the session
-                    self.delete_current_session();
-                }
-            }
-        }
+    /// Sessions from every configured socket, or the current server
+    fn list_sessions(&self) -> Result<Vec<TmuxSession>, TmuxError> {
+        self.backend.list_sessions(&self.config.sockets)
     }
 
-    fn delete_current_session(&mut self) {
-        let Some(session) = self.sessions.get(self.selected_index) else {
+    /// Whether the first session list is still being read
+    pub fn is_loading(&self) -> bool {
+        self.loading.is_some()
+    }
+
+    /// Fill in the first session list once the background read finishes
+    fn poll_loading(&mut self) {
+        let Some(loading) = &self.loading else {
             return;
         };
-        let name = session.name.clone();
-
-        match tmux::kill_session(&name) {
-            Ok(()) => {
-                self.refresh_sessions();
-                self.selected_action = SessionAction::Enter;
-            }
-            Err(e) => {
-                self.error_message = Some(e);
-            }
+        match loading.receiver.try_recv() {
+            Ok(result) => self.finish_loading(result),
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => self.finish_loading(self.list_sessions()),
         }
     }
 
-    fn create_and_attach_session(&mut self) {
-        let name = self.input_buffer.trim().to_string();
-        if name.is_empty() {
+    /// Block until the first session list is in, for startup options that
+    /// act on the sessions before the first frame
+    pub fn wait_for_sessions(&mut self) {
+        let Some(loading) = &self.loading else {
             return;
+        };
+        let result = loading
+            .receiver
+            .recv()
+            .unwrap_or_else(|_| self.list_sessions());
+        self.finish_loading(result);
+    }
+
+    fn finish_loading(&mut self, result: Result<Vec<TmuxSession>, TmuxError>) {
+        let Some(loading) = self.loading.take() else {
+            return;
+        };
+        self.set_sessions(result);
+        // Naming the binary says more than the list failing to run does
+        if let Ok(e) = loading.binary_check.try_recv() {
+            self.error_message = Some(e.to_string());
+        }
+        self.last_refresh = Instant::now();
+        self.update_matches();
+        self.restore_position(&loading.saved);
+        // The server has answered, so a control client won't wait on it
+        if self.config.control_mode {
+            self.start_watching();
         }
+    }
 
-        match tmux::create_session(&name) {
-            Ok(()) => {
-                self.action = AppAction::AttachSession(name);
+    /// Start hearing about changes from tmux; `refresh_if_due` falls back to
+    /// polling if that fails
+    fn start_watching(&mut self) {
+        self.watcher = Some(EventWatcher::start(&self.config.sockets));
+    }
+
+    /// The session name as shown in the list. tmux commands always get the
+    /// raw name.
+    pub fn display_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.display_names.get(name).map_or(name, String::as_str)
+    }
+
+    /// Where new sessions go: the first configured socket, or tmux's default
+    /// server
+    fn creation_socket(&self) -> Option<String> {
+        self.config.sockets.first().cloned()
+    }
+
+    /// Store the result of listing sessions. A missing server just means an
+    /// empty list; other failures pop up once rather than on every refresh.
+    fn set_sessions(&mut self, result: Result<Vec<TmuxSession>, TmuxError>) {
+        match result {
+            Ok(sessions) => {
+                self.display_names = display_names(sessions.iter().map(|s| s.name.as_str()));
+                self.zombies = zombie_sessions(&sessions);
+                self.sessions = sessions;
+                self.list_error = None;
             }
             Err(e) => {
-                self.error_message = Some(e);
-                self.state = AppState::SessionList;
-                self.input_buffer.clear();
+                if e != TmuxError::NoServer && self.list_error.as_ref() != Some(&e) {
+                    self.error_message = Some(e.to_string());
+                }
+                self.sessions.clear();
+                self.zombies.clear();
+                self.list_error = Some(e);
             }
         }
     }
 
-    fn handle_renaming_session_key(&mut self, key: KeyEvent) {
-        match key.code {
-            KeyCode::Esc => {
-                self.state = AppState::SessionList;
-                self.input_buffer.clear();
-                self.selected_action = SessionAction::Enter;
-            }
-            KeyCode::Enter => {
-                if !self.input_buffer.is_empty() {
-                    self.rename_current_session();
-                }
+    /// Periodic refresh driven by `tick`. Sessions created or killed outside
+    /// Ursa are marked for a moment, and the cursor stays on the same session.
+    fn auto_refresh(&mut self) {
+        let previous: HashSet<String> = self.sessions.iter().map(|s| s.name.clone()).collect();
+        let selected = self.selected_session().map(|s| s.name.clone());
+
+        self.refresh_sessions();
+
+        let until = Instant::now() + CHANGE_HIGHLIGHT_DURATION;
+        let current: HashSet<&str> = self.sessions.iter().map(|s| s.name.as_str()).collect();
+        for name in &current {
+            if !previous.contains(*name) {
+                self.appeared.insert(name.to_string(), until);
             }
-            KeyCode::Backspace => {
-                self.input_buffer.pop();
+        }
+        for name in previous {
+            if !current.contains(name.as_str()) {
+                self.vanished.push((name, until));
             }
-            KeyCode::Char(c) => {
-                // Only allow valid tmux session name characters
-                if c.is_alphanumeric() || c == '-' || c == '_' {
-                    self.input_buffer.push(c);
-                }
+        }
+
+        if let Some(name) = selected {
+            if let Some(pos) = self
+                .visible_sessions()
+                .iter()
+                .position(|&i| self.sessions[i].name == name)
+            {
+                self.selected_index = pos;
+            } else if !current.contains(name.as_str()) {
+                self.show_toast(
+                    format!("'{}' was closed elsewhere", name),
+                    VANISHED_TOAST_DURATION,
+                );
             }
-            _ => {}
         }
     }
 
-    fn rename_current_session(&mut self) {
-        let new_name = self.input_buffer.trim().to_string();
-        if new_name.is_empty() {
-            return;
+    /// Whether `name` is still there before acting on it. When it was closed
+    /// elsewhere since the list was read, say so, drop the prompt and show
+    /// the list as it is now.
+    fn recheck_exists(&mut self, socket: Option<&str>, name: &str) -> bool {
+        match self.backend.has_session(socket, name) {
+            Ok(false) => {
+                self.report_vanished(name);
+                false
+            }
+            // Let the operation itself report anything else
+            Ok(true) | Err(_) => true,
         }
+    }
 
-        // Extract original_name from the state
-        let original_name = if let AppState::RenamingSession { original_name } = &self.state {
-            original_name.clone()
+    fn report_vanished(&mut self, name: &str) {
+        self.show_toast(
+            format!("'{}' was closed elsewhere; nothing was changed", name),
+            VANISHED_TOAST_DURATION,
+        );
+        self.state = AppState::SessionList;
+        self.input.clear();
+        self.selected_action = SessionAction::Enter;
+        self.refresh_sessions();
+    }
+
+    /// The row actions in the configured order; an empty list means the default
+    pub fn actions(&self) -> &[SessionAction] {
+        if self.config.actions.is_empty() {
+            &DEFAULT_ACTIONS
         } else {
+            &self.config.actions
+        }
+    }
+
+    /// Select the next or previous configured action, stopping at the ends
+    /// unless `wrap_actions` is set
+    fn cycle_action(&mut self, forward: bool) {
+        let actions = self.actions();
+        let len = actions.len();
+        // Rows start on Enter even when it's hidden, so start over from there
+        let Some(pos) = actions.iter().position(|&a| a == self.selected_action) else {
+            self.selected_action = actions[0];
             return;
         };
+        let next = match (forward, self.config.wrap_actions) {
+            (true, true) => (pos + 1) % len,
+            (true, false) => (pos + 1).min(len - 1),
+            (false, true) => (pos + len - 1) % len,
+            (false, false) => pos.saturating_sub(1),
+        };
+        self.selected_action = actions[next];
+    }
 
-        match tmux::rename_session(&original_name, &new_name) {
-            Ok(()) => {
-                self.state = AppState::SessionList;
-                self.input_buffer.clear();
-                self.selected_action = SessionAction::Enter;
-                self.refresh_sessions();
+    /// Move to the card above, below or beside the current one. Up from the
+    /// top row reaches the title bar; down from a row without a card below
+    /// lands on the last card.
+    fn move_in_grid(&mut self, code: KeyCode) {
+        let columns = self.grid_columns.get().max(1);
+        let last = self.total_items() - 1;
+        let index = self.selected_index;
+        if self.focus_area == FocusArea::TitleBar {
+            if matches!(code, KeyCode::Down | KeyCode::Char('j')) {
+                self.focus_area = FocusArea::SessionList;
             }
-            Err(e) => {
-                self.error_message = Some(e);
-                self.state = AppState::SessionList;
-                self.input_buffer.clear();
-                self.selected_action = SessionAction::Enter;
+            return;
+        }
+
+        self.selected_index = match code {
+            KeyCode::Up | KeyCode::Char('k') if index < columns => {
+                self.focus_area = FocusArea::TitleBar;
+                index
+            }
+            KeyCode::Up | KeyCode::Char('k') => index - columns,
+            KeyCode::Down | KeyCode::Char('j') if index / columns < last / columns => {
+                (index + columns).min(last)
             }
+            KeyCode::Left | KeyCode::Char('h') => index.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('l') => (index + 1).min(last),
+            _ => index,
+        };
+        self.selected_action = SessionAction::Enter;
+    }
+
+    /// Ensure selected index is within bounds (max is the "Create new" row)
+    fn clamp_selection(&mut self) {
+        let max_index = self.visible_sessions().len(); // "Create new" is at this index
+        if self.selected_index > max_index {
+            self.selected_index = max_index;
         }
     }
+
+    /// Indices into `sessions` of the rows currently shown, in display order
+    pub fn visible_sessions(&self) -> Vec<usize> {
+        if self.is_filtering() {
+            // Matches already exclude sessions hidden by the other filters
+            self.matches.clone()
+        } else {
+            (0..self.sessions.len())
+                .filter(|&i| self.passes_filters(&self.sessions[i]))
+                .collect()
+        }
+    }
+
+    /// Visibility filters other than the search query
+    fn passes_filters(&self, session: &TmuxSession) -> bool {
+        self.tag_filter
+            .is_none_or(|tag| self.tags.get(&session.name) == Some(&tag))
+            && (self.show_hidden || !self.config.hides(&session.name))
+    }
+
+    /// The session under the cursor, or None when on the "Create new" row
+    pub fn selected_session(&self) -> Option<&TmuxSession> {
+        self.visible_sessions()
+            .get(self.selected_index)
+            .and_then(|&i| self.sessions.get(i))
+    }
+
+    /// Socket and name of the session under the cursor, for tmux commands
+    /// on it
+    fn selected_target(&self) -> Option<SessionTarget> {
+        self.selected_session()
+            .map(|s| (s.socket.clone(), s.name.clone()))
+    }
+
+    /// True when an active query hides non-matching sessions
+    pub fn is_filtering(&self) -> bool {
+        !self.search_query.is_empty() && self.search_style == SearchStyle::Filter
+    }
+
+    /// Total items = visible sessions + "Create new session" option (+ input row when creating)
+    pub fn total_items(&self) -> usize {
+        let base = self.visible_sessions().len() + 1;
+        if matches!(
+            self.state,
+            AppState::CreatingSession | AppState::AddingEnv { .. }
+        ) {
+            base + 1
+        } else {
+            base
+        }
+    }
+
+    /// Page of the list the cursor is on, counting from 0
+    pub fn page(&self) -> usize {
+        self.selected_index / self.page_size.get().max(1)
+    }
+
+    /// Pages the list takes up, the "Create new" row included
+    pub fn page_count(&self) -> usize {
+        self.total_items().div_ceil(self.page_size.get().max(1))
+    }
+
+    /// Show a toast for `duration`
+    pub fn show_toast(&mut self, message: impl Into<String>, duration: Duration) {
+        self.toast = Some(Toast {
+            message: message.into(),
+            expires_at: Instant::now() + duration,
+        });
+    }
+
+    /// How long the event loop may block waiting for input. Idle, this is the
+    /// configured poll interval; while something is animating it drops to a
+    /// short interval so timed updates land on schedule.
+    pub fn poll_timeout(&self) -> Duration {
+        let idle = self.config.poll_interval();
+        if matches!(self.state, AppState::RunningBatch { .. }) {
+            // One step per iteration, redrawing the progress in between
+            Duration::ZERO
+        } else if self.is_animating() {
+            idle.min(ANIMATION_INTERVAL)
+        } else if self.filter_dirty {
+            idle.min(FILTER_DEBOUNCE)
+        } else if self.tailing {
+            idle.min(TAIL_INTERVAL)
+        } else if self.help_pages.get() > 1 {
+            idle.min(HELP_PAGE_INTERVAL)
+        } else {
+            idle
+        }
+    }
+
+    fn is_animating(&self) -> bool {
+        self.toast.is_some() || self.loading.is_some() || self.pending_details.is_some()
+    }
+
+    /// Advance time-based state; called on every iteration of the event loop
+    pub fn tick(&mut self) {
+        self.poll_loading();
+        self.step_batch();
+        while let Ok(failure) = self.hook_failures.try_recv() {
+            self.show_toast(failure, HOOK_TOAST_DURATION);
+        }
+        let now = Instant::now();
+        self.appeared.retain(|_, until| now < *until);
+        if self
+            .pending_count
+            .is_some_and(|(_, at)| now.duration_since(at) >= COUNT_TIMEOUT)
+        {
+            self.pending_count = None;
+        }
+        self.vanished.retain(|(_, until)| now < *until);
+        if self.filter_dirty && now.duration_since(self.filter_changed_at) >= FILTER_DEBOUNCE {
+            self.flush_filter();
+        }
+        self.update_details();
+        self.update_tail();
+        self.turn_help_page(now);
+        if self
+            .config
+            .idle_quit_after()
+            .is_some_and(|after| now.duration_since(self.last_input) >= after)
+        {
+            log::info!("quitting after {}s without input", self.config.quit_after_idle_secs);
+            self.quit();
+        }
+
+        self.refresh_if_due(now);
+
+        if self
+            .toast
+            .as_ref()
+            .is_some_and(|toast| now >= toast.expires_at)
+        {
+            self.toast = None;
+            if let Some((socket, name)) = self.pending_attach.take() {
+                self.action = AppAction::AttachSession { name, socket };
+            }
+        }
+    }
+
+    /// Show the next page of key hints once the current one has been up for
+    /// `HELP_PAGE_INTERVAL`, wrapping back to the first
+    fn turn_help_page(&mut self, now: Instant) {
+        let pages = self.help_pages.get();
+        if pages <= 1 {
+            self.help_page = 0;
+            self.help_paged_at = now;
+        } else if now.duration_since(self.help_paged_at) >= HELP_PAGE_INTERVAL {
+            self.help_page = (self.help_page + 1) % pages;
+            self.help_paged_at = now;
+        }
+    }
+
+    /// Run the hook configured for `event` in the background. Hooks never
+    /// hold up or undo the operation they follow; one that can't start or
+    /// exits non-zero is reported in a toast.
+    pub fn run_hook(&mut self, event: HookEvent, session: &str) {
+        let hooks = &self.config.hooks;
+        let command = match event {
+            HookEvent::Attach => &hooks.on_attach,
+            HookEvent::Create => &hooks.on_create,
+            HookEvent::Delete => &hooks.on_delete,
+        };
+        let Some(command) = command.as_deref().filter(|cmd| !cmd.trim().is_empty()) else {
+            return;
+        };
+
+        // The word after the script becomes $0, so the session name is $1
+        let spawned = Command::new("sh")
+            .args(["-c", command, "ursa-hook", session])
+            .env("URSA_SESSION", session)
+            .env("URSA_EVENT", event.key())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match spawned {
+            Ok(mut child) => {
+                let sender = self.hook_sender.clone();
+                std::thread::spawn(move || {
+                    let failure = match child.wait() {
+                        Ok(status) if status.success() => return,
+                        Ok(status) => format!("{} hook failed ({})", event.key(), status),
+                        Err(e) => format!("{} hook failed: {}", event.key(), e),
+                    };
+                    let _ = sender.send(failure);
+                });
+            }
+            Err(e) => self.show_toast(
+                format!("{} hook could not start: {}", event.key(), e),
+                HOOK_TOAST_DURATION,
+            ),
+        }
+    }
+
+    /// Type the keys `macros` has for `name` into it, e.g. to start the
+    /// editor that's always open there. Sent just before a detached session
+    /// is attached to, or right after the client is switched to it.
+    pub fn send_attach_macro(&self, socket: Option<&str>, name: &str) -> Result<(), TmuxError> {
+        let Some(keys) = self.config.attach_macro(name) else {
+            return Ok(());
+        };
+        self.backend.send_keys(socket, name, keys)
+    }
+
+    /// Refresh when tmux reported a change, or every `auto_refresh_ms` while
+    /// there are no control clients to report them (which is also when
+    /// starting them is retried) or they're still starting. Only while
+    /// browsing so prompts don't shift under the user, and not while nobody
+    /// is looking.
+    fn refresh_if_due(&mut self, now: Instant) {
+        match self.watcher.as_mut().map(EventWatcher::changes) {
+            Some(Changes::Some) => self.changed_elsewhere = true,
+            Some(Changes::Lost) => {
+                // Often just the session it was attached to closing
+                self.changed_elsewhere = true;
+                self.start_watching();
+            }
+            Some(Changes::Unavailable) => self.watcher = None,
+            Some(Changes::None) | None => {}
+        }
+        if !self.can_auto_refresh() || !self.focused {
+            return;
+        }
+        if self.changed_elsewhere {
+            self.auto_refresh();
+        } else if !self.watcher.as_ref().is_some_and(EventWatcher::is_watching) {
+            if let Some(interval) = self.config.auto_refresh_interval() {
+                if now.duration_since(self.last_refresh) >= interval {
+                    self.auto_refresh();
+                    if self.config.control_mode && self.watcher.is_none() {
+                        self.start_watching();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Terminal focus changed: refresh right away when it comes back, so
+    /// changes made elsewhere show without pressing `r`
+    pub fn set_focused(&mut self, focused: bool) {
+        if !self.config.refresh_on_focus {
+            self.focused = true;
+            return;
+        }
+        let regained = focused && !self.focused;
+        self.focused = focused;
+        if regained && self.can_auto_refresh() {
+            self.auto_refresh();
+        }
+    }
+
+    fn can_auto_refresh(&self) -> bool {
+        self.state == AppState::SessionList && self.pending_attach.is_none()
+    }
+
+    /// Whether the detail pane is drawn: beside the list when it fits, else
+    /// in its place while it has the focus
+    pub fn details_shown(&self) -> bool {
+        self.detail_pane_fits.get() || self.focus_area == FocusArea::Detail
+    }
+
+    /// Load details for the selected session in the background when the
+    /// cursor has moved to a different one (or they were dropped by a
+    /// refresh), while the pane is shown. One load at a time, so holding j
+    /// doesn't queue one per session passed.
+    fn update_details(&mut self) {
+        self.poll_details();
+        if self.pending_details.is_some() || !self.details_shown() {
+            return;
+        }
+        let Some(target) = self.selected_target() else {
+            self.details = None;
+            return;
+        };
+        if self
+            .details
+            .as_ref()
+            .is_some_and(|(loaded, _)| *loaded == target)
+        {
+            return;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let backend = Arc::clone(&self.backend);
+        let (socket, name) = target.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(backend.session_details(socket.as_deref(), &name));
+        });
+        self.pending_details = Some((target, receiver));
+    }
+
+    /// Take the details read in the background once they're in
+    fn poll_details(&mut self) {
+        let Some((_, receiver)) = &self.pending_details else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(details) => {
+                if let Some((target, _)) = self.pending_details.take() {
+                    self.details = Some((target, details));
+                    self.detail_scroll.set(0);
+                }
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => self.pending_details = None,
+        }
+    }
+
+    /// Drop the details, including any being read, so the next tick reads
+    /// them afresh
+    fn reload_details(&mut self) {
+        self.details = None;
+        self.pending_details = None;
+    }
+
+    /// Turn the live preview on or off; off, no captures are taken at all
+    fn toggle_tail(&mut self) {
+        self.tailing = !self.tailing;
+        self.tail = None;
+        self.status_message = Some(if self.tailing {
+            "Live preview on".to_string()
+        } else {
+            "Live preview off".to_string()
+        });
+    }
+
+    /// Re-capture the selected session's active pane while tailing, right
+    /// away when the cursor moved and every `TAIL_INTERVAL` otherwise. An
+    /// unchanged capture is kept as is, so the screen stays untouched.
+    fn update_tail(&mut self) {
+        if !self.tailing {
+            return;
+        }
+        let Some(target) = self.selected_target() else {
+            self.tail = None;
+            return;
+        };
+        let moved = self
+            .tail
+            .as_ref()
+            .is_none_or(|(tailed, _)| *tailed != target);
+        if !moved && self.last_tail.elapsed() < TAIL_INTERVAL {
+            return;
+        }
+
+        self.last_tail = Instant::now();
+        let capture = self.backend.capture_pane(target.0.as_deref(), &target.1);
+        if moved || self.tail.as_ref().is_some_and(|(_, last)| *last != capture) {
+            self.tail = Some((target, capture));
+        }
+    }
+
+    /// Digits typed in the list build up a count for the next j/k. A leading
+    /// 0 isn't a count, and the grid has no use for one.
+    fn handle_count_key(&mut self, key: KeyEvent) -> bool {
+        let KeyCode::Char(c) = key.code else {
+            return false;
+        };
+        let Some(digit) = c.to_digit(10) else {
+            return false;
+        };
+        let count = self.pending_count.map(|(count, _)| count);
+        if self.layout_mode == LayoutMode::Grid || (digit == 0 && count.is_none()) {
+            return false;
+        }
+        let count = count.unwrap_or(0) * 10 + digit as usize;
+        self.pending_count = Some((count.min(MAX_COUNT), Instant::now()));
+        true
+    }
+
+    /// Keys handled while the detail pane has focus; returns false for keys
+    /// that should act on the list as usual (e.g. q, r, Enter)
+    fn handle_detail_key(&mut self, key: KeyEvent) -> bool {
+        let scroll = self.detail_scroll.get();
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.detail_scroll.set(scroll.saturating_sub(1)),
+            KeyCode::Down | KeyCode::Char('j') => self.detail_scroll.set(scroll.saturating_add(1)),
+            KeyCode::PageUp => self.detail_scroll.set(scroll.saturating_sub(10)),
+            KeyCode::PageDown => self.detail_scroll.set(scroll.saturating_add(10)),
+            // The same keys as tmux's own split bindings
+            KeyCode::Char('%') => self.split_selected(SplitDirection::Horizontal),
+            KeyCode::Char('"') => self.split_selected(SplitDirection::Vertical),
+            KeyCode::Char('S') => self.toggle_synchronize(),
+            KeyCode::Char(c) if c.is_ascii_digit() => {
+                self.attach_to_window(c.to_digit(10).unwrap_or(0));
+            }
+            KeyCode::Tab | KeyCode::Esc | KeyCode::Left | KeyCode::Char('h') => {
+                self.focus_area = FocusArea::SessionList;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Split the active pane of the selected session's current window
+    /// without attaching; the detail pane reloads to show the new pane
+    fn split_selected(&mut self, direction: SplitDirection) {
+        let Some((socket, name)) = self.selected_target() else {
+            return;
+        };
+        match self
+            .backend
+            .split_window(socket.as_deref(), &name, direction, None)
+        {
+            Ok(()) => {
+                self.status_message = Some(format!("Split the current window of '{}'", name));
+                self.log_action(format!("Split a pane in '{}'", name));
+                self.reload_details();
+            }
+            Err(e) => self.error_message = Some(e.to_string()),
+        }
+    }
+
+    /// Turn `synchronize-panes` on across the selected session's windows, or
+    /// off once every window has it, e.g. to type into all panes of a demo
+    fn toggle_synchronize(&mut self) {
+        let Some(target) = self.selected_target() else {
+            return;
+        };
+        // The current state comes from the details, so wait for them
+        let Some((_, Ok(details))) = self.details.as_ref().filter(|(shown, _)| *shown == target)
+        else {
+            self.status_message = Some("Details still loading; try again".to_string());
+            return;
+        };
+        let on = !details.windows.iter().all(|w| w.synchronized);
+        let (socket, name) = target;
+        match self
+            .backend
+            .set_synchronize_panes(socket.as_deref(), &name, on)
+        {
+            Ok(()) => {
+                let state = if on { "on" } else { "off" };
+                self.status_message = Some(format!("Synchronized panes {} in '{}'", state, name));
+                self.log_action(format!("Turned synchronize-panes {} in '{}'", state, name));
+                self.reload_details();
+            }
+            Err(e) => self.error_message = Some(e.to_string()),
+        }
+    }
+
+    /// Attach to the selected session on window `index`, the number shown
+    /// beside it in the detail pane
+    fn attach_to_window(&mut self, index: u32) {
+        let Some(target) = self.selected_target() else {
+            return;
+        };
+        let Some((_, Ok(details))) = self.details.as_ref().filter(|(shown, _)| *shown == target)
+        else {
+            self.status_message = Some("Details still loading; try again".to_string());
+            return;
+        };
+        // Typing 0 with `base-index 1` is the likely slip, so say where
+        // numbering starts
+        let (socket, name) = target;
+        if !details.windows.iter().any(|w| w.index == index) {
+            let first = details.windows.iter().map(|w| w.index).min().unwrap_or(0);
+            self.status_message = Some(format!(
+                "'{}' has no window {}; its windows are numbered from {}",
+                name, index, first
+            ));
+            return;
+        }
+        match self.backend.select_window(socket.as_deref(), &name, index) {
+            Ok(()) => self.attach_to(socket, name),
+            Err(e) => self.error_message = Some(e.to_string()),
+        }
+    }
+
+    /// The session with the newest `#{session_activity}`
+    pub fn most_recent_session(&self) -> Option<&TmuxSession> {
+        self.sessions
+            .iter()
+            .max_by_key(|session| session.last_activity)
+    }
+
+    /// Record an operation in the action log, dropping the oldest when full
+    fn log_action(&mut self, message: impl Into<String>) {
+        if self.action_log.len() == ACTION_LOG_CAPACITY {
+            self.action_log.pop_front();
+        }
+        self.action_log.push_back(LogEntry {
+            at: Instant::now(),
+            message: message.into(),
+        });
+    }
+
+    fn handle_viewing_log_key(&mut self, key: KeyEvent) {
+        if let KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('H') = key.code {
+            self.state = AppState::SessionList;
+        }
+    }
+
+    /// Close the first-run overlay for good by creating the config dir whose
+    /// absence showed it
+    fn dismiss_welcome(&mut self) {
+        self.state = AppState::SessionList;
+        if let Some(dir) = config_dir() {
+            if let Err(e) = std::fs::create_dir_all(&dir) {
+                self.error_message = Some(format!("Failed to create {}: {}", dir.display(), e));
+            }
+        }
+    }
+
+    /// Other sessions in the same group as `name`, which share its windows
+    pub fn group_peers(&self, name: &str) -> Vec<&str> {
+        let Some(group) = self
+            .sessions
+            .iter()
+            .find(|s| s.name == name)
+            .and_then(|s| s.group.as_ref())
+        else {
+            return Vec::new();
+        };
+        self.sessions
+            .iter()
+            .filter(|s| s.name != name && s.group.as_ref() == Some(group))
+            .map(|s| s.name.as_str())
+            .collect()
+    }
+
+    /// Attach to `name` on `socket`. Grouped sessions share windows, so
+    /// closing a window there closes it in the whole group; warn first,
+    /// cancellable with any key.
+    fn attach_to(&mut self, socket: Option<String>, name: String) {
+        let peers = self.group_peers(&name);
+        if peers.is_empty() {
+            self.action = AppAction::AttachSession { name, socket };
+            return;
+        }
+
+        self.show_toast(
+            format!(
+                "{} shares its windows with {}; attaching…",
+                name,
+                peers.join(", ")
+            ),
+            GROUP_WARNING_DURATION,
+        );
+        self.pending_attach = Some((socket, name));
+    }
+
+    /// Attach to the selected session with its active pane zoomed. The zoom is
+    /// tmux state, so it's applied here, before Ursa hands over to
+    /// switch-client or execs `tmux attach`, and holds either way.
+    fn attach_zoomed(&mut self) {
+        let Some((socket, name)) = self.selected_target() else {
+            return;
+        };
+        if let Err(e) = self.backend.zoom_active_pane(socket.as_deref(), &name) {
+            self.error_message = Some(e.to_string());
+            return;
+        }
+        self.attach_to(socket, name);
+    }
+
+    /// Attach to the selected session in a tmux popup over this client,
+    /// coming back to the list once it closes
+    fn attach_in_popup(&mut self) {
+        let Some((socket, name)) = self.selected_target() else {
+            return;
+        };
+        let socket = socket.as_deref();
+        if !self.backend.is_inside_tmux() {
+            self.status_message = Some("Popups need Ursa to run inside tmux".to_string());
+            return;
+        }
+        if !self.backend.supports_popup() {
+            self.status_message = Some("Popups need tmux 3.2 or newer".to_string());
+            return;
+        }
+
+        self.run_hook(HookEvent::Attach, &name);
+        if let Err(e) = self.send_attach_macro(socket, &name) {
+            self.error_message = Some(e.to_string());
+            return;
+        }
+        match self.backend.attach_in_popup(socket, &name) {
+            Ok(()) => {
+                self.status_message = Some(format!("Closed the popup for '{}'", name));
+                // Windows may have been opened or closed in the popup
+                self.refresh_sessions();
+            }
+            Err(e) => self.error_message = Some(e.to_string()),
+        }
+    }
+
+    /// Apply a key press to the current state
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        self.last_input = Instant::now();
+        // Clear error and status on any keypress
+        self.error_message = None;
+        self.status_message = None;
+
+        // Ctrl-C quits from every screen, even with text typed into a prompt.
+        // q and Esc are per state: q is text while typing, and Esc only
+        // cancels the prompt.
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.quit();
+            return;
+        }
+
+        // Any key cancels a pending "attach to last session"
+        if self.pending_attach.take().is_some() {
+            self.toast = None;
+            self.status_message = Some("Attach cancelled".to_string());
+            return;
+        }
+
+        // vi mode's Esc and normal-mode keys edit the prompt before it sees them
+        if self.config.input_mode == InputMode::Vi && self.state.takes_text() {
+            let used = if self.state == AppState::Searching {
+                self.edit_search(|query| query.handle_vi_key(key))
+            } else {
+                self.input.handle_vi_key(key)
+            };
+            if used {
+                return;
+            }
+        }
+
+        let before = self.state.clone();
+        match &self.state {
+            AppState::SessionList => self.handle_session_list_key(key),
+            AppState::CreatingSession => self.handle_creating_session_key(key),
+            AppState::AddingEnv { .. } => self.handle_adding_env_key(key),
+            AppState::GoingTo => self.handle_going_to_key(key),
+            AppState::NumberingSessions => self.handle_numbering_sessions_key(key),
+            AppState::RenamingSession { .. } => self.handle_renaming_session_key(key),
+            AppState::AffixingName { .. } => self.handle_affixing_name_key(key),
+            AppState::CreatingWindow { .. } => self.handle_creating_window_key(key),
+            AppState::SavingScrollback { .. } => self.handle_saving_scrollback_key(key),
+            AppState::EditingNote { .. } => self.handle_editing_note_key(key),
+            AppState::Searching => self.handle_searching_key(key),
+            AppState::ConfirmingBatch { .. } => self.handle_confirming_batch_key(key),
+            // Nothing to do but wait, or quit with Ctrl-C
+            AppState::RunningBatch { .. } => {}
+            AppState::ViewingLog => self.handle_viewing_log_key(key),
+            AppState::Welcome => self.dismiss_welcome(),
+            AppState::ConfirmingLocked { .. } => self.handle_confirming_locked_key(key),
+            AppState::ConfirmingAttachedRename { .. } => {
+                self.handle_confirming_attached_rename_key(key)
+            }
+            AppState::ConfirmingOwnDelete { .. } => self.handle_confirming_own_delete_key(key),
+        }
+        if self.state != before {
+            log::debug!("{:?} -> {:?}", before, self.state);
+            // Every prompt starts out typing
+            self.input.start_inserting();
+            // Other hints now, so they start from their first page
+            self.help_page = 0;
+            self.help_paged_at = Instant::now();
+        }
+        if let Some(error) = &self.error_message {
+            log::warn!("showing error: {}", error);
+        }
+    }
+
+    fn handle_session_list_key(&mut self, key: KeyEvent) {
+        if self.focus_area == FocusArea::Detail && self.handle_detail_key(key) {
+            return;
+        }
+        if self.handle_count_key(key) {
+            return;
+        }
+        // Any key other than a digit uses up the count; only j/k act on it
+        let count = self.pending_count.take().map_or(1, |(count, _)| count);
+
+        match key.code {
+            KeyCode::Char('q') => {
+                self.quit();
+            }
+            KeyCode::Esc if !self.search_query.is_empty() => {
+                // First Esc clears an active search, the next one quits
+                self.clear_search();
+            }
+            KeyCode::Esc => {
+                self.quit();
+            }
+            KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::Char('k' | 'j' | 'h' | 'l')
+                if self.layout_mode == LayoutMode::Grid =>
+            {
+                self.move_in_grid(key.code);
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                if self.focus_area == FocusArea::TitleBar {
+                    // Already at title bar, do nothing
+                } else if self.selected_index > 0 {
+                    self.selected_index = self.selected_index.saturating_sub(count);
+                    self.selected_action = SessionAction::Enter;
+                } else {
+                    // At top of list, move focus to title bar
+                    self.focus_area = FocusArea::TitleBar;
+                }
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                if self.focus_area == FocusArea::TitleBar {
+                    self.focus_area = FocusArea::SessionList;
+                } else if self.selected_index < self.total_items() - 1 {
+                    self.selected_index = (self.selected_index + count).min(self.total_items() - 1);
+                    self.selected_action = SessionAction::Enter;
+                }
+            }
+            // A count moves that many pages, keeping the row's place in its page
+            KeyCode::PageUp => {
+                let rows = count * self.page_size.get().max(1);
+                self.focus_area = FocusArea::SessionList;
+                self.selected_index = self.selected_index.saturating_sub(rows);
+                self.selected_action = SessionAction::Enter;
+            }
+            KeyCode::PageDown => {
+                let rows = count * self.page_size.get().max(1);
+                self.focus_area = FocusArea::SessionList;
+                self.selected_index = (self.selected_index + rows).min(self.total_items() - 1);
+                self.selected_action = SessionAction::Enter;
+            }
+            // Only allow action cycling for existing sessions (not "Create new")
+            KeyCode::Right | KeyCode::Char('l') if self.selected_session().is_some() => {
+                self.cycle_action(true);
+            }
+            KeyCode::Left | KeyCode::Char('h') if self.selected_session().is_some() => {
+                self.cycle_action(false);
+            }
+            KeyCode::Enter
+                if key.modifiers.contains(KeyModifiers::ALT)
+                    && self.focus_area == FocusArea::SessionList
+                    && self.selected_session().is_some() =>
+            {
+                self.attach_zoomed();
+            }
+            KeyCode::Enter => {
+                if self.focus_area == FocusArea::TitleBar {
+                    self.refresh_sessions();
+                    self.focus_area = FocusArea::SessionList;
+                } else {
+                    self.select_current();
+                }
+            }
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.reload_config();
+            }
+            KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.cycle_color_scheme();
+            }
+            KeyCode::Char('R') => {
+                self.reload_config();
+            }
+            KeyCode::Char('E') => {
+                self.edit_config();
+            }
+            KeyCode::Char('r') => {
+                self.refresh_sessions();
+            }
+            KeyCode::Tab if self.selected_session().is_some() => {
+                self.focus_area = FocusArea::Detail;
+            }
+            KeyCode::Char('C') => {
+                self.start_cleanup(CleanupKind::Empty);
+            }
+            KeyCode::Char('K') => {
+                self.start_cleanup(CleanupKind::Detached);
+            }
+            KeyCode::Char('Z') => {
+                self.start_cleanup(CleanupKind::Zombie);
+            }
+            KeyCode::Char('I') => {
+                self.start_cleanup(CleanupKind::Idle);
+            }
+            KeyCode::Char('H') => {
+                self.state = AppState::ViewingLog;
+            }
+            KeyCode::Char('w') => {
+                if let Some((socket, name)) = self.selected_target() {
+                    self.state = AppState::CreatingWindow {
+                        session_name: name,
+                        socket,
+                    };
+                    self.input.clear();
+                }
+            }
+            KeyCode::Char('o') => {
+                self.open_in_terminal();
+            }
+            KeyCode::Char('y') => self.copy_attach_command(),
+            KeyCode::Char('p') => {
+                self.attach_in_popup();
+            }
+            KeyCode::Char('v') => {
+                self.page_scrollback();
+            }
+            KeyCode::Char('f') => {
+                self.toggle_tail();
+            }
+            KeyCode::Char('#') => {
+                self.relative_numbers = !self.relative_numbers;
+            }
+            KeyCode::Char('>') => {
+                if let Some((socket, name)) = self.selected_target() {
+                    self.input.set(format!("{}-scrollback.txt", name));
+                    self.state = AppState::SavingScrollback {
+                        session_name: name,
+                        socket,
+                    };
+                }
+            }
+            KeyCode::Char('t') => {
+                self.cycle_tag();
+            }
+            KeyCode::Char('P') => {
+                self.toggle_lock();
+            }
+            KeyCode::Char('s') => {
+                self.save_snapshot();
+            }
+            KeyCode::Char('[') => {
+                self.start_affix(true);
+            }
+            KeyCode::Char(']') => {
+                self.start_affix(false);
+            }
+            KeyCode::Char('a') => {
+                self.attach_restoring_layout();
+            }
+            KeyCode::Char('.') => {
+                self.toggle_hidden();
+            }
+            KeyCode::Char('T') => {
+                self.cycle_tag_filter();
+            }
+            KeyCode::Char('L') => {
+                if let Some(session) = self.most_recent_session() {
+                    let target = (session.socket.clone(), session.name.clone());
+                    self.show_toast(format!("Attaching to {}…", target.1), ATTACH_TOAST_DURATION);
+                    self.pending_attach = Some(target);
+                } else {
+                    self.status_message = Some("No sessions to attach to".to_string());
+                }
+            }
+            KeyCode::Char('D') => {
+                self.dry_run = !self.dry_run;
+                self.status_message = Some(if self.dry_run {
+                    "Dry run enabled: delete/rename will not be executed".to_string()
+                } else {
+                    "Dry run disabled".to_string()
+                });
+            }
+            KeyCode::Char('V') => {
+                self.layout_mode = match self.layout_mode {
+                    LayoutMode::List => LayoutMode::Grid,
+                    LayoutMode::Grid => LayoutMode::List,
+                };
+                // Grid cards have no action buttons; Enter attaches
+                self.selected_action = SessionAction::Enter;
+            }
+            KeyCode::Char('g') => {
+                self.state = AppState::GoingTo;
+                self.input.clear();
+            }
+            KeyCode::Char('b') => self.start_creating_for_branch(),
+            KeyCode::Char('=') => {
+                self.state = AppState::NumberingSessions;
+                self.input.clear();
+            }
+            KeyCode::Char('/') => {
+                self.state = AppState::Searching;
+                self.focus_area = FocusArea::SessionList;
+                self.search_history.reset();
+                self.search_query.start_inserting();
+            }
+            KeyCode::Char('n') if !self.matches.is_empty() => {
+                self.cycle_match(true);
+            }
+            KeyCode::Char('N') if !self.matches.is_empty() => {
+                self.cycle_match(false);
+            }
+            // n belongs to search while there's a query, otherwise it edits the note
+            KeyCode::Char('n') if self.search_query.is_empty() => {
+                if let Some(name) = self.selected_session().map(|s| s.name.clone()) {
+                    self.input
+                        .set(self.notes.get(&name).cloned().unwrap_or_default());
+                    self.state = AppState::EditingNote { session_name: name };
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_creating_session_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.state = AppState::SessionList;
+                self.input.clear();
+            }
+            KeyCode::Enter => self.confirm_create(),
+            // → accepts the suggestion only at the end; elsewhere it moves the cursor
+            KeyCode::Tab => self.accept_suggestion(),
+            KeyCode::Right if self.input.at_end() => self.accept_suggestion(),
+            KeyCode::Up => {
+                if let Some(name) = self.create_history.older(self.input.as_str()) {
+                    self.input.set(name);
+                }
+            }
+            KeyCode::Down => {
+                if let Some(name) = self.create_history.newer() {
+                    self.input.set(name);
+                }
+            }
+            KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.layout_index = (self.layout_index + 1) % self.config.layouts.len().max(1);
+            }
+            KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let session_name = self.input.as_str().to_string();
+                self.state = AppState::AddingEnv { session_name };
+                self.input.clear();
+            }
+            // Path characters too, so a directory can be typed or pasted
+            _ => {
+                self.input.handle_key(key, |c| {
+                    is_session_name_char(c) || matches!(c, '/' | '~' | '.')
+                });
+            }
+        }
+    }
+
+    /// The directory named in the create prompt, if the text is a path to
+    /// one, along with the session name derived from its last component.
+    /// A leading `~` stands for `$HOME`.
+    pub fn dropped_directory(&self) -> Option<(String, PathBuf)> {
+        let text = self.input.as_str().trim();
+        // Local paths mean nothing to a remote server
+        if tmux::remote().is_some() || (!text.contains('/') && text != "~") {
+            return None;
+        }
+        let path = match text.strip_prefix('~') {
+            Some(rest) => {
+                PathBuf::from(std::env::var_os("HOME")?).join(rest.trim_start_matches('/'))
+            }
+            None => PathBuf::from(text),
+        };
+        if !path.is_dir() {
+            return None;
+        }
+        // "." and ".." have no name of their own
+        let dir = path.canonicalize().ok()?;
+        let name = sanitize_name(&dir.file_name()?.to_string_lossy());
+        Some((name, dir))
+    }
+
+    /// Keys for the environment entry prompt; Enter adds the entry and Esc
+    /// drops it, both returning to the create prompt
+    fn handle_adding_env_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => self.back_to_create(),
+            KeyCode::Enter => {
+                let entry = self.input.as_str().trim().to_string();
+                if entry.is_empty() {
+                    self.back_to_create();
+                    return;
+                }
+                if let Err(e) = tmux::validate_env(&entry) {
+                    self.status_message = Some(e);
+                    return;
+                }
+                // A key given again replaces its earlier value
+                let key = entry.split_once('=').map_or("", |(key, _)| key);
+                self.create_env
+                    .retain(|existing| !existing.starts_with(&format!("{}=", key)));
+                self.create_env.push(entry);
+                self.back_to_create();
+            }
+            _ => {
+                self.input.handle_key(key, |c| !c.is_control());
+            }
+        }
+    }
+
+    /// Leave the environment prompt with the session name typed before it
+    fn back_to_create(&mut self) {
+        if let AppState::AddingEnv { session_name } =
+            std::mem::replace(&mut self.state, AppState::CreatingSession)
+        {
+            self.input.set(session_name);
+        }
+    }
+
+    /// Whether the name typed into the create, rename or go-to prompt would be
+    /// accepted, or None outside those prompts. A rename may keep its own name.
+    pub fn validate_input(&self) -> Option<Result<Option<String>, String>> {
+        let own_name = match &self.state {
+            // Existing names are the point of "go to"
+            AppState::GoingTo => {
+                return Some(tmux::validate_name(self.input.as_str().trim(), []));
+            }
+            // A pasted directory is named after its last component
+            AppState::CreatingSession => {
+                if let Some((name, _)) = self.dropped_directory() {
+                    let existing = self.sessions.iter().map(|s| s.name.as_str());
+                    return Some(tmux::validate_name(&name, existing));
+                }
+                None
+            }
+            AppState::AddingEnv { .. } => {
+                let entry = self.input.as_str().trim();
+                return Some(tmux::validate_env(entry).map(|()| None));
+            }
+            // Clashes with sessions outside the series are checked on confirming
+            AppState::NumberingSessions => {
+                let prefix = self.input.as_str().trim();
+                if prefix.is_empty() {
+                    return Some(Err("Type a prefix to number the sessions under".to_string()));
+                }
+                return Some(tmux::validate_name(&format!("{}-1", prefix), []));
+            }
+            AppState::RenamingSession { original_name, .. } => Some(original_name.as_str()),
+            AppState::AffixingName {
+                original_name,
+                prepend,
+                ..
+            } => {
+                if self.input.as_str().trim().is_empty() {
+                    let what = if *prepend { "prefix" } else { "suffix" };
+                    return Some(Err(format!("Type a {} to add", what)));
+                }
+                let name = self.affixed_name()?;
+                let existing = self
+                    .sessions
+                    .iter()
+                    .map(|s| s.name.as_str())
+                    .filter(|&name| name != original_name);
+                return Some(tmux::validate_name(&name, existing).map(|warning| {
+                    // The quick prompt skips the attached-rename popup, so
+                    // it warns here instead
+                    warning.or_else(|| {
+                        self.needs_rename_confirmation(original_name).then(|| {
+                            format!(
+                                "'{}' is attached; its clients will see the new name",
+                                original_name
+                            )
+                        })
+                    })
+                }));
+            }
+            _ => return None,
+        };
+        let existing = self
+            .sessions
+            .iter()
+            .map(|s| s.name.as_str())
+            .filter(|&name| Some(name) != own_name);
+        Some(tmux::validate_name(self.input.as_str().trim(), existing))
+    }
+
+    fn handle_going_to_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.state = AppState::SessionList;
+                self.input.clear();
+            }
+            KeyCode::Enter => {
+                if self.name_accepted() {
+                    self.go_to_session();
+                }
+            }
+            _ => {
+                self.input.handle_key(key, is_session_name_char);
+            }
+        }
+    }
+
+    fn handle_numbering_sessions_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.state = AppState::SessionList;
+                self.input.clear();
+            }
+            KeyCode::Enter => {
+                if self.name_accepted() {
+                    self.start_renumber();
+                }
+            }
+            _ => {
+                self.input.handle_key(key, is_session_name_char);
+            }
+        }
+    }
+
+    /// Preview renaming the listed sessions under the typed prefix, in the
+    /// order they're shown. Locked sessions keep their names.
+    fn start_renumber(&mut self) {
+        let prefix = self.input.as_str().trim().to_string();
+        self.input.clear();
+        let items: Vec<BatchItem> = self
+            .visible_sessions()
+            .into_iter()
+            .map(|index| &self.sessions[index])
+            .filter(|session| !self.is_locked(&session.name))
+            .map(BatchItem::new)
+            .collect();
+        if items.is_empty() {
+            self.state = AppState::SessionList;
+            self.status_message = Some("No unlocked sessions to renumber".to_string());
+        } else {
+            self.start_batch(BatchOperation::Renumber { prefix }, items);
+        }
+    }
+
+    /// Attach to the session named in the go-to prompt, creating it in the
+    /// current directory first when there's none by that name (in the remote
+    /// server's default one over SSH)
+    fn go_to_session(&mut self) {
+        let name = self.input.as_str().trim().to_string();
+        if name.is_empty() {
+            return;
+        }
+        self.state = AppState::SessionList;
+        self.input.clear();
+
+        let socket = match self.sessions.iter().find(|s| s.name == name) {
+            Some(session) => session.socket.clone(),
+            None => self.creation_socket(),
+        };
+        if !self.sessions.iter().any(|s| s.name == name) {
+            if self.refuse_over_limit() {
+                return;
+            }
+            // Local paths mean nothing to a remote server
+            let start_dir = std::env::current_dir()
+                .ok()
+                .filter(|_| tmux::remote().is_none())
+                .map(|dir| dir.to_string_lossy().into_owned());
+            let result =
+                self.backend
+                    .create_if_missing(socket.as_deref(), &name, start_dir.as_deref());
+            match result {
+                Ok(created) => {
+                    if created {
+                        self.log_action(format!("Created '{}'", name));
+                        self.run_hook(HookEvent::Create, &name);
+                    }
+                    self.refresh_sessions();
+                }
+                Err(e) => {
+                    self.error_message = Some(e.to_string());
+                    return;
+                }
+            }
+        }
+        self.action = AppAction::AttachSession { name, socket };
+    }
+
+    fn accept_suggestion(&mut self) {
+        if let Some(suggestion) = self.suggest(self.input.as_str()) {
+            self.input.set(suggestion);
+        }
+    }
+
+    fn handle_creating_window_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.state = AppState::SessionList;
+                self.input.clear();
+            }
+            KeyCode::Enter => {
+                self.create_window();
+            }
+            // Window names are freeform, unlike session names
+            _ => {
+                self.input.handle_key(key, |c| !c.is_control());
+            }
+        }
+    }
+
+    pub fn is_locked(&self, name: &str) -> bool {
+        self.locks.get(name).copied().unwrap_or(false)
+    }
+
+    /// Whether `name` was started in a directory that has since been deleted
+    pub fn is_zombie(&self, name: &str) -> bool {
+        self.zombies.contains(name)
+    }
+
+    /// Whether `name` is one of the config's hidden sessions, listed because
+    /// `.` shows them
+    pub fn is_shown_hidden(&self, name: &str) -> bool {
+        self.show_hidden && self.config.hides(name)
+    }
+
+    /// Whether `name` has gone without activity for longer than the
+    /// configured `idle_hours`
+    pub fn is_long_idle(&self, name: &str) -> bool {
+        let Some(threshold) = self.config.idle_threshold() else {
+            return false;
+        };
+        self.sessions
+            .iter()
+            .find(|s| s.name == name)
+            .and_then(idle_time)
+            .is_some_and(|idle| idle > threshold)
+    }
+
+    /// Remember the layout of the selected session's current window, to be
+    /// restored when attaching with `a`
+    fn save_snapshot(&mut self) {
+        let Some((socket, name)) = self.selected_target() else {
+            return;
+        };
+        let layout = match self.backend.window_layout(socket.as_deref(), &name) {
+            Ok(layout) => layout,
+            Err(e) => {
+                self.error_message = Some(e.to_string());
+                return;
+            }
+        };
+        let message = format!(
+            "Saved the layout of '{}' ({} pane{})",
+            name,
+            layout.panes,
+            if layout.panes == 1 { "" } else { "s" }
+        );
+        if let Err(e) = self.snapshots.set(&name, Some(layout)) {
+            self.error_message = Some(e);
+            return;
+        }
+        self.status_message = Some(message.clone());
+        self.log_action(message);
+    }
+
+    /// Attach to the selected session after putting back its saved layout,
+    /// if it has one
+    fn attach_restoring_layout(&mut self) {
+        let Some((socket, name)) = self.selected_target() else {
+            return;
+        };
+        if let Some(saved) = self.snapshots.get(&name).cloned() {
+            if let Err(e) = self
+                .backend
+                .restore_layout(socket.as_deref(), &name, &saved)
+            {
+                self.error_message = Some(e.to_string());
+                return;
+            }
+        }
+        self.attach_to(socket, name);
+    }
+
+    fn toggle_lock(&mut self) {
+        let Some(name) = self.selected_session().map(|s| s.name.clone()) else {
+            return;
+        };
+        let locked = !self.is_locked(&name);
+        if let Err(e) = self.locks.set(&name, locked.then_some(true)) {
+            self.error_message = Some(e);
+            return;
+        }
+        let message = if locked {
+            format!("Locked '{}'", name)
+        } else {
+            format!("Unlocked '{}'", name)
+        };
+        self.status_message = Some(message.clone());
+        self.log_action(message);
+    }
+
+    /// Run the rename or delete that was held back until the locked
+    /// session's name was typed
+    fn handle_confirming_locked_key(&mut self, key: KeyEvent) {
+        let AppState::ConfirmingLocked {
+            name,
+            socket,
+            action,
+        } = &self.state
+        else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.state = AppState::SessionList;
+                self.input.clear();
+                self.selected_action = SessionAction::Enter;
+            }
+            KeyCode::Enter if self.input.as_str() == name => {
+                let (name, socket, action) = (name.clone(), socket.clone(), *action);
+                self.state = AppState::SessionList;
+                self.input.clear();
+                match action {
+                    SessionAction::Rename if self.needs_rename_confirmation(&name) => {
+                        self.state = AppState::ConfirmingAttachedRename { name, socket };
+                    }
+                    SessionAction::Rename => self.start_rename(socket, name),
+                    SessionAction::Delete => self.delete_current_session(),
+                    SessionAction::Enter => {}
+                }
+            }
+            KeyCode::Enter => {
+                self.status_message = Some(format!("Type '{}' exactly to confirm", name));
+            }
+            _ => {
+                self.input.handle_key(key, |c| !c.is_control());
+            }
+        }
+    }
+
+    /// Whether renaming `name` should be confirmed first: only attached
+    /// sessions, and only when `confirm_attached_rename` is set
+    fn needs_rename_confirmation(&self, name: &str) -> bool {
+        self.config.confirm_attached_rename
+            && self.sessions.iter().any(|s| s.name == name && s.attached)
+    }
+
+    fn handle_confirming_attached_rename_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter | KeyCode::Char('y') => {
+                if let AppState::ConfirmingAttachedRename { name, socket } =
+                    std::mem::replace(&mut self.state, AppState::SessionList)
+                {
+                    self.start_rename(socket, name);
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => {
+                self.state = AppState::SessionList;
+                self.selected_action = SessionAction::Enter;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_confirming_own_delete_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter | KeyCode::Char('y') => {
+                self.state = AppState::SessionList;
+                self.kill_current_session();
+            }
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => {
+                self.state = AppState::SessionList;
+                self.selected_action = SessionAction::Enter;
+            }
+            _ => {}
+        }
+    }
+
+    fn cycle_tag(&mut self) {
+        let Some(name) = self.selected_session().map(|s| s.name.clone()) else {
+            return;
+        };
+        let tag = Tag::cycle(self.tags.get(&name).copied());
+        if let Err(e) = self.tags.set(&name, tag) {
+            self.error_message = Some(e);
+        }
+        // The session may no longer pass an active tag filter
+        self.update_matches();
+        self.clamp_selection();
+    }
+
+    /// Switch to the next built-in color scheme, kept for the next run
+    fn cycle_color_scheme(&mut self) {
+        if !self.theme.color {
+            self.status_message = Some("Colors are off (--no-color or NO_COLOR)".to_string());
+            return;
+        }
+        self.theme.scheme = self.theme.scheme.next();
+        self.show_toast(
+            format!("Theme: {}", self.theme.scheme.name()),
+            THEME_TOAST_DURATION,
+        );
+    }
+
+    fn cycle_tag_filter(&mut self) {
+        let selected = self.visible_sessions().get(self.selected_index).copied();
+        self.tag_filter = Tag::cycle(self.tag_filter);
+        self.update_matches();
+        self.selected_action = SessionAction::Enter;
+
+        // Stay on the same session if it's still shown, otherwise start at the top
+        let visible = self.visible_sessions();
+        self.selected_index = selected
+            .and_then(|index| visible.iter().position(|&i| i == index))
+            .unwrap_or(0);
+    }
+
+    /// Show or hide again the sessions the config hides, for this run only
+    fn toggle_hidden(&mut self) {
+        let selected = self.visible_sessions().get(self.selected_index).copied();
+        self.show_hidden = !self.show_hidden;
+        self.update_matches();
+        self.selected_action = SessionAction::Enter;
+
+        let hidden = self
+            .sessions
+            .iter()
+            .filter(|s| self.config.hides(&s.name))
+            .count();
+        let plural = if hidden == 1 { "" } else { "s" };
+        self.status_message = Some(if self.show_hidden {
+            format!("Showing {} hidden session{}", hidden, plural)
+        } else {
+            format!("Hiding {} session{} again", hidden, plural)
+        });
+
+        // Stay on the same session if it's still shown
+        let visible = self.visible_sessions();
+        match selected.and_then(|index| visible.iter().position(|&i| i == index)) {
+            Some(row) => self.selected_index = row,
+            None => self.clamp_selection(),
+        }
+    }
+
+    fn create_window(&mut self) {
+        let AppState::CreatingWindow {
+            session_name,
+            socket,
+        } = std::mem::replace(&mut self.state, AppState::SessionList)
+        else {
+            return;
+        };
+        let window_name = self.input.as_str().trim().to_string();
+        self.input.clear();
+
+        let name = (!window_name.is_empty()).then_some(window_name.as_str());
+        match self
+            .backend
+            .new_window(socket.as_deref(), &session_name, name, None)
+        {
+            Ok(()) => {
+                self.refresh_sessions();
+                self.status_message = Some(format!("Added a window to '{}'", session_name));
+                self.log_action(format!("Added a window to '{}'", session_name));
+            }
+            Err(e) => {
+                self.error_message = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Switch this tmux client to `name` on `socket` and keep the list
+    /// running, for `stay_open`; the list is where the user left it when they
+    /// come back
+    pub fn switch_in_place(&mut self, socket: Option<&str>, name: &str) {
+        self.action = AppAction::None;
+        match self.backend.switch_client(socket, name) {
+            Ok(()) => {
+                self.run_hook(HookEvent::Attach, name);
+                if let Err(e) = self.send_attach_macro(socket, name) {
+                    self.error_message = Some(e.to_string());
+                }
+                self.switched = true;
+                self.status_message = Some(format!("Switched to '{}'", name));
+                self.refresh_sessions();
+            }
+            Err(e) => {
+                self.error_message = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Capture the selected session's scrollback to a temporary file and ask
+    /// the caller to page it
+    fn page_scrollback(&mut self) {
+        let Some((socket, name)) = self.selected_target() else {
+            return;
+        };
+        let path = std::env::temp_dir().join(format!(
+            "ursa-{}-{}.txt",
+            sanitize_name(&name),
+            std::process::id()
+        ));
+        match self
+            .backend
+            .capture_scrollback(socket.as_deref(), &name, &path)
+        {
+            Ok(()) => self.action = AppAction::OpenPager(path),
+            Err(e) => {
+                let _ = std::fs::remove_file(&path);
+                self.error_message = Some(e.to_string());
+            }
+        }
+    }
+
+    fn handle_saving_scrollback_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.state = AppState::SessionList;
+                self.input.clear();
+            }
+            KeyCode::Enter => {
+                self.save_scrollback();
+            }
+            _ => {
+                self.input.handle_key(key, |c| !c.is_control());
+            }
+        }
+    }
+
+    fn save_scrollback(&mut self) {
+        let path = self.input.as_str().trim();
+        if path.is_empty() {
+            return;
+        }
+        let path = PathBuf::from(path);
+        let AppState::SavingScrollback {
+            session_name,
+            socket,
+        } = std::mem::replace(&mut self.state, AppState::SessionList)
+        else {
+            return;
+        };
+        self.input.clear();
+
+        match self
+            .backend
+            .capture_scrollback(socket.as_deref(), &session_name, &path)
+        {
+            Ok(()) => {
+                self.status_message = Some(format!(
+                    "Saved the scrollback of '{}' to {}",
+                    session_name,
+                    path.display()
+                ));
+            }
+            Err(e) => {
+                self.error_message = Some(e.to_string());
+            }
+        }
+    }
+
+    fn handle_editing_note_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.state = AppState::SessionList;
+                self.input.clear();
+            }
+            KeyCode::Enter => {
+                self.save_note();
+            }
+            _ => {
+                self.input.handle_key(key, |c| !c.is_control());
+            }
+        }
+    }
+
+    /// Store the typed note; an empty one removes it
+    fn save_note(&mut self) {
+        let AppState::EditingNote { session_name } =
+            std::mem::replace(&mut self.state, AppState::SessionList)
+        else {
+            return;
+        };
+        let note = self.input.as_str().trim().to_string();
+        self.input.clear();
+
+        let message = if note.is_empty() {
+            format!("Cleared the note on '{}'", session_name)
+        } else {
+            format!("Saved the note on '{}'", session_name)
+        };
+        match self
+            .notes
+            .set(&session_name, (!note.is_empty()).then_some(note))
+        {
+            Ok(()) => self.status_message = Some(message),
+            Err(e) => self.error_message = Some(e),
+        }
+    }
+
+    /// Copy the command that attaches to the selected session, with its
+    /// socket (and SSH destination) as Ursa itself would attach
+    fn copy_attach_command(&mut self) {
+        let Some((socket, name)) = self.selected_target() else {
+            return;
+        };
+        let command = tmux::attach_command(socket.as_deref(), &name);
+        match clipboard::copy(&command, self.config.clipboard.as_deref()) {
+            Ok(()) => self.show_toast(format!("Copied: {}", command), COPY_TOAST_DURATION),
+            Err(e) => self.error_message = Some(e),
+        }
+    }
+
+    /// Attach to the selected session from a new terminal window
+    fn open_in_terminal(&mut self) {
+        let Some((socket, name)) = self.selected_target() else {
+            return;
+        };
+        let Some(terminal) = self.config.terminal_command() else {
+            self.error_message = Some(
+                "No terminal configured: set `terminal` in config.toml or the TERMINAL environment variable"
+                    .to_string(),
+            );
+            return;
+        };
+
+        match self
+            .backend
+            .open_in_terminal(&terminal, socket.as_deref(), &name)
+        {
+            Ok(()) => {
+                self.status_message = Some(format!("Opened '{}' in a new terminal", name));
+            }
+            Err(e) => {
+                self.error_message = Some(e);
+            }
+        }
+    }
+
+    fn handle_searching_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.state = AppState::SessionList;
+                self.clear_search();
+            }
+            KeyCode::Enter => {
+                // Keep the query active so n/N can cycle through matches
+                self.state = AppState::SessionList;
+                self.search_history.push(self.search_query.as_str());
+                self.flush_filter();
+            }
+            KeyCode::Up => {
+                if let Some(query) = self.search_history.older(self.search_query.as_str()) {
+                    self.search_query.set(query);
+                    self.filter_dirty = true;
+                    self.flush_filter();
+                }
+            }
+            KeyCode::Down => {
+                if let Some(query) = self.search_history.newer() {
+                    self.search_query.set(query);
+                    self.filter_dirty = true;
+                    self.flush_filter();
+                }
+            }
+            KeyCode::Tab => {
+                self.flush_filter();
+                self.search_style = match self.search_style {
+                    SearchStyle::Filter => SearchStyle::Jump,
+                    SearchStyle::Jump => SearchStyle::Filter,
+                };
+                self.jump_to_match();
+            }
+            _ => {
+                self.edit_search(|query| query.handle_key(key, |c| !c.is_control()));
+            }
+        }
+    }
+
+    /// Apply `edit` to the query, returning whether it used the key. Only
+    /// text changes bring new matches; cursor moves leave them be.
+    fn edit_search(&mut self, edit: impl FnOnce(&mut TextInput) -> bool) -> bool {
+        let before = self.search_query.as_str().to_string();
+        let used = edit(&mut self.search_query);
+        if self.search_query.as_str() != before {
+            self.mark_filter_dirty();
+            // There are no earlier matches to keep showing meanwhile
+            if before.is_empty() {
+                self.flush_filter();
+            }
+        }
+        used
+    }
+
+    /// Note a query edit; `tick` recomputes the matches once typing pauses
+    fn mark_filter_dirty(&mut self) {
+        self.filter_dirty = true;
+        self.filter_changed_at = Instant::now();
+    }
+
+    /// Recompute matches for a query edited since the last time, and move
+    /// the cursor to the first one
+    fn flush_filter(&mut self) {
+        if !self.filter_dirty {
+            return;
+        }
+        self.filter_dirty = false;
+        self.update_matches();
+        self.jump_to_match();
+    }
+
+    /// Sessions matching the query, best first; shorter names win ties, so
+    /// an exact match beats a longer name it prefixes
+    fn update_matches(&mut self) {
+        self.matches = if self.search_query.is_empty() {
+            Vec::new()
+        } else {
+            let mut ranked: Vec<(i32, usize)> = self
+                .sessions
+                .iter()
+                .enumerate()
+                .filter(|(_, session)| self.passes_filters(session))
+                .filter_map(|(i, session)| {
+                    fuzzy::score(self.search_query.as_str(), &session.name).map(|score| (score, i))
+                })
+                .collect();
+            ranked.sort_by_key(|&(score, i)| {
+                (std::cmp::Reverse(score), self.sessions[i].name.chars().count())
+            });
+            ranked.into_iter().map(|(_, i)| i).collect()
+        };
+        if self.match_index >= self.matches.len() {
+            self.match_index = 0;
+        }
+    }
+
+    fn clear_search(&mut self) {
+        // Keep the cursor on the same session once hidden rows reappear, or
+        // on the "Create new" row when it was there
+        let selected = self.visible_sessions().get(self.selected_index).copied();
+        self.search_query.clear();
+        self.filter_dirty = false;
+        self.matches.clear();
+        self.match_index = 0;
+        let visible = self.visible_sessions();
+        self.selected_index = match selected {
+            Some(index) => visible.iter().position(|&i| i == index).unwrap_or(0),
+            None => visible.len(),
+        };
+    }
+
+    /// Move the cursor to the first match after the query changed
+    fn jump_to_match(&mut self) {
+        self.match_index = 0;
+        self.select_match();
+    }
+
+    fn cycle_match(&mut self, forward: bool) {
+        let len = self.matches.len();
+        self.match_index = if forward {
+            (self.match_index + 1) % len
+        } else {
+            (self.match_index + len - 1) % len
+        };
+        self.select_match();
+    }
+
+    fn select_match(&mut self) {
+        self.selected_action = SessionAction::Enter;
+        let Some(&session_index) = self.matches.get(self.match_index) else {
+            self.selected_index = 0;
+            return;
+        };
+        self.selected_index = self
+            .visible_sessions()
+            .iter()
+            .position(|&i| i == session_index)
+            .unwrap_or(0);
+    }
+
+    fn select_current(&mut self) {
+        if self.selected_index == self.visible_sessions().len() {
+            // "Create new session" selected
+            self.start_creating(None);
+        } else if let Some((socket, name)) = self.selected_target() {
+            // Grid cards draw no buttons, so Enter there only ever attaches
+            let action = match self.layout_mode {
+                LayoutMode::List => self.selected_action,
+                LayoutMode::Grid => SessionAction::Enter,
+            };
+            match action {
+                SessionAction::Enter => {
+                    self.attach_to(socket, name);
+                }
+                // Locked sessions need their name typed first
+                action if self.is_locked(&name) => {
+                    self.state = AppState::ConfirmingLocked {
+                        name,
+                        socket,
+                        action,
+                    };
+                    self.input.clear();
+                }
+                SessionAction::Rename if self.needs_rename_confirmation(&name) => {
+                    self.state = AppState::ConfirmingAttachedRename { name, socket };
+                }
+                SessionAction::Rename => {
+                    self.start_rename(socket, name);
+                }
+                SessionAction::Delete => {
+                    // Delete the session
+                    self.delete_current_session();
+                }
+            }
+        }
+    }
+
+    /// Open the create prompt on the "Create new" row, prefilled with `name`
+    pub fn start_creating(&mut self, name: Option<&str>) {
+        self.selected_index = self.visible_sessions().len();
+        self.focus_area = FocusArea::SessionList;
+        self.state = AppState::CreatingSession;
+        self.input.set(name.unwrap_or_default());
+        self.name_candidates = self.collect_name_candidates();
+        self.create_history.reset();
+        self.layout_index = 0;
+        self.create_env.clear();
+    }
+
+    /// Open the create prompt prefilled with the current directory's git
+    /// branch, or with the directory's name outside a repository
+    fn start_creating_for_branch(&mut self) {
+        let name = std::env::current_dir().ok().and_then(|cwd| {
+            branch_session_name(&cwd)
+                .or_else(|| Some(sanitize_name(&cwd.file_name()?.to_string_lossy())))
+        });
+        self.start_creating(name.as_deref());
+    }
+
+    /// Create the session named in the prompt and attach to it (unless
+    /// `attach_on_create` is off), as Enter does; an invalid name is
+    /// explained in the status bar instead
+    pub fn confirm_create(&mut self) {
+        if self.name_accepted() {
+            self.create_and_attach_session();
+        }
+    }
+
+    /// Whether the name in the prompt may be submitted. Errors always stop
+    /// it; a warning only stops the first Enter for that name, so it can be
+    /// read and then overridden.
+    fn name_accepted(&mut self) -> bool {
+        match self.validate_input() {
+            Some(Err(e)) => {
+                self.status_message = Some(e);
+                false
+            }
+            Some(Ok(Some(warning)))
+                if self.warned_name.as_deref() != Some(self.input.as_str().trim()) =>
+            {
+                self.status_message = Some(format!("{}; press Enter again to go ahead", warning));
+                self.warned_name = Some(self.input.as_str().trim().to_string());
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Open the prompt for a prefix or suffix to add to the selected
+    /// session's name. Locked sessions go through the full rename instead,
+    /// which asks for their name first.
+    fn start_affix(&mut self, prepend: bool) {
+        let Some((socket, name)) = self.selected_target() else {
+            return;
+        };
+        if self.is_locked(&name) {
+            self.status_message = Some(format!("'{}' is locked; rename it from its row", name));
+            return;
+        }
+        self.state = AppState::AffixingName {
+            original_name: name,
+            socket,
+            prepend,
+        };
+        self.input.clear();
+    }
+
+    /// The name the affix prompt would rename its session to
+    pub fn affixed_name(&self) -> Option<String> {
+        let AppState::AffixingName {
+            original_name,
+            prepend,
+            ..
+        } = &self.state
+        else {
+            return None;
+        };
+        let affix = self.input.as_str().trim();
+        Some(if *prepend {
+            format!("{}{}", affix, original_name)
+        } else {
+            format!("{}{}", original_name, affix)
+        })
+    }
+
+    fn handle_affixing_name_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.state = AppState::SessionList;
+                self.input.clear();
+            }
+            KeyCode::Enter => {
+                if self.name_accepted() {
+                    if let (
+                        AppState::AffixingName {
+                            original_name,
+                            socket,
+                            ..
+                        },
+                        Some(new_name),
+                    ) = (&self.state, self.affixed_name())
+                    {
+                        self.rename_session_to(socket.clone(), original_name.clone(), new_name);
+                    }
+                }
+            }
+            _ => {
+                self.input.handle_key(key, is_session_name_char);
+            }
+        }
+    }
+
+    /// Open the rename prompt for `name` on `socket`
+    fn start_rename(&mut self, socket: Option<String>, name: String) {
+        self.state = AppState::RenamingSession {
+            original_name: name.clone(),
+            socket,
+        };
+        self.input.set(name);
+    }
+
+    /// Complete `prefix` against existing session stems and project names, like
+    /// shell tab-completion: the longest common prefix of all candidates
+    pub fn suggest(&self, prefix: &str) -> Option<String> {
+        let mut matching = self
+            .name_candidates
+            .iter()
+            .filter(|candidate| candidate.starts_with(prefix) && candidate.len() > prefix.len());
+
+        let first = matching.next()?;
+        let common = matching.fold(first.as_str(), |common, candidate| {
+            let len = common
+                .char_indices()
+                .zip(candidate.chars())
+                .take_while(|((_, a), b)| a == b)
+                .last()
+                .map_or(0, |((i, a), _)| i + a.len_utf8());
+            &common[..len]
+        });
+
+        (common.len() > prefix.len()).then(|| common.to_string())
+    }
+
+    /// Session name stems ("api-" from "api-server") plus the current directory's
+    /// name and subdirectories, sanitized into valid session names
+    fn collect_name_candidates(&self) -> Vec<String> {
+        let mut candidates: Vec<String> = self
+            .sessions
+            .iter()
+            .flat_map(|session| {
+                session
+                    .name
+                    .match_indices(['-', '_'])
+                    .map(|(i, sep)| session.name[..i + sep.len()].to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        if let Ok(cwd) = std::env::current_dir() {
+            if let Some(name) = cwd.file_name() {
+                candidates.push(sanitize_name(&name.to_string_lossy()));
+            }
+            if let Ok(entries) = std::fs::read_dir(&cwd) {
+                candidates.extend(
+                    entries
+                        .flatten()
+                        .filter(|entry| entry.path().is_dir())
+                        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                        .filter(|name| !name.starts_with('.'))
+                        .map(|name| sanitize_name(&name)),
+                );
+            }
+        }
+
+        candidates.retain(|candidate| !candidate.is_empty());
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+
+    /// Kill the selected session, asking first when it's the one Ursa runs
+    /// in since its window would close under the user
+    fn delete_current_session(&mut self) {
+        let Some((socket, name)) = self.selected_target() else {
+            return;
+        };
+        // Names are only unique per server. A session listed without a
+        // socket is on the server Ursa's commands go to, the one it runs in.
+        let own = self
+            .backend
+            .current_session()
+            .is_some_and(|(current_socket, current)| {
+                current == name && socket.as_ref().is_none_or(|s| *s == current_socket)
+            });
+        if !self.dry_run && own {
+            self.state = AppState::ConfirmingOwnDelete { name };
+            return;
+        }
+        self.kill_current_session();
+    }
+
+    fn kill_current_session(&mut self) {
+        let Some((socket, name)) = self.selected_target() else {
+            return;
+        };
+        let socket = socket.as_deref();
+        if !self.recheck_exists(socket, &name) {
+            return;
+        }
+        // The rows either side, by name since the refresh may also bring in
+        // sessions changed elsewhere
+        let visible = self.visible_sessions();
+        let neighbor = |row: Option<usize>| {
+            row.and_then(|row| visible.get(row))
+                .map(|&i| self.sessions[i].name.clone())
+        };
+        let next = neighbor(Some(self.selected_index + 1));
+        let previous = neighbor(self.selected_index.checked_sub(1));
+
+        match self.backend.kill_session(socket, &name, self.dry_run) {
+            Ok(()) if self.dry_run => {
+                self.status_message = Some(format!("Dry run: would kill session '{}'", name));
+                self.selected_action = SessionAction::Enter;
+            }
+            Ok(()) => {
+                self.log_action(format!("Killed '{}'", name));
+                self.run_hook(HookEvent::Delete, &name);
+                self.refresh_sessions();
+                // The session that moved up into the deleted one's row, or
+                // the one above when the last was deleted
+                let visible = self.visible_sessions();
+                if let Some(row) = [next, previous].into_iter().flatten().find_map(|name| {
+                    visible.iter().position(|&i| self.sessions[i].name == name)
+                }) {
+                    self.selected_index = row;
+                }
+                self.selected_action = SessionAction::Enter;
+            }
+            Err(TmuxError::NotFound(_)) => self.report_vanished(&name),
+            Err(e) => {
+                self.error_message = Some(e.to_string());
+            }
+        }
+    }
+
+    fn create_and_attach_session(&mut self) {
+        let (name, start_dir) = match self.dropped_directory() {
+            Some((name, dir)) => (name, Some(dir.to_string_lossy().into_owned())),
+            None => (self.input.as_str().trim().to_string(), None),
+        };
+        if name.is_empty() || self.refuse_over_limit() {
+            return;
+        }
+
+        let layout = self.config.layouts.get(self.layout_index).cloned();
+        let env = &self.create_env;
+        let socket = self.creation_socket();
+        let socket = socket.as_deref();
+        let result = self
+            .backend
+            .create_session(socket, &name, start_dir.as_deref(), env)
+            .and_then(|()| match &layout {
+                Some(layout) => self.backend.apply_layout(socket, &name, layout),
+                None => Ok(()),
+            });
+
+        match result {
+            Ok(()) => {
+                self.create_history.push(&name);
+                self.log_action(match &layout {
+                    Some(layout) => format!("Created '{}' ({})", name, layout.name),
+                    None => format!("Created '{}'", name),
+                });
+                self.run_hook(HookEvent::Create, &name);
+                self.refresh_sessions();
+                if self.config.attach_on_create {
+                    self.action = AppAction::AttachSession {
+                        name,
+                        socket: socket.map(str::to_string),
+                    };
+                } else {
+                    self.stay_on_created(&name);
+                }
+            }
+            Err(TmuxError::DuplicateName(taken)) => self.suggest_free_name(&taken),
+            Err(e) => {
+                self.error_message = Some(e.to_string());
+                self.state = AppState::SessionList;
+                self.input.clear();
+            }
+        }
+    }
+
+    /// The `max_sessions` setting, once the live count has reached it
+    pub fn session_limit_reached(&self) -> Option<usize> {
+        let max = self.config.max_sessions;
+        (max > 0 && self.sessions.len() >= max).then_some(max)
+    }
+
+    /// Back to the list explaining why, when creating another session would
+    /// go past `max_sessions`
+    fn refuse_over_limit(&mut self) -> bool {
+        let Some(max) = self.session_limit_reached() else {
+            return false;
+        };
+        self.error_message = Some(format!(
+            "Already at the limit of {} sessions (max_sessions); kill some to create more",
+            max
+        ));
+        self.state = AppState::SessionList;
+        self.input.clear();
+        true
+    }
+
+    /// Back to the list with the new session `name` selected, when
+    /// `attach_on_create` is off
+    fn stay_on_created(&mut self, name: &str) {
+        self.state = AppState::SessionList;
+        self.input.clear();
+        if let Some(row) = self
+            .visible_sessions()
+            .iter()
+            .position(|&i| self.sessions[i].name == name)
+        {
+            self.selected_index = row;
+        }
+        self.selected_action = SessionAction::Enter;
+        self.status_message = Some(format!("Created '{}'", name));
+    }
+
+    /// tmux rejected `taken` as a duplicate, e.g. because it was created
+    /// elsewhere since the list was read; keep the prompt open with a free name
+    fn suggest_free_name(&mut self, taken: &str) {
+        self.refresh_sessions();
+        let free = dedupe_name(taken, self.sessions.iter().map(|s| s.name.as_str()));
+        self.status_message = Some(format!(
+            "A session named '{}' already exists; try '{}'",
+            taken, free
+        ));
+        self.input.set(free);
+    }
+
+    fn handle_renaming_session_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.state = AppState::SessionList;
+                self.input.clear();
+                self.selected_action = SessionAction::Enter;
+            }
+            KeyCode::Enter => {
+                if self.name_accepted() {
+                    self.rename_current_session();
+                }
+            }
+            _ => {
+                self.input.handle_key(key, is_session_name_char);
+            }
+        }
+    }
+
+    fn rename_current_session(&mut self) {
+        let new_name = self.input.as_str().trim().to_string();
+        if new_name.is_empty() {
+            return;
+        }
+
+        // Extract original_name from the state
+        let AppState::RenamingSession {
+            original_name,
+            socket,
+        } = &self.state
+        else {
+            return;
+        };
+        self.rename_session_to(socket.clone(), original_name.clone(), new_name);
+    }
+
+    /// Rename `original_name` on `socket` from the rename or affix prompt,
+    /// closing it unless the new name turns out to be taken
+    fn rename_session_to(
+        &mut self,
+        socket: Option<String>,
+        original_name: String,
+        new_name: String,
+    ) {
+        if !self.recheck_exists(socket.as_deref(), &original_name) {
+            return;
+        }
+
+        let result =
+            self.backend
+                .rename_session(socket.as_deref(), &original_name, &new_name, self.dry_run);
+        match result {
+            Ok(()) if self.dry_run => {
+                self.status_message = Some(format!(
+                    "Dry run: would rename '{}' to '{}'",
+                    original_name, new_name
+                ));
+                self.state = AppState::SessionList;
+                self.input.clear();
+                self.selected_action = SessionAction::Enter;
+            }
+            Ok(()) => {
+                self.log_action(format!("Renamed '{}' → '{}'", original_name, new_name));
+                self.state = AppState::SessionList;
+                self.input.clear();
+                self.selected_action = SessionAction::Enter;
+                self.refresh_sessions();
+                // Keep the tag and lock attached to the session under its new name
+                if let Err(e) = self
+                    .tags
+                    .rename(&original_name, &new_name)
+                    .and_then(|()| self.locks.rename(&original_name, &new_name))
+                    .and_then(|()| self.notes.rename(&original_name, &new_name))
+                    .and_then(|()| self.snapshots.rename(&original_name, &new_name))
+                {
+                    self.error_message = Some(e);
+                }
+            }
+            Err(TmuxError::DuplicateName(taken)) => {
+                // The suggestion is a whole name, so it goes in the full prompt
+                self.start_rename(socket, original_name);
+                self.suggest_free_name(&taken);
+            }
+            // Closed between the recheck and the rename
+            Err(TmuxError::NotFound(_)) => self.report_vanished(&original_name),
+            Err(e) => {
+                self.error_message = Some(e.to_string());
+                self.state = AppState::SessionList;
+                self.input.clear();
+                self.selected_action = SessionAction::Enter;
+            }
+        }
+    }
+
+    /// Sessions that look abandoned: detached, with every pane sitting at a shell
+    /// prompt in a window still named after that shell.
+    pub fn empty_session_candidates(&self) -> Vec<&TmuxSession> {
+        // Panes from each server that hosts a listed session
+        let mut sockets: Vec<Option<String>> = Vec::new();
+        for session in &self.sessions {
+            if !sockets.contains(&session.socket) {
+                sockets.push(session.socket.clone());
+            }
+        }
+        let panes: Vec<_> = sockets
+            .iter()
+            .flat_map(|socket| {
+                self.backend
+                    .list_panes(socket.as_deref())
+                    .into_iter()
+                    .map(move |pane| (socket.clone(), pane))
+            })
+            .collect();
+
+        // Locked sessions are never offered, since cleanup kills in bulk
+        self.sessions
+            .iter()
+            .filter(|session| !session.attached && !self.is_locked(&session.name))
+            .filter(|session| {
+                let session_panes: Vec<_> = panes
+                    .iter()
+                    .filter(|(socket, p)| {
+                        *socket == session.socket && p.session_name == session.name
+                    })
+                    .map(|(_, p)| p)
+                    .collect();
+                !session_panes.is_empty()
+                    && session_panes.iter().all(|pane| {
+                        let command = pane.current_command.trim_start_matches('-');
+                        is_shell(command) && pane.window_name.trim_start_matches('-') == command
+                    })
+            })
+            .collect()
+    }
+
+    /// Every detached session that isn't locked; attached ones are never killed
+    pub fn detached_session_candidates(&self) -> Vec<&TmuxSession> {
+        self.sessions
+            .iter()
+            .filter(|session| !session.attached && !self.is_locked(&session.name))
+            .collect()
+    }
+
+    /// Detached sessions whose start directory is gone, typically left
+    /// behind by a deleted project
+    pub fn zombie_session_candidates(&self) -> Vec<&TmuxSession> {
+        self.sessions
+            .iter()
+            .filter(|session| !session.attached && !self.is_locked(&session.name))
+            .filter(|session| self.is_zombie(&session.name))
+            .collect()
+    }
+
+    /// Detached sessions nothing has happened in for longer than `idle_hours`
+    pub fn idle_session_candidates(&self) -> Vec<&TmuxSession> {
+        self.sessions
+            .iter()
+            .filter(|session| !session.attached && !self.is_locked(&session.name))
+            .filter(|session| self.is_long_idle(&session.name))
+            .collect()
+    }
+
+    /// Ask before killing the sessions `kind` selects, listing them
+    pub fn start_cleanup(&mut self, kind: CleanupKind) {
+        let candidates = match kind {
+            CleanupKind::Empty => self.empty_session_candidates(),
+            CleanupKind::Detached => self.detached_session_candidates(),
+            CleanupKind::Zombie => self.zombie_session_candidates(),
+            CleanupKind::Idle => self.idle_session_candidates(),
+        };
+        let items: Vec<BatchItem> = candidates.into_iter().map(BatchItem::new).collect();
+        if items.is_empty() {
+            self.status_message = Some(format!("No {} sessions to clean up", kind.label()));
+        } else {
+            self.start_batch(BatchOperation::Kill(kind), items);
+        }
+    }
+
+    /// Open the confirmation for `operation` on `items`
+    fn start_batch(&mut self, operation: BatchOperation, items: Vec<BatchItem>) {
+        self.state = AppState::ConfirmingBatch {
+            operation,
+            items,
+            cursor: 0,
+        };
+    }
+
+    fn handle_confirming_batch_key(&mut self, key: KeyEvent) {
+        let AppState::ConfirmingBatch { items, cursor, .. } = &mut self.state else {
+            return;
+        };
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => *cursor = cursor.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                *cursor = (*cursor + 1).min(items.len().saturating_sub(1));
+            }
+            KeyCode::Char(' ') => {
+                if let Some(item) = items.get_mut(*cursor) {
+                    item.included = !item.included;
+                }
+            }
+            // Include everything, or leave everything out once it all is
+            KeyCode::Char('a') => {
+                let include = !items.iter().all(|item| item.included);
+                for item in items.iter_mut() {
+                    item.included = include;
+                }
+            }
+            KeyCode::Enter | KeyCode::Char('y') => {
+                if items.iter().any(|item| item.included) {
+                    self.run_batch();
+                } else {
+                    self.status_message =
+                        Some("Nothing selected; Space includes a session".to_string());
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => {
+                self.state = AppState::SessionList;
+            }
+            _ => {}
+        }
+    }
+
+    /// Start the confirmed batch on the sessions still included; `tick`
+    /// carries it out from there
+    fn run_batch(&mut self) {
+        let AppState::ConfirmingBatch {
+            operation, items, ..
+        } = std::mem::replace(&mut self.state, AppState::SessionList)
+        else {
+            return;
+        };
+        let items: Vec<BatchItem> = items.into_iter().filter(|item| item.included).collect();
+        let steps = match &operation {
+            BatchOperation::Kill(kind) => items
+                .into_iter()
+                .map(|item| BatchStep::Kill {
+                    kind: *kind,
+                    name: item.name,
+                    socket: item.socket,
+                })
+                .collect(),
+            BatchOperation::Renumber { prefix } => match self.renumber_steps(prefix, items) {
+                Some(steps) => steps,
+                None => return,
+            },
+        };
+        self.state = AppState::RunningBatch {
+            progress: BatchProgress::new(operation, steps),
+        };
+    }
+
+    /// The first pass of renaming `items` to the numbered series. Every
+    /// session is first moved to a temporary name so that, say, `web-2` can
+    /// become `web-1` while another session is still becoming `web-2`. None
+    /// when there's nothing to run: a new name is taken, or it's a dry run.
+    fn renumber_steps(&mut self, prefix: &str, items: Vec<BatchItem>) -> Option<Vec<BatchStep>> {
+        let names: Vec<String> = items.iter().map(|item| item.name.clone()).collect();
+        let plan: Vec<(BatchItem, String)> = items
+            .into_iter()
+            .zip(renumber_plan(prefix, &names))
+            .filter(|(_, (old, new))| old != new)
+            .map(|(item, (_, new))| (item, new))
+            .collect();
+        if let Some((_, taken)) = plan
+            .iter()
+            .find(|(_, new)| !names.contains(new) && self.sessions.iter().any(|s| &s.name == new))
+        {
+            self.error_message = Some(format!(
+                "'{}' is taken by a session outside the series; nothing was renamed",
+                taken
+            ));
+            return None;
+        }
+        if self.dry_run {
+            self.status_message = Some(format!(
+                "Dry run: would rename {} session{}",
+                plan.len(),
+                if plan.len() == 1 { "" } else { "s" }
+            ));
+            return None;
+        }
+        Some(
+            plan.into_iter()
+                .enumerate()
+                .map(|(index, (item, new))| BatchStep::Park {
+                    old: item.name,
+                    temp: format!("ursa-renumber-{}-{}", std::process::id(), index),
+                    new,
+                    socket: item.socket,
+                })
+                .collect(),
+        )
+    }
+
+    /// Whether a renumber is under way, with sessions possibly parked under
+    /// temporary names until its second pass
+    pub fn is_renumbering(&self) -> bool {
+        matches!(
+            &self.state,
+            AppState::RunningBatch { progress }
+                if matches!(progress.operation, BatchOperation::Renumber { .. })
+        )
+    }
+
+    /// Quit, or mid-renumber once it's done: stopping between its passes
+    /// would strand sessions under their temporary names
+    pub fn quit(&mut self) {
+        if self.is_renumbering() {
+            self.quit_after_batch = true;
+            self.status_message = Some("Quitting once the renumber is done".to_string());
+        } else {
+            self.should_quit = true;
+        }
+    }
+
+    /// Run the next step of the running batch, or wrap it up once there are
+    /// none left. A session that fails is reported at the end rather than
+    /// stopping the others.
+    fn step_batch(&mut self) {
+        let AppState::RunningBatch { progress } = &mut self.state else {
+            return;
+        };
+        let Some(step) = progress.steps.pop_front() else {
+            self.finish_batch();
+            return;
+        };
+        let result = match step {
+            BatchStep::Kill { kind, name, socket } => {
+                self.kill_batch_session(kind, socket.as_deref(), &name)
+            }
+            BatchStep::Park {
+                old,
+                temp,
+                new,
+                socket,
+            } => self.park_session(old, temp, new, socket),
+            BatchStep::Rename {
+                old,
+                temp,
+                new,
+                socket,
+            } => self.unpark_session(&old, &temp, &new, socket.as_deref()),
+        };
+        let AppState::RunningBatch { progress } = &mut self.state else {
+            return;
+        };
+        match result {
+            Ok(Some(next)) => progress.steps.push_back(next),
+            Ok(None) => {
+                progress.done += 1;
+                progress.succeeded += 1;
+            }
+            Err(e) => {
+                progress.done += 1;
+                progress.failures.push(e);
+            }
+        }
+    }
+
+    fn kill_batch_session(
+        &mut self,
+        kind: CleanupKind,
+        socket: Option<&str>,
+        name: &str,
+    ) -> Result<Option<BatchStep>, String> {
+        self.backend
+            .kill_session(socket, name, self.dry_run)
+            .map_err(|e| format!("{}: {}", name, e))?;
+        if !self.dry_run {
+            self.log_action(format!("Killed {} session '{}'", kind.label(), name));
+            self.run_hook(HookEvent::Delete, name);
+        }
+        Ok(None)
+    }
+
+    /// Move `old` to `temp`; its rename to `new` is queued behind every
+    /// other session's move
+    fn park_session(
+        &mut self,
+        old: String,
+        temp: String,
+        new: String,
+        socket: Option<String>,
+    ) -> Result<Option<BatchStep>, String> {
+        self.backend
+            .rename_session(socket.as_deref(), &old, &temp, false)
+            .map_err(|e| format!("{}: {}", old, e))?;
+        Ok(Some(BatchStep::Rename {
+            old,
+            temp,
+            new,
+            socket,
+        }))
+    }
+
+    fn unpark_session(
+        &mut self,
+        old: &str,
+        temp: &str,
+        new: &str,
+        socket: Option<&str>,
+    ) -> Result<Option<BatchStep>, String> {
+        self.backend
+            .rename_session(socket, temp, new, false)
+            .inspect_err(|_| {
+                // Don't leave it under the temporary name
+                let _ = self.backend.rename_session(socket, temp, old, false);
+            })
+            .map_err(|e| format!("{}: {}", old, e))?;
+        self.log_action(format!("Renamed '{}' → '{}'", old, new));
+        self.tags
+            .rename(old, new)
+            .and_then(|()| self.locks.rename(old, new))
+            .and_then(|()| self.notes.rename(old, new))
+            .and_then(|()| self.snapshots.rename(old, new))?;
+        Ok(None)
+    }
+
+    /// Back to the list once the batch is done, with a summary of it and of
+    /// any sessions it failed on
+    fn finish_batch(&mut self) {
+        let AppState::RunningBatch { progress } =
+            std::mem::replace(&mut self.state, AppState::SessionList)
+        else {
+            return;
+        };
+        if std::mem::take(&mut self.quit_after_batch) {
+            self.should_quit = true;
+        }
+        self.refresh_sessions();
+        self.selected_action = SessionAction::Enter;
+
+        let summary = format!(
+            "{}{}",
+            if self.dry_run { "Dry run: " } else { "" },
+            progress.summary()
+        );
+        if progress.failures.is_empty() {
+            self.show_toast(summary, CLEANUP_TOAST_DURATION);
+        } else {
+            self.error_message = Some(format!(
+                "{}; {} failed:\n{}",
+                summary,
+                progress.failures.len(),
+                progress.failures.join("\n")
+            ));
+        }
+    }
+}
+
+/// Characters allowed in session names typed into the create and rename prompts
+pub fn is_session_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+/// Replace characters tmux session names can't contain (per the input filter) with '-'
+pub fn sanitize_name(raw: &str) -> String {
+    raw.chars()
+        .map(|c| if is_session_name_char(c) { c } else { '-' })
+        .collect()
+}
+
+/// The new name for each of `names` when numbered under `prefix`, in order:
+/// `prefix-1`, `prefix-2`, …
+pub fn renumber_plan(prefix: &str, names: &[String]) -> Vec<(String, String)> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.clone(), format!("{}-{}", prefix, i + 1)))
+        .collect()
+}
+
+/// The session `prefix` names, as tmux's `-t` would pick it: the session
+/// called exactly that, else the only one starting with it. Otherwise the
+/// sessions it could mean, none when nothing starts with it.
+pub fn resolve_session_prefix(
+    sessions: &[TmuxSession],
+    prefix: &str,
+) -> Result<String, Vec<String>> {
+    if sessions.iter().any(|s| s.name == prefix) {
+        return Ok(prefix.to_string());
+    }
+    let mut candidates: Vec<String> = sessions
+        .iter()
+        .filter(|s| s.name.starts_with(prefix))
+        .map(|s| s.name.clone())
+        .collect();
+    // The same name can be on several servers
+    candidates.sort();
+    candidates.dedup();
+    match candidates.len() {
+        1 => Ok(candidates.remove(0)),
+        _ => Err(candidates),
+    }
+}
+
+/// The branch checked out in the git repository at `dir`, made into a
+/// session name (`feature/login` becomes `feature-login`). None outside a
+/// repository or on a detached HEAD.
+pub fn branch_session_name(dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || branch.is_empty() || branch == "HEAD" {
+        return None;
+    }
+    Some(sanitize_name(&branch))
+}
+
+/// Names of the sessions whose start directory no longer exists. One stat
+/// per session, so it's cheap enough for every refresh. Remote sessions'
+/// directories aren't on this machine, so none are flagged.
+fn zombie_sessions(sessions: &[TmuxSession]) -> HashSet<String> {
+    if tmux::remote().is_some() {
+        return HashSet::new();
+    }
+    sessions
+        .iter()
+        .filter(|s| !s.path.is_empty() && !std::path::Path::new(&s.path).exists())
+        .map(|s| s.name.clone())
+        .collect()
+}
+
+/// How long it's been since the last activity in `session`, or None when
+/// tmux didn't report it
+pub fn idle_time(session: &TmuxSession) -> Option<Duration> {
+    if session.last_activity == 0 {
+        return None;
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    Some(Duration::from_secs(now.saturating_sub(session.last_activity)))
+}
+
+/// `name` with control characters, which would garble or vanish from the
+/// list, replaced by '?'
+pub fn printable_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_control() { '?' } else { c })
+        .collect()
+}
+
+/// Display names for the raw session `names` that can't be shown as-is.
+/// When several names print the same, the ones that were altered get their
+/// position in `names` appended ("a?b #2") so every row stays distinct.
+pub fn display_names<'a>(names: impl IntoIterator<Item = &'a str>) -> HashMap<String, String> {
+    let altered: Vec<(usize, &str, String)> = names
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| (i, name, printable_name(name)))
+        .collect();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (_, _, printable) in &altered {
+        *counts.entry(printable.as_str()).or_default() += 1;
+    }
+
+    altered
+        .iter()
+        .filter(|(_, name, printable)| name != printable)
+        .map(|(i, name, printable)| {
+            let display = if counts[printable.as_str()] > 1 {
+                format!("{} #{}", printable, i + 1)
+            } else {
+                printable.clone()
+            };
+            (name.to_string(), display)
+        })
+        .collect()
+}
+
+/// `name` with the first free "-2", "-3", … suffix when `existing` has it
+pub fn dedupe_name<'a>(name: &str, existing: impl IntoIterator<Item = &'a str>) -> String {
+    let existing: HashSet<&str> = existing.into_iter().collect();
+    if !existing.contains(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|n| format!("{}-{}", name, n))
+        .find(|candidate| !existing.contains(candidate.as_str()))
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// How many chars to keep from the start and end of a name `len` chars long
+/// so that it fits in `max` chars with an ellipsis in between, or None when
+/// it already fits
+pub fn middle_truncation(len: usize, max: usize) -> Option<(usize, usize)> {
+    if len <= max {
+        return None;
+    }
+    let kept = max.saturating_sub(1);
+    let head = kept.div_ceil(2);
+    Some((head, kept - head))
+}
+
+/// `name` shortened to at most `max` chars (but never less than the "…") by
+/// replacing its middle with an ellipsis, cutting on char boundaries
+pub fn truncate_middle(name: &str, max: usize) -> String {
+    let len = name.chars().count();
+    match middle_truncation(len, max) {
+        None => name.to_string(),
+        Some((head, tail)) => {
+            let start: String = name.chars().take(head).collect();
+            let end: String = name.chars().skip(len - tail).collect();
+            format!("{}…{}", start, end)
+        }
+    }
+}
+
+/// Char positions in `name` matching `query` as a case-insensitive subsequence
+pub fn match_positions(name: &str, query: &str) -> Option<Vec<usize>> {
+    let mut positions = Vec::new();
+    let mut query_chars = query.chars().flat_map(char::to_lowercase).peekable();
+
+    for (i, c) in name.chars().enumerate() {
+        let Some(&wanted) = query_chars.peek() else {
+            break;
+        };
+        if c.to_lowercase().eq(std::iter::once(wanted)) {
+            positions.push(i);
+            query_chars.next();
+        }
+    }
+
+    query_chars.peek().is_none().then_some(positions)
+}
+
+/// Common interactive shells, as reported by `#{pane_current_command}`
+fn is_shell(command: &str) -> bool {
+    matches!(
+        command,
+        "sh" | "bash" | "zsh" | "fish" | "dash" | "ksh" | "mksh" | "tcsh" | "csh" | "nu" | "elvish"
+    )
 }
@@ -0,0 +1,84 @@
+//! Ranking session names against a search query, fzf-style.
+//!
+//! A name matches when the query's characters appear in it in order,
+//! ignoring case. Among matches, runs of consecutive characters, characters
+//! starting a word (after `-`, `_`, `.`, `/`, `:` or a space, at a camelCase
+//! hump, or the first digit of a number) and a match at the very start score
+//! higher, and gaps cost a little.
+
+/// Every matched character
+const MATCH: i32 = 16;
+/// A matched character right after the previous one
+const CONSECUTIVE: i32 = 12;
+/// A matched character starting a word
+const BOUNDARY: i32 = 8;
+/// On top of `BOUNDARY` for the first character of the name
+const PREFIX: i32 = 8;
+/// Skipping over characters between two matched ones, plus 1 per character
+const GAP_START: i32 = 3;
+
+/// How well `candidate` matches `query`, higher being better; None when it
+/// doesn't contain the query as a case-insensitive subsequence. The best
+/// alignment is found rather than the leftmost, so "api" scores its
+/// contiguous run in "a-proj-api" rather than the scattered first letters.
+pub fn score(query: &str, candidate: &str) -> Option<i32> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let original: Vec<char> = candidate.chars().collect();
+    let name: Vec<char> = original
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    if name.len() < query.len() {
+        return None;
+    }
+
+    let bonus: Vec<i32> = (0..original.len())
+        .map(|j| position_bonus(&original, j))
+        .collect();
+
+    // best[j]: the best score with the query so far ending on name[j]
+    let mut best: Vec<Option<i32>> = name
+        .iter()
+        .enumerate()
+        .map(|(j, &c)| (c == query[0]).then(|| MATCH + bonus[j]))
+        .collect();
+    for &wanted in &query[1..] {
+        let mut next = vec![None; name.len()];
+        // The best earlier end to jump from, with the gap cost up to here
+        let mut carried: Option<i32> = None;
+        for j in 1..name.len() {
+            carried = carried.map(|score| score - 1);
+            if let Some(score) = best[j - 1] {
+                if name[j] == wanted {
+                    next[j] = Some(score + MATCH + CONSECUTIVE + bonus[j]);
+                }
+                carried = carried.max(Some(score - GAP_START));
+            }
+            if name[j] == wanted {
+                let jumped = carried.map(|score| score + MATCH + bonus[j]);
+                next[j] = next[j].max(jumped);
+            }
+        }
+        best = next;
+    }
+    best.into_iter().flatten().max()
+}
+
+/// Bonus for matching at `j`: the start of the name, or of a word in it
+fn position_bonus(name: &[char], j: usize) -> i32 {
+    let Some(&prev) = j.checked_sub(1).and_then(|i| name.get(i)) else {
+        return BOUNDARY + PREFIX;
+    };
+    let current = name[j];
+    let separator = matches!(prev, '-' | '_' | '.' | '/' | ' ' | ':');
+    let hump = prev.is_lowercase() && current.is_uppercase();
+    let digits = !prev.is_ascii_digit() && current.is_ascii_digit();
+    if separator || hump || digits {
+        BOUNDARY
+    } else {
+        0
+    }
+}
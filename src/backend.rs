@@ -0,0 +1,829 @@
+//! The tmux operations [`App`](crate::app::App) runs, behind a trait so the
+//! state machine can be driven against an in-memory server in tests.
+//!
+//! [`SystemTmux`] runs the real commands from [`tmux`];
+//! [`FakeTmux`] keeps its sessions in a `Vec`. Only process-wide settings
+//! (the binary, the remote) and checks that don't run tmux, such as name
+//! validation, are left to [`tmux`] itself.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::config::LayoutPreset;
+use crate::tmux::{
+    self, SessionDetails, SplitDirection, TmuxError, TmuxPane, TmuxSession, TmuxWindow,
+    WindowLayout,
+};
+
+/// Creating, listing, renaming, killing and switching to sessions, and
+/// working with their panes. Each operation on a session goes to the server
+/// on `socket` (as for `tmux -L`), None being tmux's default server. Shared
+/// with threads that read from tmux without holding up the UI.
+pub trait TmuxBackend: Send + Sync {
+    /// Sessions from every socket in `sockets`, or the current server when
+    /// empty
+    fn list_sessions(&self, sockets: &[String]) -> Result<Vec<TmuxSession>, TmuxError>;
+    /// Whether a session named exactly `name` exists
+    fn has_session(&self, socket: Option<&str>, name: &str) -> Result<bool, TmuxError>;
+    fn create_session(
+        &self,
+        socket: Option<&str>,
+        name: &str,
+        start_dir: Option<&str>,
+        env: &[String],
+    ) -> Result<(), TmuxError>;
+    /// Create `name` unless it exists; true when it was created
+    fn create_if_missing(
+        &self,
+        socket: Option<&str>,
+        name: &str,
+        start_dir: Option<&str>,
+    ) -> Result<bool, TmuxError>;
+    fn apply_layout(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        layout: &LayoutPreset,
+    ) -> Result<(), TmuxError>;
+    fn new_window(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        name: Option<&str>,
+        start_dir: Option<&str>,
+    ) -> Result<(), TmuxError>;
+    fn rename_session(
+        &self,
+        socket: Option<&str>,
+        old_name: &str,
+        new_name: &str,
+        dry_run: bool,
+    ) -> Result<(), TmuxError>;
+    fn kill_session(
+        &self,
+        socket: Option<&str>,
+        name: &str,
+        dry_run: bool,
+    ) -> Result<(), TmuxError>;
+    fn session_details(
+        &self,
+        socket: Option<&str>,
+        name: &str,
+    ) -> Result<SessionDetails, TmuxError>;
+    /// Make window `index` (as listed, so counting from `base-index`) the
+    /// current window of `session`
+    fn select_window(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        index: u32,
+    ) -> Result<(), TmuxError>;
+    /// Move the current client to `name`
+    fn switch_client(&self, socket: Option<&str>, name: &str) -> Result<(), TmuxError>;
+    /// Type `keys` into the active pane of `session`
+    fn send_keys(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        keys: &[String],
+    ) -> Result<(), TmuxError>;
+    /// The session Ursa itself runs in, if it's inside tmux, after the name
+    /// of its server's socket
+    fn current_session(&self) -> Option<(String, String)>;
+    /// Whether Ursa runs in a tmux client, which popups open over
+    fn is_inside_tmux(&self) -> bool;
+    /// The text on screen in the active pane of `session`
+    fn capture_pane(&self, socket: Option<&str>, session: &str) -> Result<String, TmuxError>;
+    /// Write the full scrollback of the active pane of `session` to `path`
+    fn capture_scrollback(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        path: &Path,
+    ) -> Result<(), TmuxError>;
+    /// The layout of the current window of `session`
+    fn window_layout(&self, socket: Option<&str>, session: &str)
+        -> Result<WindowLayout, TmuxError>;
+    /// Arrange the current window of `session` as `saved`; false when it
+    /// has more panes than that and was left alone
+    fn restore_layout(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        saved: &WindowLayout,
+    ) -> Result<bool, TmuxError>;
+    /// Split the active pane of `session`'s current window
+    fn split_window(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        direction: SplitDirection,
+        start_dir: Option<&str>,
+    ) -> Result<(), TmuxError>;
+    /// Turn `synchronize-panes` on or off in every window of `session`
+    fn set_synchronize_panes(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        on: bool,
+    ) -> Result<(), TmuxError>;
+    /// Every pane on the server, with the command in its foreground
+    fn list_panes(&self, socket: Option<&str>) -> Vec<TmuxPane>;
+    /// Zoom the active pane of `session`'s current window
+    fn zoom_active_pane(&self, socket: Option<&str>, session: &str) -> Result<(), TmuxError>;
+    /// Whether tmux can open popups (3.2 and newer)
+    fn supports_popup(&self) -> bool;
+    /// Attach to `name` in a popup over Ursa's client until it closes
+    fn attach_in_popup(&self, socket: Option<&str>, name: &str) -> Result<(), TmuxError>;
+    /// Launch `terminal` (a command prefix) attached to `name`
+    fn open_in_terminal(
+        &self,
+        terminal: &str,
+        socket: Option<&str>,
+        name: &str,
+    ) -> Result<(), String>;
+}
+
+/// The tmux Ursa runs, through the free functions in [`tmux`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemTmux;
+
+impl TmuxBackend for SystemTmux {
+    fn list_sessions(&self, sockets: &[String]) -> Result<Vec<TmuxSession>, TmuxError> {
+        if sockets.is_empty() {
+            tmux::list_sessions(None)
+        } else {
+            tmux::list_sessions_across(sockets)
+        }
+    }
+
+    fn has_session(&self, socket: Option<&str>, name: &str) -> Result<bool, TmuxError> {
+        tmux::has_session(socket, name)
+    }
+
+    fn create_session(
+        &self,
+        socket: Option<&str>,
+        name: &str,
+        start_dir: Option<&str>,
+        env: &[String],
+    ) -> Result<(), TmuxError> {
+        tmux::create_session(socket, name, start_dir, env)
+    }
+
+    fn create_if_missing(
+        &self,
+        socket: Option<&str>,
+        name: &str,
+        start_dir: Option<&str>,
+    ) -> Result<bool, TmuxError> {
+        tmux::create_if_missing(socket, name, start_dir)
+    }
+
+    fn apply_layout(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        layout: &LayoutPreset,
+    ) -> Result<(), TmuxError> {
+        tmux::apply_layout(socket, session, layout)
+    }
+
+    fn new_window(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        name: Option<&str>,
+        start_dir: Option<&str>,
+    ) -> Result<(), TmuxError> {
+        tmux::new_window(socket, session, name, start_dir)
+    }
+
+    fn rename_session(
+        &self,
+        socket: Option<&str>,
+        old_name: &str,
+        new_name: &str,
+        dry_run: bool,
+    ) -> Result<(), TmuxError> {
+        tmux::rename_session(socket, old_name, new_name, dry_run)
+    }
+
+    fn kill_session(
+        &self,
+        socket: Option<&str>,
+        name: &str,
+        dry_run: bool,
+    ) -> Result<(), TmuxError> {
+        tmux::kill_session(socket, name, dry_run)
+    }
+
+    fn session_details(
+        &self,
+        socket: Option<&str>,
+        name: &str,
+    ) -> Result<SessionDetails, TmuxError> {
+        tmux::session_details(socket, name)
+    }
+
+    fn select_window(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        index: u32,
+    ) -> Result<(), TmuxError> {
+        tmux::select_window(socket, session, index)
+    }
+
+    fn switch_client(&self, socket: Option<&str>, name: &str) -> Result<(), TmuxError> {
+        tmux::switch_client(socket, name)
+    }
+
+    fn send_keys(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        keys: &[String],
+    ) -> Result<(), TmuxError> {
+        tmux::send_keys(socket, session, keys)
+    }
+
+    fn current_session(&self) -> Option<(String, String)> {
+        tmux::current_session()
+    }
+
+    fn is_inside_tmux(&self) -> bool {
+        tmux::is_inside_tmux()
+    }
+
+    fn capture_pane(&self, socket: Option<&str>, session: &str) -> Result<String, TmuxError> {
+        tmux::capture_pane(socket, session)
+    }
+
+    fn capture_scrollback(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        path: &Path,
+    ) -> Result<(), TmuxError> {
+        tmux::capture_scrollback(socket, session, path)
+    }
+
+    fn window_layout(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+    ) -> Result<WindowLayout, TmuxError> {
+        tmux::window_layout(socket, session)
+    }
+
+    fn restore_layout(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        saved: &WindowLayout,
+    ) -> Result<bool, TmuxError> {
+        tmux::restore_layout(socket, session, saved)
+    }
+
+    fn split_window(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        direction: SplitDirection,
+        start_dir: Option<&str>,
+    ) -> Result<(), TmuxError> {
+        tmux::split_window(socket, session, direction, start_dir)
+    }
+
+    fn set_synchronize_panes(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        on: bool,
+    ) -> Result<(), TmuxError> {
+        tmux::set_synchronize_panes(socket, session, on)
+    }
+
+    fn list_panes(&self, socket: Option<&str>) -> Vec<TmuxPane> {
+        tmux::list_panes(socket)
+    }
+
+    fn zoom_active_pane(&self, socket: Option<&str>, session: &str) -> Result<(), TmuxError> {
+        tmux::zoom_active_pane(socket, session)
+    }
+
+    fn supports_popup(&self) -> bool {
+        tmux::supports_popup()
+    }
+
+    fn attach_in_popup(&self, socket: Option<&str>, name: &str) -> Result<(), TmuxError> {
+        tmux::attach_in_popup(socket, name)
+    }
+
+    fn open_in_terminal(
+        &self,
+        terminal: &str,
+        socket: Option<&str>,
+        name: &str,
+    ) -> Result<(), String> {
+        tmux::open_in_terminal(terminal, socket, name)
+    }
+}
+
+/// A session name and the keys `send_keys` typed into it
+pub type SentKeys = (String, Vec<String>);
+
+/// In-memory tmux servers for tests, one per socket a session names. Clones
+/// share their sessions, so a test can keep one to look at what the `App` it
+/// handed another did.
+#[derive(Debug, Clone, Default)]
+pub struct FakeTmux {
+    sessions: Arc<Mutex<Vec<TmuxSession>>>,
+    /// Socket and session Ursa pretends to run in
+    current: Arc<Mutex<Option<(String, String)>>>,
+    /// The server's `base-index`, where window numbering starts
+    base_index: Arc<Mutex<u32>>,
+    /// Keys sent with `send_keys`, with the session they went to
+    sent_keys: Arc<Mutex<Vec<SentKeys>>>,
+    /// Current windows that differ from a fresh one, by (socket, session)
+    windows: Arc<Mutex<FakeWindows>>,
+}
+
+/// Current windows by the socket and name of their session
+type FakeWindows = HashMap<(Option<String>, String), FakeWindow>;
+
+/// The current window of a [`FakeTmux`] session
+#[derive(Debug, Clone, PartialEq)]
+struct FakeWindow {
+    panes: u32,
+    synchronized: bool,
+    zoomed: bool,
+    /// Text on screen in the active pane
+    screen: String,
+    /// What runs in the foreground of every pane
+    command: String,
+}
+
+impl Default for FakeWindow {
+    /// One pane at a bash prompt
+    fn default() -> Self {
+        Self {
+            panes: 1,
+            synchronized: false,
+            zoomed: false,
+            screen: String::new(),
+            command: "bash".to_string(),
+        }
+    }
+}
+
+impl FakeTmux {
+    /// A server running `names`, each with one window
+    pub fn with_sessions(names: &[&str]) -> Self {
+        let fake = Self::default();
+        fake.lock()
+            .extend(names.iter().map(|name| fake_session(None, name, None)));
+        fake
+    }
+
+    /// Add `name` to the server on `socket`, which may already have a
+    /// session by that name on another
+    pub fn add_session(&self, socket: Option<&str>, name: &str) {
+        self.lock().push(fake_session(socket, name, None));
+    }
+
+    /// Names of the sessions on every server, in creation order
+    pub fn session_names(&self) -> Vec<String> {
+        self.lock().iter().map(|s| s.name.clone()).collect()
+    }
+
+    /// Names of the sessions on the server on `socket`, in creation order
+    pub fn session_names_on(&self, socket: Option<&str>) -> Vec<String> {
+        self.lock()
+            .iter()
+            .filter(|s| s.socket.as_deref() == socket)
+            .map(|s| s.name.clone())
+            .collect()
+    }
+
+    /// The first session named `name` on any server, as it is now
+    pub fn session(&self, name: &str) -> Option<TmuxSession> {
+        self.lock().iter().find(|s| s.name == name).cloned()
+    }
+
+    /// The session `name` on the server on `socket`, as it is now
+    pub fn session_on(&self, socket: Option<&str>, name: &str) -> Option<TmuxSession> {
+        self.lock()
+            .iter()
+            .find(|s| is_session(s, socket, name))
+            .cloned()
+    }
+
+    /// Pretend Ursa runs inside the session on the socket given, as
+    /// `(socket, name)`, or outside tmux for None
+    pub fn set_current_session(&self, current: Option<(&str, &str)>) {
+        *self.current.lock().unwrap_or_else(|e| e.into_inner()) =
+            current.map(|(socket, name)| (socket.to_string(), name.to_string()));
+    }
+
+    /// Every `send_keys` so far, oldest first
+    pub fn sent_keys(&self) -> Vec<SentKeys> {
+        self.sent_keys
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Number windows from `index`, like `set -g base-index`
+    pub fn set_base_index(&self, index: u32) {
+        *self.base_index.lock().unwrap_or_else(|e| e.into_inner()) = index;
+    }
+
+    /// Show `text` in the active pane of `name` on `socket`
+    pub fn set_screen(&self, socket: Option<&str>, name: &str, text: &str) {
+        self.update_window(socket, name, |window| window.screen = text.to_string());
+    }
+
+    /// Run `command` in every pane of `name` on `socket` instead of a shell
+    pub fn set_command(&self, socket: Option<&str>, name: &str, command: &str) {
+        self.update_window(socket, name, |window| window.command = command.to_string());
+    }
+
+    /// How many panes the current window of `name` on `socket` has
+    pub fn pane_count(&self, socket: Option<&str>, name: &str) -> u32 {
+        self.window(socket, name).panes
+    }
+
+    /// Whether the active pane of `name` on `socket` is zoomed
+    pub fn is_zoomed(&self, socket: Option<&str>, name: &str) -> bool {
+        self.window(socket, name).zoomed
+    }
+
+    fn window(&self, socket: Option<&str>, name: &str) -> FakeWindow {
+        self.lock_windows()
+            .get(&(socket.map(str::to_string), name.to_string()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn update_window(
+        &self,
+        socket: Option<&str>,
+        name: &str,
+        update: impl FnOnce(&mut FakeWindow),
+    ) {
+        let key = (socket.map(str::to_string), name.to_string());
+        update(self.lock_windows().entry(key).or_default());
+    }
+
+    /// The current window of `session` on `socket` when the session exists
+    fn existing_window(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+    ) -> Result<FakeWindow, TmuxError> {
+        self.session_on(socket, session)
+            .map(|_| self.window(socket, session))
+            .ok_or_else(|| TmuxError::NotFound(session.to_string()))
+    }
+
+    fn lock_windows(&self) -> MutexGuard<'_, FakeWindows> {
+        self.windows.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn base_index(&self) -> u32 {
+        *self.base_index.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<TmuxSession>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Whether `session` is the one `name` on the server on `socket`
+fn is_session(session: &TmuxSession, socket: Option<&str>, name: &str) -> bool {
+    session.name == name && session.socket.as_deref() == socket
+}
+
+fn fake_session(socket: Option<&str>, name: &str, start_dir: Option<&str>) -> TmuxSession {
+    TmuxSession {
+        name: name.to_string(),
+        windows: 1,
+        attached: false,
+        last_activity: 0,
+        group: None,
+        path: start_dir.unwrap_or_default().to_string(),
+        socket: socket.map(str::to_string),
+    }
+}
+
+impl TmuxBackend for FakeTmux {
+    /// Sessions on every server, whichever `sockets` are configured
+    fn list_sessions(&self, _sockets: &[String]) -> Result<Vec<TmuxSession>, TmuxError> {
+        Ok(self.lock().clone())
+    }
+
+    fn has_session(&self, socket: Option<&str>, name: &str) -> Result<bool, TmuxError> {
+        Ok(self.lock().iter().any(|s| is_session(s, socket, name)))
+    }
+
+    fn create_session(
+        &self,
+        socket: Option<&str>,
+        name: &str,
+        start_dir: Option<&str>,
+        _env: &[String],
+    ) -> Result<(), TmuxError> {
+        let mut sessions = self.lock();
+        if sessions.iter().any(|s| is_session(s, socket, name)) {
+            return Err(TmuxError::DuplicateName(name.to_string()));
+        }
+        sessions.push(fake_session(socket, name, start_dir));
+        Ok(())
+    }
+
+    fn create_if_missing(
+        &self,
+        socket: Option<&str>,
+        name: &str,
+        start_dir: Option<&str>,
+    ) -> Result<bool, TmuxError> {
+        match self.create_session(socket, name, start_dir, &[]) {
+            Ok(()) => Ok(true),
+            Err(TmuxError::DuplicateName(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn apply_layout(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        _layout: &LayoutPreset,
+    ) -> Result<(), TmuxError> {
+        self.session_on(socket, session)
+            .map(drop)
+            .ok_or_else(|| TmuxError::NotFound(session.to_string()))
+    }
+
+    fn new_window(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        _name: Option<&str>,
+        _start_dir: Option<&str>,
+    ) -> Result<(), TmuxError> {
+        let mut sessions = self.lock();
+        let session = sessions
+            .iter_mut()
+            .find(|s| is_session(s, socket, session))
+            .ok_or_else(|| TmuxError::NotFound(session.to_string()))?;
+        session.windows += 1;
+        Ok(())
+    }
+
+    fn rename_session(
+        &self,
+        socket: Option<&str>,
+        old_name: &str,
+        new_name: &str,
+        dry_run: bool,
+    ) -> Result<(), TmuxError> {
+        let mut sessions = self.lock();
+        if !sessions.iter().any(|s| is_session(s, socket, old_name)) {
+            return Err(TmuxError::NotFound(old_name.to_string()));
+        }
+        if sessions.iter().any(|s| is_session(s, socket, new_name)) {
+            return Err(TmuxError::DuplicateName(new_name.to_string()));
+        }
+        if !dry_run {
+            if let Some(session) = sessions
+                .iter_mut()
+                .find(|s| is_session(s, socket, old_name))
+            {
+                session.name = new_name.to_string();
+            }
+            let mut windows = self.lock_windows();
+            let socket = socket.map(str::to_string);
+            if let Some(window) = windows.remove(&(socket.clone(), old_name.to_string())) {
+                windows.insert((socket, new_name.to_string()), window);
+            }
+        }
+        Ok(())
+    }
+
+    fn kill_session(
+        &self,
+        socket: Option<&str>,
+        name: &str,
+        dry_run: bool,
+    ) -> Result<(), TmuxError> {
+        let mut sessions = self.lock();
+        let Some(index) = sessions.iter().position(|s| is_session(s, socket, name)) else {
+            return Err(TmuxError::NotFound(name.to_string()));
+        };
+        if !dry_run {
+            sessions.remove(index);
+            self.lock_windows()
+                .remove(&(socket.map(str::to_string), name.to_string()));
+        }
+        Ok(())
+    }
+
+    fn session_details(
+        &self,
+        socket: Option<&str>,
+        name: &str,
+    ) -> Result<SessionDetails, TmuxError> {
+        let session = self
+            .session_on(socket, name)
+            .ok_or_else(|| TmuxError::NotFound(name.to_string()))?;
+        let base = self.base_index();
+        let current = self.window(socket, name);
+        Ok(SessionDetails {
+            path: session.path,
+            created: 0,
+            windows: (base..base + session.windows)
+                .map(|index| TmuxWindow {
+                    index,
+                    name: current.command.clone(),
+                    panes: if index == base { current.panes } else { 1 },
+                    active: index == base,
+                    synchronized: current.synchronized,
+                })
+                .collect(),
+            clients: Vec::new(),
+        })
+    }
+
+    fn select_window(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        index: u32,
+    ) -> Result<(), TmuxError> {
+        let windows = self
+            .session_on(socket, session)
+            .ok_or_else(|| TmuxError::NotFound(session.to_string()))?
+            .windows;
+        let base = self.base_index();
+        if (base..base + windows).contains(&index) {
+            Ok(())
+        } else {
+            Err(TmuxError::NotFound(tmux::window_target(session, index)))
+        }
+    }
+
+    /// Marks `name` on `socket` as the one session a client is attached to
+    fn switch_client(&self, socket: Option<&str>, name: &str) -> Result<(), TmuxError> {
+        let mut sessions = self.lock();
+        if !sessions.iter().any(|s| is_session(s, socket, name)) {
+            return Err(TmuxError::NotFound(name.to_string()));
+        }
+        for session in sessions.iter_mut() {
+            session.attached = is_session(session, socket, name);
+        }
+        Ok(())
+    }
+
+    fn send_keys(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        keys: &[String],
+    ) -> Result<(), TmuxError> {
+        if self.session_on(socket, session).is_none() {
+            return Err(TmuxError::NotFound(session.to_string()));
+        }
+        self.sent_keys
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((session.to_string(), keys.to_vec()));
+        Ok(())
+    }
+
+    fn current_session(&self) -> Option<(String, String)> {
+        self.current
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Inside tmux while pretending to run in a session
+    fn is_inside_tmux(&self) -> bool {
+        self.current_session().is_some()
+    }
+
+    fn capture_pane(&self, socket: Option<&str>, session: &str) -> Result<String, TmuxError> {
+        self.existing_window(socket, session)
+            .map(|window| window.screen)
+    }
+
+    /// Writes what's on screen, as a fake pane has no history beyond it
+    fn capture_scrollback(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        path: &Path,
+    ) -> Result<(), TmuxError> {
+        let window = self.existing_window(socket, session)?;
+        std::fs::write(path, window.screen).map_err(|e| TmuxError::Io {
+            context: format!("Failed to write {}", path.display()),
+            message: e.to_string(),
+        })
+    }
+
+    /// Described as "N panes", which is what `restore_layout` goes by
+    fn window_layout(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+    ) -> Result<WindowLayout, TmuxError> {
+        let window = self.existing_window(socket, session)?;
+        Ok(WindowLayout {
+            panes: window.panes,
+            layout: format!("{} panes", window.panes),
+        })
+    }
+
+    fn restore_layout(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        saved: &WindowLayout,
+    ) -> Result<bool, TmuxError> {
+        if self.existing_window(socket, session)?.panes > saved.panes {
+            return Ok(false);
+        }
+        self.update_window(socket, session, |window| window.panes = saved.panes);
+        Ok(true)
+    }
+
+    fn split_window(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        _direction: SplitDirection,
+        _start_dir: Option<&str>,
+    ) -> Result<(), TmuxError> {
+        self.existing_window(socket, session)?;
+        self.update_window(socket, session, |window| window.panes += 1);
+        Ok(())
+    }
+
+    fn set_synchronize_panes(
+        &self,
+        socket: Option<&str>,
+        session: &str,
+        on: bool,
+    ) -> Result<(), TmuxError> {
+        self.existing_window(socket, session)?;
+        self.update_window(socket, session, |window| window.synchronized = on);
+        Ok(())
+    }
+
+    fn list_panes(&self, socket: Option<&str>) -> Vec<TmuxPane> {
+        self.lock()
+            .iter()
+            .filter(|s| s.socket.as_deref() == socket)
+            .flat_map(|session| {
+                let window = self.window(socket, &session.name);
+                let panes = window.panes + session.windows - 1;
+                (0..panes).map(move |_| TmuxPane {
+                    session_name: session.name.clone(),
+                    window_name: window.command.clone(),
+                    current_command: window.command.clone(),
+                })
+            })
+            .collect()
+    }
+
+    fn zoom_active_pane(&self, socket: Option<&str>, session: &str) -> Result<(), TmuxError> {
+        self.existing_window(socket, session)?;
+        self.update_window(socket, session, |window| window.zoomed = true);
+        Ok(())
+    }
+
+    fn supports_popup(&self) -> bool {
+        true
+    }
+
+    /// Closes straight away, as if detached from at once
+    fn attach_in_popup(&self, socket: Option<&str>, name: &str) -> Result<(), TmuxError> {
+        self.existing_window(socket, name).map(drop)
+    }
+
+    /// Nothing is launched; only a missing session fails
+    fn open_in_terminal(
+        &self,
+        _terminal: &str,
+        socket: Option<&str>,
+        name: &str,
+    ) -> Result<(), String> {
+        self.existing_window(socket, name)
+            .map(drop)
+            .map_err(|e| e.to_string())
+    }
+}
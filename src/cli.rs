@@ -0,0 +1,119 @@
+use std::path::PathBuf;
+
+/// Command-line options
+#[derive(Debug, Clone, Default)]
+pub struct Args {
+    /// Render without colors (also enabled by a non-empty `NO_COLOR`)
+    pub no_color: bool,
+    /// Report destructive operations instead of running them
+    pub dry_run: bool,
+    /// Exit with a nonzero code when quitting without attaching
+    pub require_attach: bool,
+    /// Start in the create prompt, prefilled with the name if one was given
+    pub new: Option<Option<String>>,
+    /// With `--new NAME`, create and attach right away instead of prompting
+    pub attach: bool,
+    /// Attach to the session with this name, or the only one starting with it
+    pub attach_to: Option<String>,
+    /// Start by confirming the kill of every detached session
+    pub kill_detached: bool,
+    /// Print Ursa's and tmux's versions instead of starting
+    pub version: bool,
+    /// Answer JSON requests on stdin instead of starting the TUI
+    pub server: bool,
+    /// Manage the tmux sessions on this SSH destination (`user@host`)
+    pub remote: Option<String>,
+    /// Append a debug log to this file (`$URSA_LOG` when not given)
+    pub log: Option<PathBuf>,
+}
+
+pub const USAGE: &str = "\
+Usage: ursa [OPTIONS]
+
+Options:
+      --new [NAME]      Start in the create prompt, prefilled with NAME
+      --attach          With --new NAME, create and attach without prompting
+      --attach NAME     Attach to NAME, or the only session starting with it
+      --kill-detached   Start by offering to kill every detached session
+      --no-color        Disable colors (also honors NO_COLOR)
+      --dry-run         Show what delete/kill/rename would do without doing it
+      --require-attach  Exit with code 4 when quitting without attaching
+      --remote HOST     Manage the sessions on HOST (user@host) over SSH
+      --log PATH        Append a debug log to PATH (also set by URSA_LOG)
+      --server          Answer JSON requests on stdin, one per line, without a TUI
+  -V, --version         Print the Ursa and tmux versions
+  -h, --help            Print help
+
+Exit codes: 0 ok, 1 error, 2 bad arguments, 3 attach failed,
+            4 quit without attaching (with --require-attach)";
+
+impl Args {
+    pub fn parse() -> Result<Self, String> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter().peekable();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--new" => {
+                    // The name is optional, so only take a following non-flag
+                    let name = args.next_if(|next| !next.starts_with('-'));
+                    parsed.new = Some(name);
+                }
+                // With a name it attaches to an existing session instead
+                "--attach" => match args.next_if(|next| !next.starts_with('-')) {
+                    Some(name) => parsed.attach_to = Some(name),
+                    None => parsed.attach = true,
+                },
+                "--kill-detached" => parsed.kill_detached = true,
+                "--no-color" => parsed.no_color = true,
+                "--dry-run" => parsed.dry_run = true,
+                "--require-attach" => parsed.require_attach = true,
+                "-V" | "--version" => parsed.version = true,
+                "--server" => parsed.server = true,
+                "--remote" => match args.next() {
+                    Some(host) if !host.starts_with('-') && !host.trim().is_empty() => {
+                        parsed.remote = Some(host);
+                    }
+                    _ => return Err(format!("--remote needs a host\n\n{}", USAGE)),
+                },
+                "--log" => match args.next() {
+                    Some(path) if !path.starts_with('-') && !path.trim().is_empty() => {
+                        parsed.log = Some(PathBuf::from(path));
+                    }
+                    _ => return Err(format!("--log needs a path\n\n{}", USAGE)),
+                },
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                other => return Err(format!("Unknown argument: {}\n\n{}", other, USAGE)),
+            }
+        }
+
+        if parsed.attach_to.is_some() && parsed.new.is_some() {
+            return Err(format!("--attach NAME can't be combined with --new\n\n{}", USAGE));
+        }
+        let starts_tui = parsed.new.is_some() || parsed.attach_to.is_some() || parsed.kill_detached;
+        if parsed.server && starts_tui {
+            return Err(format!(
+                "--server can't be combined with --new, --attach or --kill-detached\n\n{}",
+                USAGE
+            ));
+        }
+        if parsed.attach && !matches!(parsed.new, Some(Some(_))) {
+            return Err(format!("--attach needs --new NAME\n\n{}", USAGE));
+        }
+
+        Ok(parsed)
+    }
+
+    /// Whether the UI should use colors, honoring https://no-color.org
+    pub fn use_color(&self) -> bool {
+        let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        !self.no_color && !no_color_env
+    }
+}
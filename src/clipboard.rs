@@ -0,0 +1,58 @@
+//! Copying text to the system clipboard through whichever tool is installed.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Clipboard tools tried in order when no command is configured, with the
+/// arguments that make them read the text from stdin
+const TOOLS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+];
+
+/// Put `text` on the clipboard. `command` (run with `sh -c`, reading the text
+/// on stdin) takes precedence over the tools Ursa knows about.
+pub fn copy(text: &str, command: Option<&str>) -> Result<(), String> {
+    if let Some(command) = command.filter(|cmd| !cmd.trim().is_empty()) {
+        let mut sh = Command::new("sh");
+        sh.args(["-c", command]);
+        return pipe(sh, text).map_err(|e| format!("Clipboard command failed: {}", e));
+    }
+
+    // A tool that is installed but can't reach a display (e.g. wl-copy under
+    // X11) is skipped for the next one, but its error is kept to report
+    let mut failure = None;
+    for (program, args) in TOOLS {
+        let mut tool = Command::new(program);
+        tool.args(*args);
+        match pipe(tool, text) {
+            Ok(()) => return Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => failure = Some(format!("{} failed: {}", program, e)),
+        }
+    }
+    Err(failure.unwrap_or_else(|| {
+        "No clipboard tool found: install wl-copy, xclip or xsel, or set `clipboard` in config.toml"
+            .to_string()
+    }))
+}
+
+/// Run `command` with `text` on its stdin, failing on a non-zero exit
+fn pipe(mut command: Command, text: &str) -> std::io::Result<()> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!("exited with {}", status)))
+    }
+}
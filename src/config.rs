@@ -0,0 +1,324 @@
+//! User preferences loaded from `~/.config/ursa/config.toml`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ratatui::style::Color;
+use serde::Deserialize;
+
+use crate::app::{SessionAction, DEFAULT_ACTIONS};
+use crate::input::InputMode;
+
+/// What `E` starts a missing config file with. Every setting is commented
+/// out, so the file behaves exactly like having none.
+pub const CONFIG_TEMPLATE: &str = "\
+# Ursa configuration. Uncomment a line to change it from its default;
+# every setting is described in the README's Configuration section.
+# Save and quit the editor to apply, or press R later to reload.
+
+# row_style = \"inline\"
+# row_spacing = 0
+# minimal_buttons = false
+# paginate = false
+# wrap_actions = false
+# auto_refresh_ms = 0
+# hidden = []
+";
+
+/// `row_format` that reproduces the built-in inline row
+pub const DEFAULT_ROW_FORMAT: &str = "{name} [{windows}] {attached}";
+
+/// Preferences read from the config file; missing keys use the defaults
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// How long the event loop waits for input before waking up, in
+    /// milliseconds. Lower values make time-based updates snappier at the cost
+    /// of more wakeups (and battery) while idle; key presses are always handled
+    /// immediately regardless of this value.
+    pub poll_interval_ms: u64,
+    /// Re-read the session list this often, in milliseconds; 0, the
+    /// default, disables it
+    pub auto_refresh_ms: u64,
+    /// Refresh when tmux reports a change, through a control-mode client
+    /// (`tmux -C`) on each server, instead of every `auto_refresh_ms`.
+    /// Polling, when `auto_refresh_ms` is set, takes over and retries control
+    /// mode while it can't run.
+    pub control_mode: bool,
+    /// Refresh as soon as the terminal regains focus, and skip auto-refresh
+    /// while it's in the background. Needs a terminal that reports focus
+    /// changes; others simply never trigger it.
+    pub refresh_on_focus: bool,
+    /// How session rows are laid out; narrow terminals always use compact
+    pub row_style: RowStyle,
+    /// What inline rows show before their action buttons, with `{name}`,
+    /// `{windows}`, `{path}`, `{activity}` and `{attached}` filled in. Unknown
+    /// tokens are shown as typed.
+    pub row_format: String,
+    /// Show the list a page at a time ("Page 2/5" below it) instead of
+    /// scrolling it row by row
+    pub paginate: bool,
+    /// Blank lines between session rows; 0 keeps the list compact
+    pub row_spacing: u16,
+    /// Show the action buttons on the selected row only, leaving the others
+    /// with just their name and details
+    pub minimal_buttons: bool,
+    /// Sessions left out of the list unless `.` shows them, by name or by a
+    /// prefix ending in `*` (e.g. "infra-*")
+    pub hidden: Vec<String>,
+    /// Row actions offered by ←/→, in order; leave one out to hide it. Rows
+    /// start on Enter wherever it's listed.
+    pub actions: Vec<SessionAction>,
+    /// Let ←/→ wrap between the first and last row action instead of stopping
+    pub wrap_actions: bool,
+    /// Ask before renaming a session a client is attached to, since the name
+    /// changes under that client (e.g. in its status line)
+    pub confirm_attached_rename: bool,
+    /// Color of the bar marking attached sessions' rows, as a name ("green",
+    /// "lightblue"), an index ("208") or "#rrggbb"; empty hides the bar
+    pub attached_color: String,
+    /// Sessions without activity for this many hours are dimmed in the list
+    /// and offered for cleanup by `I`; 0 turns this off
+    pub idle_hours: u64,
+    /// Window count from which a session's count is drawn in yellow
+    pub busy_windows: u32,
+    /// Window count from which a session's count is drawn in red
+    pub heavy_windows: u32,
+    /// tmux sockets (as for `tmux -L`, "default" being tmux's own) whose
+    /// sessions are merged into one list; empty lists only the current server.
+    /// New sessions are created on the first one.
+    pub sockets: Vec<String>,
+    /// Pane layouts offered when creating a session (cycled with Ctrl-L)
+    pub layouts: Vec<LayoutPreset>,
+    /// Command prefix used to open a session in a new terminal window, e.g.
+    /// "alacritty -e". Falls back to `$TERMINAL -e` when unset.
+    pub terminal: Option<String>,
+    /// Command that copies its stdin to the clipboard (run with `sh -c`), e.g.
+    /// "wl-copy". Unset tries pbcopy, wl-copy, xclip and xsel in turn.
+    pub clipboard: Option<String>,
+    /// Quit after this many seconds without a key press, for when Ursa is a
+    /// throwaway chooser (e.g. bound to a hotkey); 0 never quits
+    pub quit_after_idle_secs: u64,
+    /// Inside tmux, keep the list running after switching the client to a
+    /// session instead of quitting. Outside tmux Ursa replaces itself with
+    /// `tmux attach`, so it always exits there.
+    pub stay_open: bool,
+    /// Attach to a session right after creating it; off stays in the list
+    /// with the new session selected, for setting up several in a row
+    pub attach_on_create: bool,
+    /// Refuse to create sessions once this many are running, with a warning
+    /// in the list until some are killed; 0 is unlimited
+    pub max_sessions: usize,
+    /// tmux executable to run, as a name on PATH or a full path (e.g. a
+    /// wrapper script). `$URSA_TMUX` overrides it.
+    pub tmux_bin: String,
+    /// Shell commands run when sessions are attached to, created or deleted
+    pub hooks: Hooks,
+    /// Keys typed into a session right after attaching to it, by session
+    /// name. Each entry is one `send-keys` argument: text, or a key name such
+    /// as "Enter" or "C-c".
+    pub macros: HashMap<String, Vec<String>>,
+    /// Editing keys in the prompts: "emacs" (readline's Ctrl keys) or "vi"
+    /// (Esc switches to a normal mode with h/l/w/b motions)
+    pub input_mode: InputMode,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: 250,
+            auto_refresh_ms: 0,
+            control_mode: false,
+            refresh_on_focus: true,
+            row_style: RowStyle::default(),
+            row_format: DEFAULT_ROW_FORMAT.to_string(),
+            paginate: false,
+            row_spacing: 0,
+            minimal_buttons: false,
+            hidden: Vec::new(),
+            actions: DEFAULT_ACTIONS.to_vec(),
+            wrap_actions: false,
+            confirm_attached_rename: false,
+            attached_color: "green".to_string(),
+            idle_hours: 24,
+            busy_windows: 5,
+            heavy_windows: 10,
+            sockets: Vec::new(),
+            layouts: LayoutPreset::defaults(),
+            terminal: None,
+            clipboard: None,
+            quit_after_idle_secs: 0,
+            stay_open: false,
+            attach_on_create: true,
+            max_sessions: 0,
+            tmux_bin: "tmux".to_string(),
+            hooks: Hooks::default(),
+            macros: HashMap::new(),
+            input_mode: InputMode::default(),
+        }
+    }
+}
+
+/// Layout of each session row in the list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RowStyle {
+    /// Name, metadata and action buttons on one line
+    #[default]
+    Inline,
+    /// Name and a short window count; only the selected action is shown
+    Compact,
+    /// Name on one line, metadata and action buttons indented below it
+    Detailed,
+}
+
+/// Commands run with `sh -c` on session events. The session name is passed
+/// as `$1` and in `$URSA_SESSION`, the event name in `$URSA_EVENT`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Hooks {
+    pub on_attach: Option<String>,
+    pub on_create: Option<String>,
+    pub on_delete: Option<String>,
+}
+
+/// A pane arrangement applied right after creating a session
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct LayoutPreset {
+    pub name: String,
+    /// Number of panes to split the first window into
+    pub panes: u32,
+    /// tmux layout passed to `select-layout` (e.g. "main-vertical", "tiled")
+    #[serde(default)]
+    pub layout: Option<String>,
+}
+
+impl LayoutPreset {
+    fn new(name: &str, panes: u32, layout: Option<&str>) -> Self {
+        Self {
+            name: name.to_string(),
+            panes,
+            layout: layout.map(str::to_string),
+        }
+    }
+
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::new("single", 1, None),
+            Self::new("even-horizontal", 2, Some("even-horizontal")),
+            Self::new("even-vertical", 2, Some("even-vertical")),
+            Self::new("main-vertical", 3, Some("main-vertical")),
+        ]
+    }
+}
+
+impl Config {
+    /// Load the config file, falling back to defaults when it doesn't exist
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = config_path() else {
+            return Ok(Self::default());
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents)
+                .map_err(|e| format!("Invalid config {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_millis(self.poll_interval_ms.max(1))
+    }
+
+    /// Interval between automatic refreshes, or None when disabled
+    pub fn auto_refresh_interval(&self) -> Option<Duration> {
+        (self.auto_refresh_ms > 0).then(|| Duration::from_millis(self.auto_refresh_ms))
+    }
+
+    /// Inactivity after which a session counts as long idle, or None when
+    /// disabled
+    pub fn idle_threshold(&self) -> Option<Duration> {
+        (self.idle_hours > 0).then(|| Duration::from_secs(self.idle_hours * 3600))
+    }
+
+    /// Color of the attached-session bar, or None when hidden or not a color
+    /// ratatui knows
+    pub fn attached_color(&self) -> Option<Color> {
+        let color = self.attached_color.trim();
+        (!color.is_empty()).then(|| color.parse().ok()).flatten()
+    }
+
+    /// How long Ursa waits for a key before quitting, or None when it waits
+    /// forever
+    pub fn idle_quit_after(&self) -> Option<Duration> {
+        (self.quit_after_idle_secs > 0).then(|| Duration::from_secs(self.quit_after_idle_secs))
+    }
+
+    /// The keys to send `name` on attaching, if any are configured
+    pub fn attach_macro(&self, name: &str) -> Option<&[String]> {
+        self.macros
+            .get(name)
+            .map(Vec::as_slice)
+            .filter(|keys| !keys.is_empty())
+    }
+
+    /// Whether `hidden` leaves the session `name` out of the list
+    pub fn hides(&self, name: &str) -> bool {
+        self.hidden
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == pattern,
+            })
+    }
+
+    /// The tmux executable: `$URSA_TMUX` when set, else `tmux_bin`
+    pub fn tmux_binary(&self) -> String {
+        std::env::var("URSA_TMUX")
+            .ok()
+            .filter(|bin| !bin.trim().is_empty())
+            .unwrap_or_else(|| self.tmux_bin.clone())
+    }
+
+    /// The terminal command prefix from the config, or `$TERMINAL -e`
+    pub fn terminal_command(&self) -> Option<String> {
+        self.terminal
+            .clone()
+            .filter(|cmd| !cmd.trim().is_empty())
+            .or_else(|| {
+                std::env::var("TERMINAL")
+                    .ok()
+                    .filter(|term| !term.trim().is_empty())
+                    .map(|term| format!("{} -e", term.trim()))
+            })
+    }
+}
+
+/// `$XDG_CONFIG_HOME/ursa`, or `~/.config/ursa`
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("ursa"))
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+/// Create the config file at `path` from [`CONFIG_TEMPLATE`] unless there's
+/// one already
+pub fn ensure_config_file(path: &Path) -> Result<(), String> {
+    if path.exists() {
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    std::fs::write(path, CONFIG_TEMPLATE)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))
+}
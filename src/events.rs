@@ -0,0 +1,113 @@
+//! Refreshing when tmux says something changed instead of on a timer: a
+//! control-mode client on each server passes on the notifications that
+//! affect the session list.
+
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+
+use crate::tmux::{self, ControlClient, TmuxError};
+
+/// What the control clients reported since last asked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Changes {
+    None,
+    /// The session list may read differently now
+    Some,
+    /// A client exited, e.g. with its server or the session it was attached
+    /// to, so changes are no longer all being heard about
+    Lost,
+    /// A client couldn't start, e.g. because its server isn't running or has
+    /// no sessions yet
+    Unavailable,
+}
+
+enum Notice {
+    /// A client is attached and reading notifications from now on
+    Started(ControlClient),
+    Failed(TmuxError),
+    Changed,
+    Exited,
+}
+
+/// Control clients on every watched server, reading their notifications in
+/// the background. Dropping it ends them.
+pub struct EventWatcher {
+    clients: Vec<ControlClient>,
+    /// Clients still starting up
+    pending: usize,
+    receiver: Receiver<Notice>,
+}
+
+impl EventWatcher {
+    /// Start a control client on each of `sockets`, or on the current server
+    /// when there are none. They start in the background, so a server or ssh
+    /// that's slow to answer doesn't hold up the caller; [`changes`] reports
+    /// `Unavailable` when any of them fails to.
+    ///
+    /// [`changes`]: EventWatcher::changes
+    pub fn start(sockets: &[String]) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        if sockets.is_empty() {
+            watch(None, sender);
+        } else {
+            for socket in sockets {
+                watch(Some(socket.clone()), sender.clone());
+            }
+        }
+        Self {
+            clients: Vec::new(),
+            pending: sockets.len().max(1),
+            receiver,
+        }
+    }
+
+    /// Whether every client has started, so changes are all being heard about
+    pub fn is_watching(&self) -> bool {
+        self.pending == 0
+    }
+
+    /// Everything reported since the last call, summed up
+    pub fn changes(&mut self) -> Changes {
+        let mut changes = Changes::None;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Notice::Started(client)) => {
+                    log::info!("watching for changes as control client {}", client.name());
+                    self.clients.push(client);
+                    self.pending -= 1;
+                }
+                Ok(Notice::Failed(e)) => {
+                    log::info!("control mode unavailable, polling instead: {}", e);
+                    return Changes::Unavailable;
+                }
+                Ok(Notice::Changed) => changes = Changes::Some,
+                Ok(Notice::Exited) | Err(TryRecvError::Disconnected) => return Changes::Lost,
+                Err(TryRecvError::Empty) => return changes,
+            }
+        }
+    }
+}
+
+/// Start a client on the server on `socket` on a thread of its own, which
+/// hands it back and then passes on what it prints
+fn watch(socket: Option<String>, sender: Sender<Notice>) {
+    std::thread::spawn(move || {
+        let lines = match tmux::control_client(socket.as_deref()) {
+            Ok((client, lines)) => {
+                if sender.send(Notice::Started(client)).is_err() {
+                    return;
+                }
+                lines
+            }
+            Err(e) => {
+                let _ = sender.send(Notice::Failed(e));
+                return;
+            }
+        };
+        for line in lines.map_while(Result::ok) {
+            if tmux::changes_session_list(&line) && sender.send(Notice::Changed).is_err() {
+                return;
+            }
+        }
+        let _ = sender.send(Notice::Exited);
+    });
+}
@@ -0,0 +1,283 @@
+//! Single-line text input for the prompts, with readline-style editing and
+//! an optional vi-style normal mode.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+/// How the prompts edit text, from the `input_mode` setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputMode {
+    /// Always typing, with readline's Ctrl keys
+    #[default]
+    Emacs,
+    /// Typing as in emacs mode until Esc, which switches to a normal mode
+    /// where letters move the cursor; `i` or `a` goes back to typing
+    Vi,
+}
+
+/// Text typed into a prompt along with the cursor position
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextInput {
+    text: String,
+    /// Cursor position in characters, from 0 to the text's length
+    cursor: usize,
+    /// In vi mode's normal mode rather than typing
+    normal: bool,
+}
+
+impl TextInput {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    /// Replace the text and move the cursor to the end
+    pub fn set(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        self.cursor = self.text.chars().count();
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.cursor == self.text.chars().count()
+    }
+
+    /// The text before and after the cursor
+    pub fn split_at_cursor(&self) -> (&str, &str) {
+        self.text.split_at(self.byte_index(self.cursor))
+    }
+
+    /// Apply an editing key, returning false when the key isn't an edit so the
+    /// prompt can handle it. Only characters passing `accept` can be typed.
+    ///
+    /// Besides typing, Backspace/Delete and ←/→, this supports Ctrl-U (clear),
+    /// Ctrl-W (delete previous word), Ctrl-K (delete to the end), and
+    /// Ctrl-A/Home and Ctrl-E/End.
+    pub fn handle_key(&mut self, key: KeyEvent, accept: impl Fn(char) -> bool) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('u') if ctrl => self.clear(),
+            KeyCode::Char('w') if ctrl => self.delete_word(),
+            KeyCode::Char('k') if ctrl => {
+                let at = self.byte_index(self.cursor);
+                self.text.truncate(at);
+            }
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.text.chars().count(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.chars().count(),
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right if !self.at_end() => self.cursor += 1,
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.text.remove(self.byte_index(self.cursor));
+            }
+            KeyCode::Delete if !self.at_end() => {
+                self.text.remove(self.byte_index(self.cursor));
+            }
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+                    && accept(c) =>
+            {
+                self.text.insert(self.byte_index(self.cursor), c);
+                self.cursor += 1;
+            }
+            // Keep editing keys from falling through to the prompt at the edges
+            KeyCode::Backspace | KeyCode::Delete => {}
+            _ => return false,
+        }
+        true
+    }
+
+    /// Whether vi mode's normal mode is on, where letters are commands
+    pub fn is_normal_mode(&self) -> bool {
+        self.normal
+    }
+
+    /// Back to typing, e.g. when another prompt opens
+    pub fn start_inserting(&mut self) {
+        self.normal = false;
+    }
+
+    /// The keys vi mode handles before the prompt does, returning whether the
+    /// key was used. While typing that's only Esc, which switches to normal
+    /// mode. In normal mode h/l, w/b, 0/$ and ←/→ move, x deletes, and i/a
+    /// (I/A at the ends) go back to typing; other characters are swallowed
+    /// so they aren't typed. Enter, Esc, ↑/↓ and Ctrl keys always reach the
+    /// prompt, so Esc in normal mode cancels it.
+    pub fn handle_vi_key(&mut self, key: KeyEvent) -> bool {
+        if !self.normal {
+            if key.code != KeyCode::Esc {
+                return false;
+            }
+            // As in vi, the cursor steps back onto the last character typed
+            self.normal = true;
+            self.cursor = self.cursor.saturating_sub(1);
+            return true;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            return false;
+        }
+        let last = self.text.chars().count().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('h') | KeyCode::Left | KeyCode::Backspace => {
+                self.cursor = self.cursor.saturating_sub(1);
+            }
+            KeyCode::Char('l') | KeyCode::Right => self.cursor = (self.cursor + 1).min(last),
+            KeyCode::Char('w') => self.cursor = self.next_word_start().min(last),
+            KeyCode::Char('b') => self.cursor = self.word_start_before(),
+            KeyCode::Char('0') | KeyCode::Home => self.cursor = 0,
+            KeyCode::Char('$') | KeyCode::End => self.cursor = last,
+            KeyCode::Char('x') | KeyCode::Delete => {
+                if self.cursor < self.text.chars().count() {
+                    self.text.remove(self.byte_index(self.cursor));
+                }
+                self.cursor = self.cursor.min(self.text.chars().count().saturating_sub(1));
+            }
+            KeyCode::Char('i') => self.normal = false,
+            KeyCode::Char('a') => {
+                self.normal = false;
+                self.cursor = (self.cursor + 1).min(self.text.chars().count());
+            }
+            KeyCode::Char('I') => {
+                self.normal = false;
+                self.cursor = 0;
+            }
+            KeyCode::Char('A') => {
+                self.normal = false;
+                self.cursor = self.text.chars().count();
+            }
+            KeyCode::Char(_) => {}
+            _ => return false,
+        }
+        true
+    }
+
+    /// Delete back to the start of the previous word, treating punctuation
+    /// such as `/`, `-` and `_` as word boundaries
+    fn delete_word(&mut self) {
+        let start = self.word_start_before();
+        let range = self.byte_index(start)..self.byte_index(self.cursor);
+        self.text.replace_range(range, "");
+        self.cursor = start;
+    }
+
+    /// Where the word before the cursor starts, skipping punctuation first
+    fn word_start_before(&self) -> usize {
+        let before: Vec<char> = self.text.chars().take(self.cursor).collect();
+        let mut start = before.len();
+        while start > 0 && !before[start - 1].is_alphanumeric() {
+            start -= 1;
+        }
+        while start > 0 && before[start - 1].is_alphanumeric() {
+            start -= 1;
+        }
+        start
+    }
+
+    /// Where the next word after the cursor starts, or the end of the text
+    fn next_word_start(&self) -> usize {
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut end = self.cursor;
+        while end < chars.len() && chars[end].is_alphanumeric() {
+            end += 1;
+        }
+        while end < chars.len() && !chars[end].is_alphanumeric() {
+            end += 1;
+        }
+        end
+    }
+
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(char_index)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+}
+
+/// Most entries a prompt's history keeps; older ones are dropped
+const HISTORY_CAPACITY: usize = 50;
+
+/// Entries submitted in a prompt, oldest first, recalled with ↑/↓ like a
+/// shell's history
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct History {
+    entries: Vec<String>,
+    /// Entry currently recalled, None while editing fresh text
+    position: Option<usize>,
+    /// What was typed before the first ↑, given back by ↓ past the newest entry
+    draft: String,
+}
+
+impl History {
+    /// A history holding `entries` (oldest first), e.g. as saved last run
+    pub fn new(mut entries: Vec<String>) -> Self {
+        let excess = entries.len().saturating_sub(HISTORY_CAPACITY);
+        entries.drain(..excess);
+        Self {
+            entries,
+            ..Self::default()
+        }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Record a submitted entry. Blank entries and repeats of the newest one
+    /// aren't kept.
+    pub fn push(&mut self, entry: &str) {
+        self.position = None;
+        let entry = entry.trim();
+        if entry.is_empty() || self.entries.last().is_some_and(|last| last == entry) {
+            return;
+        }
+        if self.entries.len() == HISTORY_CAPACITY {
+            self.entries.remove(0);
+        }
+        self.entries.push(entry.to_string());
+    }
+
+    /// Go back to editing fresh text, e.g. when the prompt opens again
+    pub fn reset(&mut self) {
+        self.position = None;
+    }
+
+    /// The entry before the one recalled, starting from the newest; `current`
+    /// is kept as the draft when leaving fresh text. Stops at the oldest.
+    pub fn older(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            None => {
+                self.draft = current.to_string();
+                self.entries.len().checked_sub(1)?
+            }
+            Some(position) => position.saturating_sub(1),
+        };
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// The entry after the one recalled, or the draft past the newest; None
+    /// when nothing is recalled
+    pub fn newer(&mut self) -> Option<&str> {
+        let position = self.position?;
+        if position + 1 < self.entries.len() {
+            self.position = Some(position + 1);
+            Some(&self.entries[position + 1])
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! Session logic behind the `ursa` tmux session manager.
+//!
+//! - [`tmux`] wraps the tmux commands Ursa runs (listing, creating, renaming,
+//!   killing and attaching to sessions).
+//! - [`app`] holds the [`App`](app::App) state machine that the TUI drives with
+//!   key events; it can be used without a terminal.
+//! - [`clipboard`] copies text (such as attach commands) to the system
+//!   clipboard.
+//! - [`backend`] puts the session operations [`App`](app::App) runs behind
+//!   a trait, with an in-memory fake for tests.
+//! - [`logging`] writes a debug log of tmux commands and screen changes.
+//! - [`server`] answers the JSON requests of `ursa --server`.
+//! - [`events`] hears about session changes from tmux's control mode, for
+//!   refreshing without polling.
+//! - [`signals`] decides when SIGINT, SIGTERM and SIGHUP stop Ursa.
+//!
+//! ```no_run
+//! match ursa::tmux::list_sessions(None) {
+//!     Ok(sessions) => {
+//!         for session in &sessions {
+//!             println!("{} ({} windows)", session.name, session.windows);
+//!         }
+//!     }
+//!     Err(ursa::tmux::TmuxError::NoServer) => println!("no tmux server running"),
+//!     Err(e) => eprintln!("{}", e),
+//! }
+//! ```
+
+pub mod app;
+pub mod backend;
+pub mod clipboard;
+pub mod config;
+pub mod events;
+pub mod input;
+pub mod logging;
+pub mod server;
+pub mod signals;
+pub mod state;
+pub mod store;
+pub mod tags;
+pub mod theme;
+pub mod tmux;
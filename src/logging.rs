@@ -0,0 +1,68 @@
+//! Debug log written to a file given with `--log` or `$URSA_LOG`.
+//!
+//! Records every tmux command with its exit status and stderr, plus screen
+//! changes and errors shown in the UI. Nothing goes to stdout or stderr, which
+//! belong to the TUI while it runs.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Appends one line per record to the log file
+struct FileLogger {
+    file: Mutex<File>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Only Ursa's own records; dependencies may log through the facade too
+        metadata.target().starts_with("ursa")
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        // A failed write has nowhere to be reported without disturbing the TUI
+        let _ = writeln!(
+            file,
+            "{}.{:03} {:<5} {}: {}",
+            now.as_secs(),
+            now.subsec_millis(),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+    }
+
+    fn flush(&self) {
+        let _ = self.file.lock().unwrap_or_else(|e| e.into_inner()).flush();
+    }
+}
+
+/// Start appending debug records to `path`, creating the file if needed.
+/// Only the first call in a process takes effect.
+pub fn init(path: &Path) -> Result<(), String> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open log file {}: {}", path.display(), e))?;
+
+    // The logger lives as long as the process
+    let logger = Box::leak(Box::new(FileLogger {
+        file: Mutex::new(file),
+    }));
+    log::set_logger(logger).map_err(|_| "A logger is already installed".to_string())?;
+    log::set_max_level(LevelFilter::Debug);
+    log::info!("ursa {} started", env!("CARGO_PKG_VERSION"));
+    Ok(())
+}
@@ -1,89 +1,374 @@
-mod app;
-mod tmux;
+mod cli;
 mod ui;
 
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::time::Duration;
 
-use app::{App, AppAction};
+use cli::Args;
 use color_eyre::Result;
-use crossterm::event::{self, Event, KeyEventKind};
+use crossterm::event::{self, DisableFocusChange, EnableFocusChange, Event, KeyEventKind};
+use crossterm::execute;
 use ratatui::DefaultTerminal;
+use ursa::app::{resolve_session_prefix, App, AppAction, CleanupKind, HookEvent};
+use ursa::backend::SystemTmux;
+use ursa::config::Config;
+use ursa::logging;
+use ursa::server::Server;
+use ursa::signals;
+use ursa::tmux;
 
 #[cfg(unix)]
 use std::os::unix::process::CommandExt;
 
+/// Process exit codes, so shell wrappers can tell outcomes apart
+const EXIT_ERROR: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_ATTACH_FAILED: i32 = 3;
+/// Quit without attaching while `--require-attach` was given
+const EXIT_NO_ATTACH: i32 = 4;
+
 fn main() -> Result<()> {
     color_eyre::install()?;
 
-    let mut terminal = ratatui::init();
-    let result = run(&mut terminal);
-    ratatui::restore();
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+
+    // Before anything runs tmux, so every command goes over SSH and is logged
+    tmux::set_remote(args.remote.as_deref());
+    let log_path = args.log.clone().or_else(|| {
+        std::env::var_os("URSA_LOG")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+    });
+    if let Some(path) = log_path {
+        // The TUI hasn't taken the terminal yet, so this can still be printed
+        if let Err(e) = logging::init(&path) {
+            eprintln!("Warning: {}", e);
+        }
+    }
+
+    if args.version {
+        print_version();
+        return Ok(());
+    }
+
+    if args.server {
+        return run_server();
+    }
+
+    // Detect color support once, before the TUI takes over the terminal
+    let use_color = args.use_color();
+
+    let mut app = App::new();
+    // The color scheme comes from the saved state
+    app.theme.color = use_color;
+    app.dry_run = args.dry_run;
+
+    // These act on the session list, so it has to be in first
+    if args.kill_detached || args.new.is_some() || args.attach_to.is_some() {
+        app.wait_for_sessions();
+    }
+
+    if let Some(prefix) = &args.attach_to {
+        match resolve_session_prefix(&app.sessions, prefix) {
+            Ok(name) => {
+                // A name on several servers goes to the first one listed
+                let socket = app
+                    .sessions
+                    .iter()
+                    .find(|s| s.name == name)
+                    .and_then(|s| s.socket.clone());
+                app.action = AppAction::AttachSession { name, socket };
+            }
+            Err(candidates) if candidates.is_empty() => {
+                eprintln!("Error: No session matches '{}'", prefix);
+                std::process::exit(EXIT_ATTACH_FAILED);
+            }
+            Err(candidates) => {
+                eprintln!(
+                    "Error: '{}' matches several sessions: {}",
+                    prefix,
+                    candidates.join(", ")
+                );
+                std::process::exit(EXIT_ATTACH_FAILED);
+            }
+        }
+    }
+
+    if args.kill_detached {
+        app.start_cleanup(CleanupKind::Detached);
+    }
+
+    if let Some(name) = &args.new {
+        app.start_creating(name.as_deref());
+        if args.attach {
+            app.confirm_create();
+        }
+    }
+
+    // `--new NAME --attach` and `--attach NAME` skip the TUI entirely unless
+    // the name was rejected
+    let result = if let AppAction::AttachSession { .. } = &app.action {
+        Ok(Some(app.action.clone()))
+    } else {
+        let mut terminal = init_terminal(&app);
+        restore_on_signal();
+        let result = run(&mut terminal, &mut app);
+        restore_terminal();
+        result
+    };
+
+    if let Err(e) = app.saved_state().save() {
+        eprintln!("Warning: {}", e);
+    }
+
+    if let Some(signal) = signals::deferred() {
+        std::process::exit(128 + signal);
+    }
 
     // Handle post-TUI actions (attaching to session)
-    if let Ok(Some(AppAction::AttachSession(name))) = result {
-        attach_to_session(&name);
-    } else if let Err(e) = result {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
+    match result {
+        Ok(Some(AppAction::AttachSession { name, socket })) => {
+            app.run_hook(HookEvent::Attach, &name);
+            if let Err(e) = app.send_attach_macro(socket.as_deref(), &name) {
+                eprintln!("Warning: {}", e);
+            }
+            attach_to_session(socket.as_deref(), &name);
+        }
+        Ok(_) if args.require_attach && !app.switched => std::process::exit(EXIT_NO_ATTACH),
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
     }
 
     Ok(())
 }
 
+/// `--server`: answer JSON requests on stdin until it closes
+fn run_server() -> Result<()> {
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_ERROR);
+        }
+    };
+    tmux::set_binary(Some(&config.tmux_binary()));
+    let server = Server::new(&SystemTmux, &config.sockets);
+    server.serve(std::io::stdin().lock(), std::io::stdout().lock())?;
+    Ok(())
+}
+
+/// Ursa's version and the version of the tmux it would run, for bug reports
+fn print_version() {
+    println!("ursa {}", env!("CARGO_PKG_VERSION"));
+    let config = Config::load().unwrap_or_default();
+    tmux::set_binary(Some(&config.tmux_binary()));
+    match tmux::check_binary() {
+        Ok(version) if tmux::remote().is_some() => {
+            println!("{} (on {})", version, tmux::remote().unwrap_or_default());
+        }
+        Ok(version) if tmux::binary() == "tmux" => println!("{}", version),
+        Ok(version) => println!("{} ({})", version, tmux::binary()),
+        Err(e) => println!("{}", e),
+    }
+}
+
 /// Attach to a tmux session, using exec when outside tmux for reliable attachment
-fn attach_to_session(name: &str) {
+fn attach_to_session(socket: Option<&str>, name: &str) {
     if tmux::is_inside_tmux() {
         // Inside tmux: use switch-client (doesn't need exec)
-        if let Err(e) = tmux::attach_session(name) {
-            eprintln!("Error: {}", e);
-            std::process::exit(1);
+        match tmux::switch_client(socket, name) {
+            Ok(()) => return,
+            // A stale TMUX variable (e.g. inherited from a launcher) leaves no
+            // client to switch; fall through to a direct attach instead
+            Err(e) => eprintln!("Warning: {}; attaching directly instead", e),
         }
-    } else {
-        // Outside tmux: use exec to replace this process with tmux attach
-        // This gives tmux full control of the terminal
-        #[cfg(unix)]
-        {
-            let err = Command::new("tmux")
-                .args(["attach-session", "-t", name])
-                .exec();
-            // exec only returns on error
-            eprintln!("Error: Failed to attach to session: {}", err);
-            std::process::exit(1);
-        }
-
-        #[cfg(not(unix))]
-        {
-            if let Err(e) = tmux::attach_session(name) {
+    }
+
+    // Outside tmux: use exec to replace this process with tmux attach (or
+    // ssh running it). This gives tmux full control of the terminal
+    #[cfg(unix)]
+    {
+        let argv = tmux::attach_argv(socket, name);
+        log::info!("exec {:?}", argv);
+        let err = Command::new(&argv[0])
+            .args(&argv[1..])
+            .env_remove("TMUX")
+            .exec();
+        // exec only returns on error
+        log::warn!("exec failed: {}", err);
+        eprintln!("Error: Failed to attach to session: {}", err);
+    }
+
+    // Without exec, run attach as a child; this may not behave in every
+    // environment (e.g. tmux reached through WSL from Windows)
+    #[cfg(not(unix))]
+    {
+        match tmux::attach_client(socket, name) {
+            Ok(()) => return,
+            Err(e) => {
                 eprintln!("Error: {}", e);
-                std::process::exit(1);
+                eprintln!("Automatic attach is not fully supported on this platform.");
             }
         }
     }
-}
 
-fn run(terminal: &mut DefaultTerminal) -> Result<Option<AppAction>> {
-    let mut app = App::new();
+    eprintln!("You can attach manually with:");
+    eprintln!("    {}", tmux::attach_command(socket, name));
+    std::process::exit(EXIT_ATTACH_FAILED);
+}
 
+fn run(terminal: &mut DefaultTerminal, app: &mut App) -> Result<Option<AppAction>> {
     loop {
-        terminal.draw(|frame| ui::render(frame, &app))?;
+        terminal.draw(|frame| ui::render(frame, app))?;
 
-        // Poll for events with a timeout to allow for potential refresh
-        if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
+        // Block for input until the app's next timed update is due
+        if event::poll(app.poll_timeout())? {
+            match event::read()? {
                 // Only handle key press events (not release)
-                if key.kind == KeyEventKind::Press {
-                    app.handle_key(key);
-                }
+                Event::Key(key) if key.kind == KeyEventKind::Press => app.handle_key(key),
+                Event::FocusGained => app.set_focused(true),
+                Event::FocusLost => app.set_focused(false),
+                _ => {}
             }
         }
 
+        app.tick();
+
+        // A signal waits for a renumber to finish, the same as Ctrl-C
+        if signals::deferred().is_some() {
+            app.quit();
+        }
+        signals::set_renumbering(app.is_renumbering());
+
         if app.should_quit {
             return Ok(Some(AppAction::Quit));
         }
 
-        if let AppAction::AttachSession(name) = &app.action {
-            return Ok(Some(AppAction::AttachSession(name.clone())));
+        if let AppAction::OpenPager(path) = &app.action {
+            let path = path.clone();
+            app.action = AppAction::None;
+            if let Err(e) = page_file(terminal, app, &path) {
+                app.error_message = Some(e);
+            }
+            let _ = std::fs::remove_file(&path);
+        }
+
+        if let AppAction::EditConfig(path) = &app.action {
+            let path = path.clone();
+            app.action = AppAction::None;
+            match edit_file(terminal, app, &path) {
+                Ok(()) => app.reload_config(),
+                Err(e) => app.error_message = Some(e),
+            }
+        }
+
+        if let AppAction::AttachSession { name, socket } = &app.action {
+            let (name, socket) = (name.clone(), socket.clone());
+            // exec can't come back, so only switch-client keeps the list open
+            if app.config.stay_open && tmux::is_inside_tmux() {
+                app.switch_in_place(socket.as_deref(), &name);
+                continue;
+            }
+            return Ok(Some(AppAction::AttachSession { name, socket }));
         }
     }
 }
+
+/// Take over the terminal, with focus reporting when `refresh_on_focus` is set
+fn init_terminal(app: &App) -> DefaultTerminal {
+    let terminal = ratatui::init();
+    if app.config.refresh_on_focus {
+        let _ = execute!(std::io::stdout(), EnableFocusChange);
+    }
+    terminal
+}
+
+/// Hand the terminal back; focus reporting is switched off either way, since
+/// a config reload may have changed `refresh_on_focus` since `init_terminal`
+fn restore_terminal() {
+    let _ = execute!(std::io::stdout(), DisableFocusChange);
+    ratatui::restore();
+}
+
+/// Restore the terminal and exit when Ursa is told to stop (e.g. SIGTERM from
+/// a parent process), instead of leaving it in raw mode. Raw mode turns Ctrl-C
+/// into a key press, so SIGINT only arrives from outside too, except while a
+/// pager or editor has the terminal (see [`signals`]). Mid-renumber the event
+/// loop quits instead, once no session is left under a temporary name.
+#[cfg(unix)]
+fn restore_on_signal() {
+    let result = signals::handle(|signal| {
+        restore_terminal();
+        std::process::exit(128 + signal);
+    });
+    if let Err(e) = result {
+        log::warn!("failed to install signal handler: {}", e);
+    }
+}
+
+#[cfg(not(unix))]
+fn restore_on_signal() {}
+
+/// Open `path` in `$VISUAL` or `$EDITOR` (default `vi`), handing it the
+/// terminal until it exits
+fn edit_file(
+    terminal: &mut DefaultTerminal,
+    app: &App,
+    path: &Path,
+) -> std::result::Result<(), String> {
+    run_in_terminal(terminal, app, &["VISUAL", "EDITOR"], "vi", path, "Editor")
+}
+
+/// Show `path` in `$PAGER` (default `less`), handing it the terminal until it exits
+fn page_file(
+    terminal: &mut DefaultTerminal,
+    app: &App,
+    path: &Path,
+) -> std::result::Result<(), String> {
+    run_in_terminal(terminal, app, &["PAGER"], "less", path, "Pager")
+}
+
+/// Run the program named by the first of `vars` that's set (else `default`)
+/// on `path`, handing it the terminal until it exits. The variable may hold
+/// arguments too, as in `PAGER="less -R"`; `what` names the program in
+/// errors.
+fn run_in_terminal(
+    terminal: &mut DefaultTerminal,
+    app: &App,
+    vars: &[&str],
+    default: &str,
+    path: &Path,
+    what: &str,
+) -> std::result::Result<(), String> {
+    let command = vars
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|command| !command.trim().is_empty())
+        .unwrap_or_else(|| default.to_string());
+    let mut parts = command.split_whitespace();
+    let program = parts.next().unwrap_or(default);
+
+    restore_terminal();
+    let status = signals::run_child(Command::new(program).args(parts).arg(path));
+    *terminal = init_terminal(app);
+
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("{} '{}' exited with {}", what, program, status)),
+        Err(e) => Err(format!(
+            "Failed to run {} '{}': {}",
+            what.to_lowercase(),
+            program,
+            e
+        )),
+    }
+}
@@ -0,0 +1,199 @@
+//! The `--server` control protocol: newline-delimited JSON requests on stdin,
+//! one JSON response line on stdout for each, with no TUI.
+//!
+//! Requests name their command in `cmd`:
+//!
+//! ```text
+//! {"cmd":"list"}
+//! {"cmd":"attach","name":"api"}
+//! {"cmd":"create","name":"web","dir":"/src/web"}
+//! ```
+//!
+//! Every response has `ok`, plus `error` when it's false. `list` adds
+//! `sessions`; `attach` outside tmux, where there's no client to switch, adds
+//! `command`, the argv that attaches from the caller's terminal:
+//!
+//! ```text
+//! {"ok":true,"sessions":[{"name":"api","windows":2,"attached":false,"last_activity":1700000000,"path":"/src/api"}]}
+//! {"ok":true,"command":["tmux","attach-session","-t","api"]}
+//! {"ok":false,"error":"No session named 'nope'"}
+//! ```
+
+use std::io::{self, BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::backend::TmuxBackend;
+use crate::tmux::{self, TmuxSession};
+
+/// One line of input
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase")]
+pub enum Request {
+    /// Every session, from all configured sockets
+    List,
+    /// Switch the caller's tmux client to `name`, or say how to attach
+    Attach { name: String },
+    /// Start a detached session, in `dir` when given
+    Create {
+        name: String,
+        #[serde(default)]
+        dir: Option<String>,
+    },
+}
+
+/// One line of output, answering a request
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sessions: Option<Vec<SessionInfo>>,
+    /// Outside tmux, the command line that attaches to the session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<Vec<String>>,
+}
+
+impl Response {
+    fn ok() -> Self {
+        Self {
+            ok: true,
+            ..Self::default()
+        }
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            error: Some(message.into()),
+            ..Self::default()
+        }
+    }
+}
+
+/// A session as `list` reports it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionInfo {
+    pub name: String,
+    pub windows: u32,
+    pub attached: bool,
+    /// Unix timestamp of the last activity in the session
+    pub last_activity: u64,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Socket of the server it's on, when several are configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socket: Option<String>,
+}
+
+impl From<TmuxSession> for SessionInfo {
+    fn from(session: TmuxSession) -> Self {
+        Self {
+            name: session.name,
+            windows: session.windows,
+            attached: session.attached,
+            last_activity: session.last_activity,
+            path: session.path,
+            group: session.group,
+            socket: session.socket,
+        }
+    }
+}
+
+/// Answers requests against one tmux backend
+pub struct Server<'a> {
+    backend: &'a dyn TmuxBackend,
+    /// Sockets to list sessions from, as in the `sockets` setting
+    sockets: &'a [String],
+}
+
+impl<'a> Server<'a> {
+    pub fn new(backend: &'a dyn TmuxBackend, sockets: &'a [String]) -> Self {
+        Self { backend, sockets }
+    }
+
+    /// Answer requests from `input` until it ends. Lines that aren't valid
+    /// requests get an error response rather than stopping the server.
+    pub fn serve(&self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            writeln!(output, "{}", self.handle_line(&line))?;
+            // The caller is waiting on each answer before sending more
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    /// The JSON response line for one JSON request line
+    pub fn handle_line(&self, line: &str) -> String {
+        let response = match serde_json::from_str::<Request>(line) {
+            Ok(request) => self.handle(&request),
+            Err(e) => Response::error(format!("Invalid request: {}", e)),
+        };
+        serde_json::to_string(&response)
+            .unwrap_or_else(|_| r#"{"ok":false,"error":"Unencodable response"}"#.to_string())
+    }
+
+    pub fn handle(&self, request: &Request) -> Response {
+        match request {
+            Request::List => match self.backend.list_sessions(self.sockets) {
+                Ok(sessions) => Response {
+                    sessions: Some(sessions.into_iter().map(SessionInfo::from).collect()),
+                    ..Response::ok()
+                },
+                Err(tmux::TmuxError::NoServer) => Response {
+                    sessions: Some(Vec::new()),
+                    ..Response::ok()
+                },
+                Err(e) => Response::error(e.to_string()),
+            },
+            Request::Attach { name } => self.attach(name),
+            Request::Create { name, dir } => self.create(name, dir.as_deref()),
+        }
+    }
+
+    fn attach(&self, name: &str) -> Response {
+        let sessions = match self.backend.list_sessions(self.sockets) {
+            Ok(sessions) => sessions,
+            Err(e) => return Response::error(e.to_string()),
+        };
+        let Some(session) = sessions.into_iter().find(|s| s.name == name) else {
+            return Response::error(tmux::TmuxError::NotFound(name.to_string()).to_string());
+        };
+        let socket = session.socket.as_deref();
+        // Only a caller inside tmux has a client to switch
+        if self.backend.current_session().is_none() {
+            return Response {
+                command: Some(tmux::attach_argv(socket, name)),
+                ..Response::ok()
+            };
+        }
+        match self.backend.switch_client(socket, name) {
+            Ok(()) => Response::ok(),
+            Err(e) => Response::error(e.to_string()),
+        }
+    }
+
+    fn create(&self, name: &str, dir: Option<&str>) -> Response {
+        if let Err(e) = tmux::validate_name(name, []) {
+            return Response::error(e);
+        }
+        // The same names the TUI's input filter lets through
+        if !name.chars().all(crate::app::is_session_name_char) {
+            return Response::error(format!(
+                "'{}' can only contain letters, digits, '-' and '_'",
+                name
+            ));
+        }
+        // New sessions go where the TUI creates them: the first socket
+        let socket = self.sockets.first().map(String::as_str);
+        match self.backend.create_session(socket, name, dir, &[]) {
+            Ok(()) => Response::ok(),
+            Err(e) => Response::error(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! What SIGINT, SIGTERM and SIGHUP do to a running Ursa: normally stop it,
+//! but not while a renumber has sessions under temporary names, and SIGINT
+//! not while another program (a pager or editor) has the terminal, where
+//! Ctrl-C is meant for that program.
+
+use std::io;
+use std::process::{Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+/// A program started by [`run_child`] has the terminal
+static CHILD_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Kept up to date by the event loop, so a signal doesn't stop Ursa in the
+/// middle of a renumber
+static RENUMBERING: AtomicBool = AtomicBool::new(false);
+
+/// A signal that arrived mid-renumber, for the event loop to quit on once
+/// that's done; 0 for none
+static DEFERRED_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+/// Note whether a renumber is under way, for signals arriving meanwhile
+pub fn set_renumbering(renumbering: bool) {
+    RENUMBERING.store(renumbering, Ordering::SeqCst);
+}
+
+/// The signal that arrived mid-renumber, if one did
+pub fn deferred() -> Option<i32> {
+    match DEFERRED_SIGNAL.load(Ordering::SeqCst) {
+        0 => None,
+        signal => Some(signal),
+    }
+}
+
+/// Run `command` in the foreground until it exits. Ctrl-C reaches Ursa too
+/// while the terminal isn't in raw mode, so SIGINT is left to the program
+/// meanwhile.
+pub fn run_child(command: &mut Command) -> io::Result<ExitStatus> {
+    CHILD_RUNNING.store(true, Ordering::SeqCst);
+    let status = command.status();
+    CHILD_RUNNING.store(false, Ordering::SeqCst);
+    status
+}
+
+/// Handle SIGINT, SIGTERM and SIGHUP on a thread of their own for the rest
+/// of the process, calling `exit` with the signal for those that should stop
+/// Ursa. The thread is where restoring the terminal is safe to do.
+#[cfg(unix)]
+pub fn handle(exit: impl Fn(i32) + Send + 'static) -> io::Result<()> {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+    use signal_hook::iterator::Signals;
+
+    let mut signals = Signals::new([SIGINT, SIGTERM, SIGHUP])?;
+    std::thread::spawn(move || {
+        for signal in signals.forever() {
+            if signal == SIGINT && CHILD_RUNNING.load(Ordering::SeqCst) {
+                log::info!("leaving signal {} to the program in the foreground", signal);
+            } else if RENUMBERING.load(Ordering::SeqCst) {
+                log::info!("exiting on signal {} once the renumber is done", signal);
+                DEFERRED_SIGNAL.store(signal, Ordering::SeqCst);
+            } else {
+                log::info!("exiting on signal {}", signal);
+                exit(signal);
+            }
+        }
+    });
+    Ok(())
+}
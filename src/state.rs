@@ -0,0 +1,64 @@
+//! Where the user was in the list, what they typed into its prompts and
+//! the color scheme they picked, remembered between runs in `~/.config/ursa/state.toml`.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::config_dir;
+use crate::theme::ColorScheme;
+
+const STATE_FILE: &str = "state.toml";
+
+/// List position, prompt history and color scheme saved on exit and restored on the next
+/// start
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedState {
+    /// Name of the session under the cursor, if any
+    pub selected: Option<String>,
+    /// First row shown in the session list
+    pub scroll_offset: usize,
+    /// Queries submitted in the search prompt, oldest first
+    pub search_history: Vec<String>,
+    /// Names submitted in the create prompt, oldest first
+    pub create_history: Vec<String>,
+    /// Color scheme last picked with Ctrl-T
+    pub color_scheme: ColorScheme,
+}
+
+impl SavedState {
+    /// Load the saved position; a missing file is the default state
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = state_path() else {
+            return Ok(Self::default());
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                toml::from_str(&contents).map_err(|e| format!("Invalid {}: {}", path.display(), e))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let Some(path) = state_path() else {
+            return Err("Cannot save: no config directory (is HOME set?)".to_string());
+        };
+
+        let contents = toml::to_string(self)
+            .map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        std::fs::write(&path, contents)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+fn state_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(STATE_FILE))
+}
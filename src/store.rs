@@ -0,0 +1,81 @@
+//! Small TOML files in the config dir that map session names to values.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::config::config_dir;
+
+/// Values persisted per session name, e.g. `~/.config/ursa/tags.toml`
+#[derive(Debug, Clone)]
+pub struct SessionStore<T> {
+    path: Option<PathBuf>,
+    entries: BTreeMap<String, T>,
+}
+
+impl<T: Serialize + DeserializeOwned> SessionStore<T> {
+    /// An empty store that saves to `file_name` in the config dir
+    pub fn empty(file_name: &str) -> Self {
+        Self {
+            path: config_dir().map(|dir| dir.join(file_name)),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Load `file_name` from the config dir; a missing file is an empty store
+    pub fn load(file_name: &str) -> Result<Self, String> {
+        let mut store = Self::empty(file_name);
+        let Some(path) = &store.path else {
+            return Ok(store);
+        };
+
+        match std::fs::read_to_string(path) {
+            Ok(contents) => {
+                store.entries = toml::from_str(&contents)
+                    .map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+                Ok(store)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(store),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.entries.get(name)
+    }
+
+    /// Set or clear the value for `name` and save
+    pub fn set(&mut self, name: &str, value: Option<T>) -> Result<(), String> {
+        match value {
+            Some(value) => self.entries.insert(name.to_string(), value),
+            None => self.entries.remove(name),
+        };
+        self.save()
+    }
+
+    /// Move the value stored under `old_name` to `new_name` and save
+    pub fn rename(&mut self, old_name: &str, new_name: &str) -> Result<(), String> {
+        let Some(value) = self.entries.remove(old_name) else {
+            return Ok(());
+        };
+        self.entries.insert(new_name.to_string(), value);
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Err("Cannot save: no config directory (is HOME set?)".to_string());
+        };
+
+        let contents = toml::to_string(&self.entries)
+            .map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        std::fs::write(path, contents)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
@@ -0,0 +1,48 @@
+//! Color labels for organizing sessions (e.g. red for prod, green for scratch).
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tag {
+    Red,
+    Yellow,
+    Green,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+impl Tag {
+    /// Palette in cycling order
+    pub const ALL: [Tag; 6] = [
+        Tag::Red,
+        Tag::Yellow,
+        Tag::Green,
+        Tag::Blue,
+        Tag::Magenta,
+        Tag::Cyan,
+    ];
+
+    /// The tag after `current` in the palette; untagged comes after the last one
+    pub fn cycle(current: Option<Tag>) -> Option<Tag> {
+        match current {
+            None => Some(Tag::ALL[0]),
+            Some(tag) => {
+                let index = Tag::ALL.iter().position(|&t| t == tag).unwrap_or(0);
+                Tag::ALL.get(index + 1).copied()
+            }
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tag::Red => "red",
+            Tag::Yellow => "yellow",
+            Tag::Green => "green",
+            Tag::Blue => "blue",
+            Tag::Magenta => "magenta",
+            Tag::Cyan => "cyan",
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Styling helpers shared by the renderer.
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+
+/// Color handling for the UI.
+///
+/// When color is disabled (`NO_COLOR` or `--no-color`) every style falls back to
+/// the terminal's default colors, keeping only bold/reverse for selection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub color: bool,
+    /// Palette the renderer's colors are drawn from, cycled with Ctrl-T
+    pub scheme: ColorScheme,
+}
+
+/// Built-in palettes. The renderer asks for plain terminal colors and each
+/// scheme decides what they look like.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorScheme {
+    /// The terminal's own palette
+    #[default]
+    Default,
+    /// Bright colors, and black on white for the cursor row
+    HighContrast,
+    /// Solarized's accent colors, whatever the terminal's palette
+    Solarized,
+    /// Default colors only, as with `NO_COLOR`
+    Monochrome,
+}
+
+impl ColorScheme {
+    const ALL: [ColorScheme; 4] = [
+        ColorScheme::Default,
+        ColorScheme::HighContrast,
+        ColorScheme::Solarized,
+        ColorScheme::Monochrome,
+    ];
+
+    /// The scheme after this one, wrapping around
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&s| s == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorScheme::Default => "default",
+            ColorScheme::HighContrast => "high-contrast",
+            ColorScheme::Solarized => "solarized",
+            ColorScheme::Monochrome => "monochrome",
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            color: true,
+            scheme: ColorScheme::default(),
+        }
+    }
+}
+
+impl Theme {
+    pub fn monochrome() -> Self {
+        Self {
+            color: false,
+            ..Self::default()
+        }
+    }
+
+    /// Whether styles carry colors at all
+    pub fn has_color(&self) -> bool {
+        self.color && self.scheme != ColorScheme::Monochrome
+    }
+
+    /// What `color` looks like in the current scheme
+    pub fn color(&self, color: Color) -> Color {
+        match self.scheme {
+            ColorScheme::Default | ColorScheme::Monochrome => color,
+            ColorScheme::HighContrast => match color {
+                Color::DarkGray => Color::Gray,
+                Color::Gray => Color::White,
+                Color::Red => Color::LightRed,
+                Color::Green => Color::LightGreen,
+                Color::Yellow => Color::LightYellow,
+                Color::Blue => Color::LightBlue,
+                Color::Magenta => Color::LightMagenta,
+                Color::Cyan => Color::LightCyan,
+                other => other,
+            },
+            ColorScheme::Solarized => match color {
+                Color::DarkGray => Color::Rgb(0x58, 0x6e, 0x75),
+                Color::Gray => Color::Rgb(0x93, 0xa1, 0xa1),
+                Color::Red => Color::Rgb(0xdc, 0x32, 0x2f),
+                Color::Green => Color::Rgb(0x85, 0x99, 0x00),
+                Color::Yellow => Color::Rgb(0xb5, 0x89, 0x00),
+                Color::Blue => Color::Rgb(0x26, 0x8b, 0xd2),
+                Color::Magenta => Color::Rgb(0xd3, 0x36, 0x82),
+                Color::Cyan => Color::Rgb(0x2a, 0xa1, 0x98),
+                other => other,
+            },
+        }
+    }
+
+    /// Foreground-only style
+    pub fn fg(&self, color: Color) -> Style {
+        if self.has_color() {
+            Style::default().fg(self.color(color))
+        } else {
+            Style::default()
+        }
+    }
+
+    /// Style for the active button / focused element (black on `color`)
+    pub fn active(&self, color: Color) -> Style {
+        if self.has_color() {
+            Style::default().fg(Color::Black).bg(self.color(color))
+        } else {
+            Style::default().add_modifier(Modifier::REVERSED)
+        }
+    }
+
+    /// Style for the highlighted list row. Monochrome relies on bold plus the
+    /// highlight symbol so reversed action buttons stay visible on the row.
+    pub fn highlight(&self) -> Style {
+        if !self.has_color() {
+            return Style::default().add_modifier(Modifier::BOLD);
+        }
+        let style = match self.scheme {
+            ColorScheme::HighContrast => Style::default().fg(Color::Black).bg(Color::White),
+            ColorScheme::Solarized => Style::default().bg(Color::Rgb(0x07, 0x36, 0x42)),
+            _ => Style::default().bg(Color::DarkGray),
+        };
+        style.add_modifier(Modifier::BOLD)
+    }
+}
@@ -1,17 +1,256 @@
-use std::process::Command;
+//! Thin wrappers around the `tmux` command line.
+//!
+//! Commands aimed at a server take its `socket` (as for `tmux -L`) first,
+//! None being tmux's default server.
 
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Lines, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Output, Stdio};
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::LayoutPreset;
+
+/// tmux executable run by every command; None runs `tmux` from PATH
+static BINARY: RwLock<Option<String>> = RwLock::new(None);
+
+/// SSH destination (`user@host`) every command runs on; None runs locally
+static REMOTE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Names of the control clients Ursa runs itself (see [`control_client`]),
+/// which don't count towards a session being attached
+static HIDDEN_CLIENTS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Run `path` instead of `tmux` from PATH for every command in this process,
+/// e.g. a wrapper script or a build installed somewhere unusual
+pub fn set_binary(path: Option<&str>) {
+    let mut binary = BINARY.write().unwrap_or_else(|e| e.into_inner());
+    *binary = path.map(str::to_string);
+}
+
+/// The tmux executable commands currently run
+pub fn binary() -> String {
+    BINARY
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| "tmux".to_string())
+}
+
+/// Check that the tmux executable runs by asking for its version (`tmux -V`),
+/// returning the version line
+pub fn check_binary() -> Result<String, TmuxError> {
+    let context = format!("Failed to run {}", binary());
+    let output = logged_output(command_for(&["-V"])).map_err(|e| spawn_error(&context, e))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(command_error(&context, &output))
+    }
+}
+
+/// Run every tmux command in this process on `destination` over SSH (as
+/// for `ssh user@host`), or locally again with None
+pub fn set_remote(destination: Option<&str>) {
+    let mut remote = REMOTE.write().unwrap_or_else(|e| e.into_inner());
+    *remote = destination.map(str::to_string);
+}
+
+/// The SSH destination commands run on, if not this machine
+pub fn remote() -> Option<String> {
+    REMOTE.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// `tmux args`, or with a remote set `ssh destination tmux args`. ssh hands
+/// its arguments to the remote shell as one line, so they're quoted for it.
+/// BatchMode makes a host that would prompt for a password fail instead of
+/// hanging behind the TUI.
+fn command_for(args: &[&str]) -> Command {
+    match remote() {
+        Some(destination) => {
+            let mut command = Command::new("ssh");
+            command
+                .args(["-o", "BatchMode=yes", destination.as_str(), "--"])
+                .arg(shell_quote(&binary()))
+                .args(args.iter().map(|arg| shell_quote(arg)));
+            command
+        }
+        None => {
+            let mut command = Command::new(binary());
+            command.args(args);
+            command
+        }
+    }
+}
+
+/// A `tmux` command with `args` aimed at the server on `socket` (as for
+/// `tmux -L`), or at tmux's default server for None
+fn tmux_command(socket: Option<&str>, args: &[&str]) -> Command {
+    let mut full = Vec::with_capacity(args.len() + 2);
+    if let Some(name) = socket {
+        full.extend(["-L", name]);
+    }
+    full.extend_from_slice(args);
+    command_for(&full)
+}
+
+/// A session as reported by `tmux list-sessions`
 #[derive(Debug, Clone)]
 pub struct TmuxSession {
     pub name: String,
     pub windows: u32,
     pub attached: bool,
+    /// Unix timestamp of the last activity in the session
+    pub last_activity: u64,
+    /// Session group (see `new-session -t`) whose members share windows
+    pub group: Option<String>,
+    /// Directory the session was started in (`#{session_path}`)
+    pub path: String,
+    /// Socket name of the server the session lives on (None for the default)
+    pub socket: Option<String>,
+}
+
+/// Why a tmux command failed, classified from tmux's error output where the
+/// cause is recognizable
+#[derive(Debug, Clone, PartialEq)]
+pub enum TmuxError {
+    /// No server is running yet; tmux starts one with the first new session
+    NoServer,
+    /// The tmux binary (see [`binary`]) couldn't be found
+    NotInstalled,
+    /// A session with this name already exists
+    DuplicateName(String),
+    /// The targeted session doesn't exist (any more)
+    NotFound(String),
+    /// tmux ran but failed for some other reason; `context` says what was
+    /// being done and `stderr` is tmux's own message, if any
+    CommandFailed { context: String, stderr: String },
+    /// tmux couldn't be started, e.g. for lack of permission
+    Io { context: String, message: String },
+    /// The SSH connection to the remote (see [`set_remote`]) failed;
+    /// `message` is ssh's own explanation
+    Unreachable { host: String, message: String },
 }
 
-pub fn list_sessions() -> Vec<TmuxSession> {
+impl fmt::Display for TmuxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TmuxError::NoServer => write!(f, "No tmux server running"),
+            TmuxError::NotInstalled => {
+                write!(
+                    f,
+                    "'{}' not found; is tmux installed and on PATH?",
+                    binary()
+                )
+            }
+            TmuxError::DuplicateName(name) => {
+                write!(f, "A session named '{}' already exists", name)
+            }
+            TmuxError::NotFound(name) => write!(f, "No session named '{}'", name),
+            TmuxError::CommandFailed { context, stderr } if stderr.is_empty() => {
+                write!(f, "{}", context)
+            }
+            TmuxError::CommandFailed { context, stderr } => write!(f, "{}: {}", context, stderr),
+            TmuxError::Io { context, message } => write!(f, "{}: {}", context, message),
+            TmuxError::Unreachable { host, message } => {
+                write!(f, "Can't reach {} over SSH: {}", host, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TmuxError {}
+
+/// List all sessions on the server on `socket`. A missing server is reported as
+/// [`TmuxError::NoServer`] rather than an empty list.
+pub fn list_sessions(socket: Option<&str>) -> Result<Vec<TmuxSession>, TmuxError> {
     // Use tab as delimiter to handle session names containing colons
-    let output = Command::new("tmux")
-        .args(["list-sessions", "-F", "#{session_name}\t#{session_windows}\t#{session_attached}"])
-        .output();
+    let stdout = query(
+        socket,
+        &[
+            "list-sessions",
+            "-F",
+            "#{session_name}\t#{session_windows}\t#{session_attached}\t#{session_activity}\t#{session_group}\t#{session_path}\t#{session_attached_list}",
+        ],
+        "Failed to list tmux sessions",
+    )?;
+
+    let hidden = HIDDEN_CLIENTS.read().unwrap_or_else(|e| e.into_inner());
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split('\t').collect();
+            if parts.len() >= 6 {
+                let clients: usize = parts[2].parse().unwrap_or(0);
+                // tmux before 3.1 leaves the list empty, so nothing is left out
+                let own = parts.get(6).map_or(0, |list| {
+                    list.split(',')
+                        .filter(|client| hidden.iter().any(|name| name == client))
+                        .count()
+                });
+                Some(TmuxSession {
+                    name: parts[0].to_string(),
+                    windows: parts[1].parse().unwrap_or(0),
+                    attached: clients > own,
+                    last_activity: parts[3].parse().unwrap_or(0),
+                    group: Some(parts[4].to_string()).filter(|group| !group.is_empty()),
+                    path: parts[5].to_string(),
+                    socket: socket.map(str::to_string),
+                })
+            } else {
+                None
+            }
+        })
+        .collect())
+}
+
+/// Sessions from every server in `sockets` (socket names as for `tmux -L`;
+/// tmux's own server is "default"), in that order. Sockets without a running
+/// server are skipped; NoServer is only returned when none is running.
+pub fn list_sessions_across(sockets: &[String]) -> Result<Vec<TmuxSession>, TmuxError> {
+    let mut sessions = Vec::new();
+    let mut any_server = false;
+    for socket in sockets {
+        match list_sessions(Some(socket)) {
+            Ok(found) => {
+                any_server = true;
+                sessions.extend(found);
+            }
+            Err(TmuxError::NoServer) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    if any_server {
+        Ok(sessions)
+    } else {
+        Err(TmuxError::NoServer)
+    }
+}
+
+/// A pane as reported by `tmux list-panes`
+#[derive(Debug, Clone)]
+pub struct TmuxPane {
+    pub session_name: String,
+    pub window_name: String,
+    pub current_command: String,
+}
+
+/// List every pane on the server along with the command running in its foreground
+pub fn list_panes(socket: Option<&str>) -> Vec<TmuxPane> {
+    let output = logged_output(tmux_command(
+        socket,
+        &[
+            "list-panes",
+            "-a",
+            "-F",
+            "#{session_name}\t#{window_name}\t#{pane_current_command}",
+        ],
+    ));
 
     match output {
         Ok(output) if output.status.success() => {
@@ -21,10 +260,10 @@ pub fn list_sessions() -> Vec<TmuxSession> {
                 .filter_map(|line| {
                     let parts: Vec<&str> = line.split('\t').collect();
                     if parts.len() >= 3 {
-                        Some(TmuxSession {
-                            name: parts[0].to_string(),
-                            windows: parts[1].parse().unwrap_or(0),
-                            attached: parts[2] == "1",
+                        Some(TmuxPane {
+                            session_name: parts[0].to_string(),
+                            window_name: parts[1].to_string(),
+                            current_command: parts[2].to_string(),
                         })
                     } else {
                         None
@@ -36,76 +275,983 @@ pub fn list_sessions() -> Vec<TmuxSession> {
     }
 }
 
-pub fn create_session(name: &str) -> Result<(), String> {
-    let status = Command::new("tmux")
-        .args(["new-session", "-d", "-s", name])
-        .status()
-        .map_err(|e| format!("Failed to create session: {}", e))?;
+/// A window as reported by `tmux list-windows`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TmuxWindow {
+    pub index: u32,
+    pub name: String,
+    pub panes: u32,
+    /// The window shown when attaching to the session
+    pub active: bool,
+    /// Whether typing in one of its panes goes to all of them
+    /// (`synchronize-panes`)
+    pub synchronized: bool,
+}
 
-    if status.success() {
-        Ok(())
-    } else {
-        Err("Failed to create tmux session".to_string())
+/// Everything the detail pane shows about one session beyond `TmuxSession`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionDetails {
+    /// Working directory new windows start in
+    pub path: String,
+    /// Unix timestamp of when the session was created
+    pub created: u64,
+    pub windows: Vec<TmuxWindow>,
+    /// TTYs of the clients attached to the session
+    pub clients: Vec<String>,
+}
+
+/// Read the path, creation time, windows and attached clients of `name`
+pub fn session_details(socket: Option<&str>, name: &str) -> Result<SessionDetails, TmuxError> {
+    let info = query(
+        socket,
+        &[
+            "display-message",
+            "-p",
+            "-t",
+            name,
+            "#{session_path}\t#{session_created}",
+        ],
+        "Failed to read session details",
+    )?;
+    let (path, created) = info
+        .trim_end()
+        .split_once('\t')
+        .unwrap_or((info.trim_end(), ""));
+
+    let windows = query(
+        socket,
+        &[
+            "list-windows",
+            "-t",
+            name,
+            "-F",
+            "#{window_index}\t#{window_name}\t#{window_panes}\t#{window_active}\t#{pane_synchronized}",
+        ],
+        "Failed to list windows",
+    )?
+    .lines()
+    .filter_map(|line| {
+        let parts: Vec<&str> = line.split('\t').collect();
+        if parts.len() >= 4 {
+            Some(TmuxWindow {
+                index: parts[0].parse().unwrap_or(0),
+                name: parts[1].to_string(),
+                panes: parts[2].parse().unwrap_or(0),
+                active: parts[3] == "1",
+                synchronized: parts.get(4) == Some(&"1"),
+            })
+        } else {
+            None
+        }
+    })
+    .collect();
+
+    // Control clients, such as Ursa's own, have no tty
+    let clients = query(
+        socket,
+        &["list-clients", "-t", name, "-F", "#{client_tty}"],
+        "Failed to list clients",
+    )?
+    .lines()
+    .filter(|tty| !tty.is_empty())
+    .map(str::to_string)
+    .collect();
+
+    Ok(SessionDetails {
+        path: path.to_string(),
+        created: created.parse().unwrap_or(0),
+        windows,
+        clients,
+    })
+}
+
+/// Write the full scrollback of the active pane in `session` to `path`. tmux
+/// writes straight into a file beside it, so large histories aren't held in
+/// memory, which replaces `path` only once the capture worked.
+pub fn capture_scrollback(
+    socket: Option<&str>,
+    session: &str,
+    path: &Path,
+) -> Result<(), TmuxError> {
+    let context = "Failed to capture scrollback";
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+    let file = File::create(&partial).map_err(|e| TmuxError::Io {
+        context: format!("Failed to create {}", path.display()),
+        message: e.to_string(),
+    })?;
+    // Trailing colon targets the active pane of the session's current window
+    let target = format!("{}:", session);
+    let mut command = tmux_command(
+        socket,
+        &["capture-pane", "-p", "-S", "-", "-t", target.as_str()],
+    );
+    let output = command
+        .stdout(file)
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|child| child.wait_with_output());
+    log_command(&command, &output);
+
+    let result = match output {
+        Ok(output) if output.status.success() => {
+            std::fs::rename(&partial, path).map_err(|e| TmuxError::Io {
+                context: format!("Failed to write {}", path.display()),
+                message: e.to_string(),
+            })
+        }
+        Ok(output) => Err(command_error(context, &output)),
+        Err(e) => Err(spawn_error(context, e)),
+    };
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
     }
+    result
 }
 
-pub fn rename_session(old_name: &str, new_name: &str) -> Result<(), String> {
-    let status = Command::new("tmux")
-        .args(["rename-session", "-t", old_name, new_name])
-        .status()
-        .map_err(|e| format!("Failed to rename session: {}", e))?;
+/// The text currently on screen in the active pane of `session`, without
+/// its scrollback
+pub fn capture_pane(socket: Option<&str>, session: &str) -> Result<String, TmuxError> {
+    let target = format!("{}:", session);
+    query(
+        socket,
+        &["capture-pane", "-p", "-t", target.as_str()],
+        "Failed to capture pane",
+    )
+}
 
-    if status.success() {
-        Ok(())
+/// Check `entry` as an environment variable for a new session: `KEY=VALUE`,
+/// the key made of letters, digits and underscores and not starting with a
+/// digit. The value may be empty.
+pub fn validate_env(entry: &str) -> Result<(), String> {
+    let Some((key, _)) = entry.split_once('=') else {
+        return Err("Use KEY=VALUE".to_string());
+    };
+    if key.is_empty() {
+        return Err("Variable name can't be empty".to_string());
+    }
+    if key.starts_with(|c: char| c.is_ascii_digit())
+        || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(format!("'{}' is not a valid variable name", key));
+    }
+    Ok(())
+}
+
+/// Check `name` as a new session name: not empty, free of the characters tmux
+/// reserves for targets (`:` and `.`), and not already taken by `existing`.
+/// A name that only differs by case from an existing one is allowed, since
+/// tmux tells them apart, but comes back as `Ok(Some(warning))`.
+pub fn validate_name<'a>(
+    name: &str,
+    existing: impl IntoIterator<Item = &'a str>,
+) -> Result<Option<String>, String> {
+    if name.trim().is_empty() {
+        return Err("Name can't be empty".to_string());
+    }
+    if name.contains([':', '.']) {
+        return Err("Name can't contain ':' or '.'".to_string());
+    }
+    let mut similar = None;
+    for other in existing {
+        if other == name {
+            return Err(format!("A session named '{}' already exists", name));
+        }
+        if similar.is_none() && other.to_lowercase() == name.to_lowercase() {
+            similar = Some(other);
+        }
+    }
+    Ok(similar.map(|other| format!("A session '{}' already exists", other)))
+}
+
+/// Create a detached session, starting in `start_dir` when given (tmux
+/// defaults to the current directory), with `env` (`KEY=VALUE` entries) set
+/// in its environment
+pub fn create_session(
+    socket: Option<&str>,
+    name: &str,
+    start_dir: Option<&str>,
+    env: &[String],
+) -> Result<(), TmuxError> {
+    let context = "Failed to create tmux session";
+    let mut args = vec!["new-session", "-d", "-s", name];
+    if let Some(dir) = start_dir {
+        args.extend(["-c", dir]);
+    }
+    if env.is_empty() || supports_session_env() {
+        for entry in env {
+            args.extend(["-e", entry.as_str()]);
+        }
+        return run(socket, &args, context).map(drop);
+    }
+
+    // Older tmux: set the variables on the session, then restart its first
+    // pane so the shell starts with them
+    run(socket, &args, context)?;
+    for entry in env {
+        let (key, value) = entry.split_once('=').unwrap_or((entry.as_str(), ""));
+        run(
+            socket,
+            &["set-environment", "-t", name, key, value],
+            "Failed to set session environment",
+        )?;
+    }
+    let target = format!("{}:", name);
+    run(
+        socket,
+        &["respawn-pane", "-k", "-t", target.as_str()],
+        "Failed to restart tmux pane",
+    )
+    .map(drop)
+}
+
+/// Whether the tmux binary takes `-e` on `new-session`, added in 3.2
+fn supports_session_env() -> bool {
+    version_at_least((3, 2))
+}
+
+/// Whether the tmux binary has `display-popup`, added in 3.2
+pub fn supports_popup() -> bool {
+    version_at_least((3, 2))
+}
+
+/// Whether the tmux binary is `minimum` or newer. Versions that don't parse,
+/// like development builds, are taken as recent.
+fn version_at_least(minimum: (u32, u32)) -> bool {
+    check_binary()
+        .ok()
+        .and_then(|version| parse_version(&version))
+        .is_none_or(|version| version >= minimum)
+}
+
+/// Major and minor version from `tmux -V` output, e.g. (3, 3) for
+/// "tmux 3.3a" or "tmux next-3.4"
+pub fn parse_version(output: &str) -> Option<(u32, u32)> {
+    let number = output.trim().rsplit([' ', '-']).next()?;
+    let (major, rest) = number.split_once('.')?;
+    let minor: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Create a detached session starting in `start_dir` unless one with that
+/// name exists, like `tmux new-session -A` minus the attaching. Returns
+/// whether the session was created.
+pub fn create_if_missing(
+    socket: Option<&str>,
+    name: &str,
+    start_dir: Option<&str>,
+) -> Result<bool, TmuxError> {
+    let mut args = vec!["new-session", "-d", "-s", name];
+    if let Some(dir) = start_dir {
+        args.extend(["-c", dir]);
+    }
+
+    match run(socket, &args, "Failed to create tmux session") {
+        Ok(_) => Ok(true),
+        Err(TmuxError::DuplicateName(_)) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Add a window to an existing session without switching to it
+pub fn new_window(
+    socket: Option<&str>,
+    session: &str,
+    name: Option<&str>,
+    start_dir: Option<&str>,
+) -> Result<(), TmuxError> {
+    // Trailing colon targets the session itself, letting tmux pick the index
+    let target = format!("{}:", session);
+    let mut args = vec!["new-window", "-d", "-t", target.as_str()];
+    if let Some(name) = name {
+        args.extend(["-n", name]);
+    }
+    if let Some(dir) = start_dir {
+        args.extend(["-c", dir]);
+    }
+
+    run(socket, &args, "Failed to create tmux window").map(drop)
+}
+
+/// Which way `split_window` divides the active pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitDirection {
+    /// Side by side (`split-window -h`)
+    Horizontal,
+    /// One above the other (`split-window -v`)
+    Vertical,
+}
+
+/// Split the active pane of `session`'s current window in `direction`
+/// without switching to the new pane, starting it in `start_dir` when given
+pub fn split_window(
+    socket: Option<&str>,
+    session: &str,
+    direction: SplitDirection,
+    start_dir: Option<&str>,
+) -> Result<(), TmuxError> {
+    let target = format!("{}:", session);
+    let flag = match direction {
+        SplitDirection::Horizontal => "-h",
+        SplitDirection::Vertical => "-v",
+    };
+    let mut args = vec!["split-window", "-d", flag, "-t", target.as_str()];
+    if let Some(dir) = start_dir {
+        args.extend(["-c", dir]);
+    }
+
+    run(socket, &args, "Failed to split tmux window").map(drop)
+}
+
+/// Turn `synchronize-panes` on or off in every window of `session`, so keys
+/// typed into one pane go to all panes of its window
+pub fn set_synchronize_panes(
+    socket: Option<&str>,
+    session: &str,
+    on: bool,
+) -> Result<(), TmuxError> {
+    let context = "Failed to set synchronize-panes";
+    let indices = query(
+        socket,
+        &["list-windows", "-t", session, "-F", "#{window_index}"],
+        "Failed to list windows",
+    )?;
+    let value = if on { "on" } else { "off" };
+    for index in indices.lines().filter_map(|line| line.trim().parse().ok()) {
+        let target = window_target(session, index);
+        run(
+            socket,
+            &["set-window-option", "-t", target.as_str(), "synchronize-panes", value],
+            context,
+        )?;
+    }
+    Ok(())
+}
+
+/// The index the server gives a session's first window (`base-index`),
+/// 0 unless the tmux config says otherwise
+pub fn base_index(socket: Option<&str>) -> Result<u32, TmuxError> {
+    let value = query(
+        socket,
+        &["show-options", "-gv", "base-index"],
+        "Failed to read base-index",
+    )?;
+    Ok(value.trim().parse().unwrap_or(0))
+}
+
+/// `session:index`, for a window index as `list-windows` reports it. The
+/// indices already account for `base-index`, so they're never recomputed
+/// from a window's position.
+pub fn window_target(session: &str, index: u32) -> String {
+    format!("{}:{}", session, index)
+}
+
+/// Make window `index` the current window of `session`, so attaching lands
+/// on it
+pub fn select_window(socket: Option<&str>, session: &str, index: u32) -> Result<(), TmuxError> {
+    let target = window_target(session, index);
+    run(
+        socket,
+        &["select-window", "-t", target.as_str()],
+        "Failed to select window",
+    )
+    .map(drop)
+}
+
+/// Split a session's current window into the preset's panes and arrange them
+pub fn apply_layout(
+    socket: Option<&str>,
+    session: &str,
+    layout: &LayoutPreset,
+) -> Result<(), TmuxError> {
+    let target = format!("{}:", session);
+
+    for _ in 1..layout.panes {
+        run(
+            socket,
+            &["split-window", "-d", "-t", target.as_str()],
+            "Failed to split tmux window",
+        )?;
+    }
+
+    if let Some(name) = &layout.layout {
+        run(
+            socket,
+            &["select-layout", "-t", target.as_str(), name.as_str()],
+            "Failed to apply tmux layout",
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Whether a session named exactly `name` still exists; a server that has
+/// gone away has none
+pub fn has_session(socket: Option<&str>, name: &str) -> Result<bool, TmuxError> {
+    // "=" turns off tmux's prefix matching, so "api" doesn't find "api-old"
+    let target = format!("={}", name);
+    match run(
+        socket,
+        &["has-session", "-t", target.as_str()],
+        "Failed to look up session",
+    ) {
+        Ok(_) => Ok(true),
+        Err(TmuxError::NotFound(_) | TmuxError::NoServer) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Pane arrangement of a window, as saved by a layout snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowLayout {
+    pub panes: u32,
+    /// tmux's description of the arrangement (`#{window_layout}`), which
+    /// `select-layout` takes back
+    pub layout: String,
+}
+
+/// The layout of the current window in `session`
+pub fn window_layout(socket: Option<&str>, session: &str) -> Result<WindowLayout, TmuxError> {
+    let target = format!("{}:", session);
+    let info = query(
+        socket,
+        &[
+            "display-message",
+            "-p",
+            "-t",
+            target.as_str(),
+            "#{window_panes}\t#{window_layout}",
+        ],
+        "Failed to read window layout",
+    )?;
+    let (panes, layout) = info.trim_end().split_once('\t').unwrap_or(("0", ""));
+    Ok(WindowLayout {
+        panes: panes.parse().unwrap_or(0),
+        layout: layout.to_string(),
+    })
+}
+
+/// Split the current window of `session` until it has as many panes as
+/// `saved`, then arrange them as saved. A window with more panes is left
+/// alone rather than closing any; returns whether the layout was applied.
+pub fn restore_layout(
+    socket: Option<&str>,
+    session: &str,
+    saved: &WindowLayout,
+) -> Result<bool, TmuxError> {
+    let current = window_layout(socket, session)?;
+    if current.panes > saved.panes {
+        return Ok(false);
+    }
+
+    let target = format!("{}:", session);
+    for _ in current.panes..saved.panes {
+        run(
+            socket,
+            &["split-window", "-d", "-t", target.as_str()],
+            "Failed to split tmux window",
+        )?;
+    }
+    run(
+        socket,
+        &[
+            "select-layout",
+            "-t",
+            target.as_str(),
+            saved.layout.as_str(),
+        ],
+        "Failed to apply tmux layout",
+    )?;
+    Ok(true)
+}
+
+/// Zoom the active pane of `session`'s current window so it fills the
+/// window. `resize-pane -Z` toggles, so an already zoomed pane is left as is.
+pub fn zoom_active_pane(socket: Option<&str>, session: &str) -> Result<(), TmuxError> {
+    let target = format!("{}:", session);
+    let zoomed = query(
+        socket,
+        &[
+            "display-message",
+            "-p",
+            "-t",
+            target.as_str(),
+            "#{window_zoomed_flag}",
+        ],
+        "Failed to read zoom state",
+    )?;
+    if zoomed.trim() == "1" {
+        return Ok(());
+    }
+    run(
+        socket,
+        &["resize-pane", "-Z", "-t", target.as_str()],
+        "Failed to zoom pane",
+    )
+    .map(drop)
+}
+
+/// Rename an existing session. With `dry_run` nothing is executed.
+pub fn rename_session(
+    socket: Option<&str>,
+    old_name: &str,
+    new_name: &str,
+    dry_run: bool,
+) -> Result<(), TmuxError> {
+    if dry_run {
+        return Ok(());
+    }
+
+    run(
+        socket,
+        &["rename-session", "-t", old_name, new_name],
+        "Failed to rename tmux session",
+    )
+    .map(drop)
+}
+
+/// Kill a session and all of its windows. With `dry_run` nothing is executed.
+pub fn kill_session(socket: Option<&str>, name: &str, dry_run: bool) -> Result<(), TmuxError> {
+    if dry_run {
+        return Ok(());
+    }
+
+    run(
+        socket,
+        &["kill-session", "-t", name],
+        "Failed to kill tmux session",
+    )
+    .map(drop)
+}
+
+/// The session of the tmux client Ursa runs in with the name of its
+/// server's socket (as for `tmux -L`), or None outside tmux. The pane from
+/// `$TMUX_PANE` pins it to Ursa's own pane, not whichever session some
+/// client most recently used.
+pub fn current_session() -> Option<(String, String)> {
+    if !is_inside_tmux() {
+        return None;
+    }
+    let pane = std::env::var("TMUX_PANE").ok().filter(|pane| !pane.is_empty());
+    let mut args = vec!["display-message", "-p"];
+    if let Some(pane) = &pane {
+        args.extend(["-t", pane.as_str()]);
+    }
+    args.push("#{socket_path}\t#{session_name}");
+    // Without -L tmux goes to the server in $TMUX, the one Ursa runs in
+    let output = query(None, &args, "Failed to read the current session").ok()?;
+    let (path, name) = output.trim_end_matches('\n').split_once('\t')?;
+    let socket = Path::new(path).file_name()?.to_string_lossy().into_owned();
+    (!name.is_empty()).then(|| (socket, name.to_string()))
+}
+
+/// Returns true if currently running inside a tmux client. `$TMUX` alone can be
+/// inherited by processes that aren't in a client (e.g. apps launched from a
+/// window manager started inside tmux), so tmux is asked to confirm. With a
+/// remote set the local client can't switch to its sessions, so this is false.
+pub fn is_inside_tmux() -> bool {
+    if remote().is_some() {
+        return false;
+    }
+    detect_inside_tmux(std::env::var("TMUX").ok().as_deref(), has_client_tty)
+}
+
+/// Detection logic with the environment injected: `tmux_env` is the value of
+/// `$TMUX` and `client_check` reports whether tmux resolves a client for us.
+pub fn detect_inside_tmux(tmux_env: Option<&str>, client_check: impl FnOnce() -> bool) -> bool {
+    match tmux_env {
+        Some(value) if !value.trim().is_empty() => client_check(),
+        _ => false,
+    }
+}
+
+/// True when `display-message` resolves a client terminal for this process
+fn has_client_tty() -> bool {
+    let command = tmux_command(None, &["display-message", "-p", "#{client_tty}"]);
+    logged_output(command).is_ok_and(|output| {
+        output.status.success() && !String::from_utf8_lossy(&output.stdout).trim().is_empty()
+    })
+}
+
+/// Attach to a session, switching the current client when already inside tmux
+pub fn attach_session(socket: Option<&str>, name: &str) -> Result<(), TmuxError> {
+    if is_inside_tmux() {
+        switch_client(socket, name)
     } else {
-        Err("Failed to rename tmux session".to_string())
+        attach_client(socket, name)
     }
 }
 
-pub fn kill_session(name: &str) -> Result<(), String> {
-    let status = Command::new("tmux")
-        .args(["kill-session", "-t", name])
+/// Type `keys` into the active pane of `session`, each one a `send-keys`
+/// argument (text, or a key name like "Enter")
+pub fn send_keys(socket: Option<&str>, session: &str, keys: &[String]) -> Result<(), TmuxError> {
+    let target = format!("{}:", session);
+    let mut args = vec!["send-keys", "-t", target.as_str()];
+    args.extend(keys.iter().map(String::as_str));
+    run(socket, &args, "Failed to send keys").map(drop)
+}
+
+/// Point the current tmux client at another session
+pub fn switch_client(socket: Option<&str>, name: &str) -> Result<(), TmuxError> {
+    run(
+        socket,
+        &["switch-client", "-t", name],
+        "Failed to switch to session",
+    )
+    .map(drop)
+}
+
+/// Run `tmux attach-session` as a child process, returning once it detaches
+pub fn attach_client(socket: Option<&str>, name: &str) -> Result<(), TmuxError> {
+    let context = "Failed to attach to tmux session";
+    // attach_argv names the server itself
+    let argv = attach_argv(socket, name);
+    log::info!("attaching with {:?}", argv);
+    let status = Command::new(&argv[0])
+        .args(&argv[1..])
+        .env_remove("TMUX")
         .status()
-        .map_err(|e| format!("Failed to kill session: {}", e))?;
+        .map_err(|e| spawn_error(context, e))?;
+    log::info!("attach exited with {}", status);
 
     if status.success() {
         Ok(())
     } else {
-        Err("Failed to kill tmux session".to_string())
+        // tmux wrote its error straight to the terminal
+        Err(TmuxError::CommandFailed {
+            context: context.to_string(),
+            stderr: String::new(),
+        })
     }
 }
 
-/// Returns true if currently running inside a tmux session
-pub fn is_inside_tmux() -> bool {
-    std::env::var("TMUX").is_ok()
+/// Arguments for `tmux attach-session`. The server is named explicitly
+/// (`socket`, else the socket from `$TMUX`), so attaching still reaches the
+/// same server once `$TMUX` is removed to avoid tmux's nesting check.
+pub fn attach_args(socket: Option<&str>, name: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(socket) = socket {
+        args.extend(["-L".to_string(), socket.to_string()]);
+    } else if let Some(socket) = env_socket().filter(|_| remote().is_none()) {
+        args.extend(["-S".to_string(), socket]);
+    }
+    args.extend([
+        "attach-session".to_string(),
+        "-t".to_string(),
+        name.to_string(),
+    ]);
+    args
 }
 
-pub fn attach_session(name: &str) -> Result<(), String> {
-    if is_inside_tmux() {
-        // Use switch-client when inside tmux
-        let output = Command::new("tmux")
-            .args(["switch-client", "-t", name])
-            .output()
-            .map_err(|e| format!("Failed to switch client: {}", e))?;
-
-        if output.status.success() {
-            Ok(())
-        } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(format!("Failed to switch to session: {}", stderr.trim()))
+/// The full command line that attaches to `name`: the tmux binary and
+/// [`attach_args`], or with a remote set the same through `ssh -t`, which
+/// gives the remote tmux a terminal
+pub fn attach_argv(socket: Option<&str>, name: &str) -> Vec<String> {
+    let args = attach_args(socket, name);
+    match remote() {
+        Some(destination) => {
+            let mut argv = vec!["ssh".to_string(), "-t".to_string(), destination];
+            argv.push("--".to_string());
+            argv.push(shell_quote(&binary()));
+            argv.extend(args.iter().map(|arg| shell_quote(arg)));
+            argv
         }
-    } else {
-        // Use attach-session when outside tmux
-        let status = Command::new("tmux")
-            .args(["attach-session", "-t", name])
-            .status()
-            .map_err(|e| format!("Failed to attach session: {}", e))?;
-
-        if status.success() {
-            Ok(())
-        } else {
-            Err("Failed to attach to tmux session".to_string())
+        None => {
+            let mut argv = vec![binary()];
+            argv.extend(args);
+            argv
         }
     }
 }
 
+/// Attach to `name` inside a popup over the current client (tmux 3.2+), so
+/// the client's own session and layout stay as they are underneath. Returns
+/// once the popup closes, e.g. on detaching.
+pub fn attach_in_popup(socket: Option<&str>, name: &str) -> Result<(), TmuxError> {
+    let args = popup_args(socket, name);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    // The popup opens on Ursa's own client, whichever server `name` is on
+    run(None, &args, "Failed to open tmux popup").map(drop)
+}
+
+/// Arguments for `display-popup` running an attach to `name`. The popup's
+/// shell has `$TMUX` set, which `tmux attach` would refuse as nesting, so
+/// it's cleared for the command.
+pub fn popup_args(socket: Option<&str>, name: &str) -> Vec<String> {
+    vec![
+        "display-popup".to_string(),
+        "-E".to_string(),
+        "-w".to_string(),
+        "90%".to_string(),
+        "-h".to_string(),
+        "90%".to_string(),
+        format!("TMUX= {}", attach_command(socket, name)),
+    ]
+}
+
+/// Launch `terminal` (a command prefix such as "alacritty -e") running an
+/// attach to `name`. The terminal is left running on its own; only failure to
+/// start it is reported, as a plain message since tmux isn't involved yet.
+pub fn open_in_terminal(terminal: &str, socket: Option<&str>, name: &str) -> Result<(), String> {
+    let mut parts = terminal.split_whitespace();
+    let Some(program) = parts.next() else {
+        return Err("Terminal command is empty".to_string());
+    };
+
+    Command::new(program)
+        .args(parts)
+        .args(attach_argv(socket, name))
+        .env_remove("TMUX")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => format!("Terminal '{}' not found", program),
+            _ => format!("Failed to launch '{}': {}", program, e),
+        })
+}
+
+/// The command a user can run by hand to attach to `name`
+pub fn attach_command(socket: Option<&str>, name: &str) -> String {
+    let argv: Vec<String> = attach_argv(socket, name)
+        .iter()
+        .map(|a| shell_quote(a))
+        .collect();
+    argv.join(" ")
+}
+
+/// A control-mode client (`tmux -C`) started by [`control_client`]. It's
+/// attached to a session like any client, but left out when listing which
+/// sessions are attached; dropping it ends it.
+#[derive(Debug)]
+pub struct ControlClient {
+    child: Child,
+    /// As tmux names it (`#{client_name}`)
+    name: String,
+}
+
+impl ControlClient {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for ControlClient {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        HIDDEN_CLIENTS
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|name| *name != self.name);
+    }
+}
+
+/// Start a control client on the server on `socket` to hear about changes,
+/// returned with the lines it prints: tmux's `%` notifications. It attaches
+/// read-only to the most recent session, without pane output, so there has
+/// to be a session to attach to.
+pub fn control_client(
+    socket: Option<&str>,
+) -> Result<(ControlClient, Lines<BufReader<ChildStdout>>), TmuxError> {
+    let context = "Failed to start a tmux control client";
+    let mut command = tmux_command(
+        socket,
+        &["-C", "attach-session", "-r", "-f", "no-output,ignore-size"],
+    );
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    log::debug!("{:?}", command);
+    let mut child = command.spawn().map_err(|e| spawn_error(context, e))?;
+
+    let Some(stdout) = child.stdout.take() else {
+        return Err(TmuxError::Io {
+            context: context.to_string(),
+            message: "no output to read".to_string(),
+        });
+    };
+    let mut lines = BufReader::new(stdout).lines();
+
+    // Commands answer between %begin and %end (or %error). The client's
+    // name is asked for once %session-changed says the attach is done, since
+    // it's blank until then.
+    let mut reply: Vec<String> = Vec::new();
+    let mut asked = false;
+    let mut name = None;
+    for line in lines.by_ref().map_while(Result::ok) {
+        if line.starts_with("%session-changed") && !asked {
+            if let Some(stdin) = child.stdin.as_mut() {
+                let _ = writeln!(stdin, "display-message -p '#{{client_name}}'");
+            }
+            asked = true;
+        } else if line.starts_with("%begin") {
+            reply.clear();
+        } else if line.starts_with("%error") {
+            // e.g. "no sessions"; tmux -C starts a server when there's none
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(TmuxError::CommandFailed {
+                context: context.to_string(),
+                stderr: reply.join("\n"),
+            });
+        } else if line.starts_with("%end") && asked {
+            name = reply.iter().find(|line| !line.trim().is_empty()).cloned();
+            if name.is_some() {
+                break;
+            }
+        } else if !line.starts_with('%') {
+            reply.push(line);
+        }
+    }
+    let Some(name) = name else {
+        // It exited without a word, e.g. when tmux is too old for the flags
+        let output = child
+            .wait_with_output()
+            .map_err(|e| spawn_error(context, e))?;
+        return Err(command_error(context, &output));
+    };
+
+    let name = name.trim().to_string();
+    HIDDEN_CLIENTS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(name.clone());
+    Ok((ControlClient { child, name }, lines))
+}
+
+/// Whether a control-mode notification line means the session list may read
+/// differently now: sessions created, closed or renamed, windows opened or
+/// closed, or clients switching sessions or detaching
+pub fn changes_session_list(line: &str) -> bool {
+    let notification = line.split(' ').next().unwrap_or_default();
+    matches!(
+        notification,
+        "%sessions-changed"
+            | "%session-renamed"
+            | "%window-add"
+            | "%window-close"
+            | "%unlinked-window-add"
+            | "%unlinked-window-close"
+            | "%client-session-changed"
+            | "%client-detached"
+    )
+}
+
+/// Server socket path from `$TMUX` ("socket,pid,session")
+fn env_socket() -> Option<String> {
+    let tmux = std::env::var("TMUX").ok()?;
+    let socket = tmux.split(',').next()?;
+    (!socket.is_empty()).then(|| socket.to_string())
+}
+
+/// Quote `s` for a POSIX shell when it contains anything beyond safe characters
+fn shell_quote(s: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_.:/@%+=".contains(c);
+    if !s.is_empty() && s.chars().all(safe) {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', "'\\''"))
+    }
+}
+
+/// Run a tmux command, turning a failure to start it or a non-zero exit into
+/// a [`TmuxError`] described by `context`
+fn run(socket: Option<&str>, args: &[&str], context: &str) -> Result<Output, TmuxError> {
+    let output = logged_output(tmux_command(socket, args)).map_err(|e| spawn_error(context, e))?;
+
+    if output.status.success() {
+        Ok(output)
+    } else {
+        Err(command_error(context, &output))
+    }
+}
+
+/// Run `command` to completion, logging it with its outcome
+fn logged_output(mut command: Command) -> std::io::Result<Output> {
+    let output = command.output();
+    log_command(&command, &output);
+    output
+}
+
+/// Record a finished command in the debug log: failures with tmux's stderr,
+/// successes only by their exit status
+fn log_command(command: &Command, output: &std::io::Result<Output>) {
+    match output {
+        Ok(output) if output.status.success() => log::debug!("{:?}: {}", command, output.status),
+        Ok(output) => log::warn!(
+            "{:?}: {}: {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => log::warn!("{:?}: failed to start: {}", command, e),
+    }
+}
+
+/// Run a tmux command and return its stdout
+fn query(socket: Option<&str>, args: &[&str], context: &str) -> Result<String, TmuxError> {
+    run(socket, args, context).map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn spawn_error(context: &str, e: std::io::Error) -> TmuxError {
+    if let Some(host) = remote() {
+        return TmuxError::Unreachable {
+            host,
+            message: match e.kind() {
+                std::io::ErrorKind::NotFound => "ssh is not installed".to_string(),
+                _ => e.to_string(),
+            },
+        };
+    }
+    match e.kind() {
+        std::io::ErrorKind::NotFound => TmuxError::NotInstalled,
+        _ => TmuxError::Io {
+            context: context.to_string(),
+            message: e.to_string(),
+        },
+    }
+}
+
+/// Classify a failed tmux command by its stderr, keeping tmux's message for
+/// failures that aren't recognized
+fn command_error(context: &str, output: &Output) -> TmuxError {
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    // ssh exits with 255 for its own failures, any other code is tmux's
+    if let Some(host) = remote().filter(|_| output.status.code() == Some(255)) {
+        let message = stderr.lines().last().unwrap_or("connection failed");
+        return TmuxError::Unreachable {
+            host,
+            message: message.to_string(),
+        };
+    }
+    // The wording differs between "no socket yet" and "stale socket"
+    if stderr.contains("no server running") || stderr.contains("error connecting to") {
+        return TmuxError::NoServer;
+    }
+    if let Some(name) = stderr_subject(&stderr, "duplicate session: ") {
+        return TmuxError::DuplicateName(name);
+    }
+    // Older tmux versions say "session not found"
+    if let Some(name) = stderr_subject(&stderr, "can't find session: ")
+        .or_else(|| stderr_subject(&stderr, "session not found: "))
+    {
+        return TmuxError::NotFound(name);
+    }
+    TmuxError::CommandFailed {
+        context: context.to_string(),
+        stderr,
+    }
+}
+
+/// What follows `prefix` on the first stderr line containing it
+fn stderr_subject(stderr: &str, prefix: &str) -> Option<String> {
+    stderr
+        .lines()
+        .find_map(|line| line.split_once(prefix))
+        .map(|(_, subject)| subject.trim().to_string())
+}
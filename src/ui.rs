@@ -1,28 +1,119 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use ratatui::{
-    layout::{Constraint, Layout, Rect},
+    layout::{Alignment, Constraint, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::{Line, Span},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Gauge, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
 
-use crate::app::{App, AppState, FocusArea, SessionAction};
+use ursa::app::{
+    match_positions, middle_truncation, App, AppState, BatchItem, BatchOperation, BatchProgress,
+    CleanupKind, FocusArea, LayoutMode, SearchStyle, SessionAction, SessionTarget,
+};
+use ursa::config::{config_path, RowStyle};
+use ursa::input::TextInput;
+use ursa::tags::Tag;
+use ursa::tmux::{self, TmuxError, TmuxSession};
+
+/// Below this width the session list uses compact rows regardless of config
+const COMPACT_BELOW_WIDTH: u16 = 60;
+
+/// Long names are shortened to fit the row, but never below this many chars
+const MIN_NAME_WIDTH: usize = 8;
+
+/// From this width the detail pane sits beside the list; narrower terminals
+/// show one or the other depending on focus
+const DETAIL_PANE_MIN_WIDTH: u16 = 120;
+
+/// Narrowest session card in grid mode, borders included; cards grow to share
+/// leftover width
+const CARD_MIN_WIDTH: u16 = 26;
+const CARD_HEIGHT: u16 = 4;
+
+/// Room kept at the end of each page of an overflowing help bar for its
+/// " [2/3]" marker
+const HELP_MARKER_WIDTH: usize = 8;
 
 pub fn render(frame: &mut Frame, app: &App) {
+    let limit = app.session_limit_reached();
     let chunks = Layout::vertical([
-        Constraint::Length(3), // Title
-        Constraint::Min(5),    // Main content
-        Constraint::Length(3), // Help bar
+        Constraint::Length(3),                          // Title
+        Constraint::Length(u16::from(limit.is_some())), // Limit banner
+        Constraint::Min(5),                             // Main content
+        Constraint::Length(3),                          // Help bar
     ])
     .split(frame.area());
 
     render_title(frame, chunks[0], app);
-    render_session_list(frame, chunks[1], app);
-    render_help_bar(frame, chunks[2], app);
+    if let Some(max) = limit {
+        render_limit_banner(frame, chunks[1], max, app);
+    }
+    let main = chunks[2];
+    let fits = main.width >= DETAIL_PANE_MIN_WIDTH;
+    app.detail_pane_fits.set(fits);
+    if fits {
+        let columns = Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(main);
+        render_sessions(frame, columns[0], app);
+        render_details(frame, columns[1], app);
+    } else if app.focus_area == FocusArea::Detail {
+        render_details(frame, main, app);
+    } else if app.tailing {
+        // No room beside the list, so the live preview goes below it
+        let rows =
+            Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).split(main);
+        render_sessions(frame, rows[0], app);
+        render_tail(frame, rows[1], app);
+    } else {
+        render_sessions(frame, main, app);
+    }
+    render_help_bar(frame, chunks[3], app);
+
+    if let AppState::ConfirmingBatch {
+        ref operation,
+        ref items,
+        cursor,
+    } = app.state
+    {
+        render_batch_popup(frame, operation, items, cursor, app);
+    }
+
+    if let AppState::RunningBatch { ref progress } = app.state {
+        render_batch_progress_popup(frame, progress, app);
+    }
+
+    if app.state == AppState::ViewingLog {
+        render_log_popup(frame, app);
+    }
+
+    if app.state == AppState::Welcome {
+        render_welcome_popup(frame, app);
+    }
+
+    if let AppState::ConfirmingLocked {
+        ref name, action, ..
+    } = app.state
+    {
+        render_locked_popup(frame, name, action, app);
+    }
+
+    if let AppState::ConfirmingAttachedRename { ref name, .. } = app.state {
+        render_attached_rename_popup(frame, name, app);
+    }
+
+    if let AppState::ConfirmingOwnDelete { ref name } = app.state {
+        render_own_delete_popup(frame, name, app);
+    }
+
+    if let Some(ref toast) = app.toast {
+        render_toast(frame, main, &toast.message, app);
+    }
 
     // Render error message if any
     if let Some(ref error) = app.error_message {
-        render_error_popup(frame, error);
+        render_error_popup(frame, error, app);
     }
 }
 
@@ -31,165 +122,1398 @@ fn render_title(frame: &mut Frame, area: Rect, app: &App) {
         app.focus_area == FocusArea::TitleBar && app.state == AppState::SessionList;
 
     let refresh_style = if is_refresh_focused {
-        Style::default()
-            .fg(Color::Black)
-            .bg(Color::Cyan)
-            .add_modifier(Modifier::BOLD)
+        app.theme.active(Color::Cyan).add_modifier(Modifier::BOLD)
     } else {
-        Style::default().fg(Color::DarkGray)
+        app.theme.fg(Color::DarkGray)
     };
 
-    let title_line = Line::from(vec![
+    let mut title_spans = vec![
         Span::styled(
             "  Ursa - Tmux Session Manager  ",
-            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            app.theme.fg(Color::Cyan).add_modifier(Modifier::BOLD),
         ),
         Span::styled("Refresh", refresh_style),
-    ]);
+    ];
+    if let Some(host) = tmux::remote() {
+        title_spans.push(Span::raw("  "));
+        title_spans.push(Span::styled(
+            format!(" {} ", host),
+            app.theme
+                .active(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    if app.dry_run {
+        title_spans.push(Span::raw("  "));
+        title_spans.push(Span::styled(
+            " DRY RUN ",
+            app.theme.active(Color::Yellow).add_modifier(Modifier::BOLD),
+        ));
+    }
+    title_spans.push(Span::styled(
+        format!("  {}", breadcrumb(app)),
+        app.theme.fg(Color::DarkGray),
+    ));
+    let title_line = Line::from(title_spans);
 
     let title = Paragraph::new(title_line).block(Block::default().borders(Borders::BOTTOM));
     frame.render_widget(title, area);
 }
 
+/// A line under the title while `max_sessions` is reached, so it's clear
+/// before trying why new sessions are refused
+fn render_limit_banner(frame: &mut Frame, area: Rect, max: usize, app: &App) {
+    let banner = Paragraph::new(format!(
+        " {} of {} sessions (max_sessions); kill some to create more ",
+        app.sessions.len(),
+        max
+    ))
+    .style(app.theme.active(Color::Yellow).add_modifier(Modifier::BOLD));
+    frame.render_widget(banner, area);
+}
+
+/// Where the user is and which modes are on, e.g. "Sessions › Rename ·
+/// filter:api · tag:red", so it's clear at a glance (and in screenshots)
+fn breadcrumb(app: &App) -> String {
+    let screen = match &app.state {
+        AppState::SessionList if app.focus_area == FocusArea::Detail => "Details",
+        AppState::SessionList => "",
+        AppState::CreatingSession => "New session",
+        AppState::AddingEnv { .. } => "New session › Environment",
+        AppState::GoingTo => "Go to",
+        AppState::NumberingSessions => "Renumber",
+        AppState::RenamingSession { .. } | AppState::AffixingName { .. } => "Rename",
+        AppState::CreatingWindow { .. } => "New window",
+        AppState::SavingScrollback { .. } => "Save scrollback",
+        AppState::EditingNote { .. } => "Note",
+        AppState::Searching => "Search",
+        AppState::ConfirmingBatch {
+            operation: BatchOperation::Kill(_),
+            ..
+        } => "Kill",
+        AppState::ConfirmingBatch { .. } => "Renumber",
+        AppState::RunningBatch { ref progress } => match progress.operation {
+            BatchOperation::Kill(_) => "Kill",
+            BatchOperation::Renumber { .. } => "Renumber",
+        },
+        AppState::ViewingLog => "Action log",
+        AppState::Welcome => "Welcome",
+        AppState::ConfirmingLocked { .. }
+        | AppState::ConfirmingAttachedRename { .. }
+        | AppState::ConfirmingOwnDelete { .. } => "Confirm",
+    };
+    let mut crumb = String::from("Sessions");
+    if !screen.is_empty() {
+        crumb.push_str(" › ");
+        crumb.push_str(screen);
+    }
+
+    let mut modes = Vec::new();
+    if app.layout_mode == LayoutMode::Grid {
+        modes.push("view:grid".to_string());
+    }
+    if !app.search_query.is_empty() {
+        let style = match app.search_style {
+            SearchStyle::Filter => "filter",
+            SearchStyle::Jump => "jump",
+        };
+        modes.push(format!("{}:{}", style, app.search_query.as_str()));
+    }
+    if let Some(tag) = app.tag_filter {
+        modes.push(format!("tag:{}", tag.name()));
+    }
+    if app.show_hidden {
+        modes.push("hidden:shown".to_string());
+    }
+    for mode in modes {
+        crumb.push_str(" · ");
+        crumb.push_str(&mode);
+    }
+    crumb
+}
+
+/// The sessions as a list or grid. Prompts that edit a row in place always
+/// use the list.
+fn render_sessions(frame: &mut Frame, area: Rect, app: &App) {
+    let inline_prompt = matches!(
+        app.state,
+        AppState::CreatingSession
+            | AppState::AddingEnv { .. }
+            | AppState::GoingTo
+            | AppState::NumberingSessions
+            | AppState::RenamingSession { .. }
+            | AppState::AffixingName { .. }
+            | AppState::CreatingWindow { .. }
+            | AppState::SavingScrollback { .. }
+            | AppState::EditingNote { .. }
+    );
+    if app.layout_mode == LayoutMode::Grid && !inline_prompt {
+        render_session_grid(frame, area, app);
+    } else {
+        render_session_list(frame, area, app);
+    }
+}
+
+/// Sessions as cards across as many columns as fit, followed by a card for
+/// creating one
+fn render_session_grid(frame: &mut Frame, area: Rect, app: &App) {
+    let block = Block::default()
+        .title(" Sessions ")
+        .borders(Borders::ALL)
+        .border_style(app.theme.fg(Color::DarkGray));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let columns = usize::from((inner.width / CARD_MIN_WIDTH).max(1));
+    let card_width = inner.width / columns as u16;
+    let rows = usize::from((inner.height / CARD_HEIGHT).max(1));
+    app.grid_columns.set(columns);
+
+    // Scroll just enough to keep the selected card's row on screen
+    let selected_row = app.selected_index / columns;
+    let mut scroll = app.grid_scroll.get();
+    if selected_row < scroll {
+        scroll = selected_row;
+    } else if selected_row >= scroll + rows {
+        scroll = selected_row + 1 - rows;
+    }
+    app.grid_scroll.set(scroll);
+
+    let visible = app.visible_sessions();
+    for index in scroll * columns..(visible.len() + 1).min((scroll + rows) * columns) {
+        let cell = Rect {
+            x: inner.x + (index % columns) as u16 * card_width,
+            y: inner.y + (index / columns - scroll) as u16 * CARD_HEIGHT,
+            width: card_width,
+            height: CARD_HEIGHT.min(inner.height),
+        };
+        let selected = index == app.selected_index && app.focus_area != FocusArea::TitleBar;
+        match visible.get(index) {
+            Some(&session) => render_card(frame, cell, &app.sessions[session], selected, app),
+            None => render_create_card(frame, cell, selected, app),
+        }
+    }
+}
+
+fn card_block(selected: bool, app: &App) -> Block<'static> {
+    let border = if selected {
+        app.theme.fg(Color::Cyan).add_modifier(Modifier::BOLD)
+    } else {
+        app.theme.fg(Color::DarkGray)
+    };
+    Block::default().borders(Borders::ALL).border_style(border)
+}
+
+/// One session in the grid: name, then window count and attachment
+fn render_card(frame: &mut Frame, area: Rect, session: &TmuxSession, selected: bool, app: &App) {
+    let block = card_block(selected, app);
+    let width = usize::from(block.inner(area).width);
+
+    let mut name_line = vec![tag_span(app.tags.get(&session.name).copied(), app)];
+    let name_at = name_line.len();
+    if app.is_locked(&session.name) {
+        name_line.push(Span::styled(" 🔒", app.theme.fg(Color::Yellow)));
+    }
+    insert_name(&mut name_line, name_at, &session.name, width, app);
+    if selected {
+        for span in &mut name_line {
+            span.style = span.style.add_modifier(Modifier::BOLD);
+        }
+    }
+
+    let plural = if session.windows == 1 { "" } else { "s" };
+    let mut meta_line = vec![Span::styled(
+        format!("  {} window{}", session.windows, plural),
+        app.theme.fg(window_count_color(session.windows, app)),
+    )];
+    if session.attached {
+        let color = app.config.attached_color().unwrap_or(Color::Green);
+        meta_line.push(Span::styled(" *", app.theme.fg(color)));
+    }
+
+    let card = Paragraph::new(vec![Line::from(name_line), Line::from(meta_line)]).block(block);
+    frame.render_widget(card, area);
+}
+
+fn render_create_card(frame: &mut Frame, area: Rect, selected: bool, app: &App) {
+    let card = Paragraph::new(Line::from(Span::styled(
+        "  + New session",
+        app.theme.fg(Color::Green),
+    )))
+    .block(card_block(selected, app));
+    frame.render_widget(card, area);
+}
+
 fn render_session_list(frame: &mut Frame, area: Rect, app: &App) {
-    let mut items: Vec<ListItem> = app
-        .sessions
+    let visible = app.visible_sessions();
+    let row_style = if area.width < COMPACT_BELOW_WIDTH {
+        RowStyle::Compact
+    } else {
+        app.config.row_style
+    };
+    // Inside the borders and past the highlight symbol
+    let row_width = usize::from(area.width.saturating_sub(3));
+    let mut items: Vec<ListItem> = visible
         .iter()
+        .map(|&index| &app.sessions[index])
         .enumerate()
         .map(|(i, session)| {
             // Check if this session is being renamed
             let is_renaming = matches!(app.state, AppState::RenamingSession { .. })
                 && i == app.selected_index;
 
-            if is_renaming {
+            let mut text = if is_renaming {
                 // Show inline input for rename
-                let input_text = format!("  {}_", app.input_buffer);
-                ListItem::new(Line::from(vec![Span::styled(
-                    input_text,
-                    Style::default().fg(Color::Yellow),
-                )]))
+                let mut spans = vec![Span::raw("   ")];
+                spans.extend(input_spans(&app.input, app.theme.fg(Color::Yellow)));
+                spans.extend(validation_span(app));
+                Text::from(Line::from(spans))
             } else {
-                // Normal session row
-                let attached_indicator = if session.attached { " (attached)" } else { "" };
-                let is_selected = i == app.selected_index;
+                session_row(session, i, row_style, row_width, app)
+            };
+            text.lines
+                .extend((0..app.config.row_spacing).map(|_| Line::default()));
+            ListItem::new(text)
+        })
+        .collect();
 
-                // Build action buttons for existing sessions
-                // Use lighter gray for inactive buttons on highlighted rows for better contrast
-                let inactive_color = if is_selected { Color::Gray } else { Color::DarkGray };
+    // Add inline window-name input below the target session
+    if let AppState::CreatingWindow { .. } = app.state {
+        let mut spans = vec![Span::styled("    + window: ", app.theme.fg(Color::Cyan))];
+        if app.input.is_empty() {
+            spans.push(Span::styled(
+                "window name (optional)",
+                app.theme.fg(Color::DarkGray),
+            ));
+        }
+        spans.extend(input_spans(&app.input, app.theme.fg(Color::Cyan)));
+        items.insert(app.selected_index + 1, ListItem::new(Line::from(spans)));
+    }
 
-                let enter_style = if is_selected && app.selected_action == SessionAction::Enter {
-                    Style::default().fg(Color::Black).bg(Color::Cyan)
-                } else {
-                    Style::default().fg(inactive_color)
-                };
-                let rename_style = if is_selected && app.selected_action == SessionAction::Rename {
-                    Style::default().fg(Color::Black).bg(Color::Yellow)
-                } else {
-                    Style::default().fg(inactive_color)
-                };
-                let delete_style = if is_selected && app.selected_action == SessionAction::Delete {
-                    Style::default().fg(Color::Black).bg(Color::Red)
-                } else {
-                    Style::default().fg(inactive_color)
-                };
+    // Likewise for the prefix or suffix to add to the name, with the result
+    if let AppState::AffixingName { prepend, .. } = app.state {
+        let label = if prepend {
+            "    + prefix: "
+        } else {
+            "    + suffix: "
+        };
+        let mut spans = vec![Span::styled(label, app.theme.fg(Color::Cyan))];
+        spans.extend(input_spans(&app.input, app.theme.fg(Color::Cyan)));
+        if let Some(name) = app.affixed_name().filter(|_| !app.input.is_empty()) {
+            spans.push(Span::styled(
+                format!("  → {}", name),
+                app.theme.fg(Color::DarkGray),
+            ));
+        }
+        spans.extend(validation_span(app));
+        items.insert(app.selected_index + 1, ListItem::new(Line::from(spans)));
+    }
 
-                ListItem::new(Line::from(vec![
-                    Span::raw("  "),
-                    Span::raw(&session.name),
-                    Span::styled(
-                        format!(
-                            " [{} window{}]{}",
-                            session.windows,
-                            if session.windows == 1 { "" } else { "s" },
-                            attached_indicator
-                        ),
-                        Style::default().fg(Color::DarkGray),
-                    ),
-                    Span::raw("  "),
-                    Span::styled("[Enter]", enter_style),
-                    Span::raw(" "),
-                    Span::styled("[Rename]", rename_style),
-                    Span::raw(" "),
-                    Span::styled("[Delete]", delete_style),
-                ]))
-            }
-        })
-        .collect();
+    // Likewise for the file to save the scrollback to
+    if let AppState::SavingScrollback { .. } = app.state {
+        let mut spans = vec![Span::styled("    > save to: ", app.theme.fg(Color::Cyan))];
+        spans.extend(input_spans(&app.input, app.theme.fg(Color::Cyan)));
+        items.insert(app.selected_index + 1, ListItem::new(Line::from(spans)));
+    }
+
+    // And for the session's note
+    if let AppState::EditingNote { .. } = app.state {
+        let mut spans = vec![Span::styled("    ✎ note: ", app.theme.fg(Color::Cyan))];
+        if app.input.is_empty() {
+            spans.push(Span::styled(
+                "empty to remove",
+                app.theme.fg(Color::DarkGray),
+            ));
+        }
+        spans.extend(input_spans(&app.input, app.theme.fg(Color::Cyan)));
+        items.insert(app.selected_index + 1, ListItem::new(Line::from(spans)));
+    }
+
+    // Sessions that just vanished linger below the live ones for a moment
+    for (name, _) in &app.vanished {
+        items.push(ListItem::new(Line::from(Span::styled(
+            format!("   {} (closed)", name),
+            app.theme
+                .fg(Color::Red)
+                .add_modifier(Modifier::CROSSED_OUT | Modifier::DIM),
+        ))));
+    }
 
     // Add inline input row when creating session
     if app.state == AppState::CreatingSession {
-        let input_text = format!("  {}_", app.input_buffer);
-        items.push(ListItem::new(Line::from(vec![Span::styled(
-            input_text,
-            Style::default().fg(Color::Cyan),
-        )])));
+        let input_style = app.theme.fg(Color::Cyan);
+        let mut spans = vec![Span::raw("  ")];
+        // Ghosted completion, accepted with Tab/→ while the cursor is at the end
+        match app
+            .suggest(app.input.as_str())
+            .filter(|_| app.input.at_end())
+        {
+            Some(suggestion) => spans.extend([
+                Span::styled(app.input.as_str(), input_style),
+                Span::styled(
+                    suggestion[app.input.as_str().len()..].to_string(),
+                    app.theme.fg(Color::DarkGray).add_modifier(Modifier::DIM),
+                ),
+                Span::styled("_", input_style),
+            ]),
+            None => spans.extend(input_spans(&app.input, input_style)),
+        }
+        spans.extend(validation_span(app));
+        if let Some((name, _)) = app.dropped_directory() {
+            spans.push(Span::styled(
+                format!("  detected directory — using basename '{}'", name),
+                app.theme.fg(Color::DarkGray),
+            ));
+        }
+        push_create_options(&mut spans, app);
+        items.push(ListItem::new(Line::from(spans)));
+    }
+
+    // The name stays on the row while an environment entry is typed after it
+    if let AppState::AddingEnv { ref session_name } = app.state {
+        let input_style = app.theme.fg(Color::Cyan);
+        let mut spans = vec![
+            Span::raw("  "),
+            Span::styled(session_name.as_str(), app.theme.fg(Color::DarkGray)),
+        ];
+        push_create_options(&mut spans, app);
+        spans.push(Span::styled("  + ", input_style));
+        spans.extend(input_spans(&app.input, input_style));
+        spans.extend(validation_span(app));
+        items.push(ListItem::new(Line::from(spans)));
+    }
+
+    // Same place for the go-to prompt, saying whether Enter attaches or creates
+    if app.state == AppState::GoingTo {
+        let input_style = app.theme.fg(Color::Cyan);
+        let mut spans = vec![Span::styled("  → ", input_style)];
+        spans.extend(input_spans(&app.input, input_style));
+        let name = app.input.as_str().trim();
+        match app.validate_input() {
+            Some(Ok(_)) => {
+                let outcome = if app.sessions.iter().any(|s| s.name == name) {
+                    "  attach"
+                } else {
+                    "  create and attach"
+                };
+                spans.push(Span::styled(outcome, app.theme.fg(Color::DarkGray)));
+            }
+            _ if name.is_empty() => spans.push(Span::styled(
+                "session to attach to or create",
+                app.theme.fg(Color::DarkGray),
+            )),
+            _ => spans.extend(validation_span(app)),
+        }
+        items.push(ListItem::new(Line::from(spans)));
+    }
+
+    // And for the prefix to renumber the listed sessions under
+    if app.state == AppState::NumberingSessions {
+        let input_style = app.theme.fg(Color::Cyan);
+        let mut spans = vec![Span::styled("  # prefix: ", input_style)];
+        spans.extend(input_spans(&app.input, input_style));
+        let prefix = app.input.as_str().trim();
+        match app.validate_input() {
+            Some(Ok(_)) => spans.push(Span::styled(
+                format!("  → {0}-1, {0}-2, …", prefix),
+                app.theme.fg(Color::DarkGray),
+            )),
+            _ if prefix.is_empty() => spans.push(Span::styled(
+                "sessions are renamed prefix-1, prefix-2, … in list order",
+                app.theme.fg(Color::DarkGray),
+            )),
+            _ => spans.extend(validation_span(app)),
+        }
+        items.push(ListItem::new(Line::from(spans)));
     }
 
     // Add "Create new session" option
     items.push(ListItem::new(Line::from(vec![
-        Span::styled("  + ", Style::default().fg(Color::Green)),
-        Span::styled("Create new session", Style::default().fg(Color::Green)),
+        Span::styled("  + ", app.theme.fg(Color::Green)),
+        Span::styled("Create new session", app.theme.fg(Color::Green)),
     ])));
 
+    // Detailed rows take two lines each, plus any spacing after them
+    let row_height = if row_style == RowStyle::Detailed { 2 } else { 1 }
+        + usize::from(app.config.row_spacing);
+    app.page_size
+        .set((usize::from(area.height.saturating_sub(2)) / row_height).max(1));
+    let mut block = Block::default()
+        .title(" Sessions ")
+        .borders(Borders::ALL)
+        .border_style(app.theme.fg(Color::DarkGray));
+    if app.config.paginate {
+        block = block.title_bottom(format!(" Page {}/{} ", app.page() + 1, app.page_count()));
+    }
     let list = List::new(items)
-        .block(
-            Block::default()
-                .title(" Sessions ")
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::DarkGray)),
-        )
-        .highlight_style(
-            Style::default()
-                .bg(Color::DarkGray)
-                .add_modifier(Modifier::BOLD),
-        )
+        .block(block)
+        .highlight_style(app.theme.highlight())
         .highlight_symbol(">");
 
     // Highlight the input row when creating, otherwise use selected_index
-    let highlight_index = if app.state == AppState::CreatingSession {
-        app.sessions.len() // The input row
-    } else {
-        app.selected_index
+    // Rows past the sessions shift down by the lingering vanished ones
+    let highlight_index = match app.state {
+        // The input row
+        AppState::CreatingSession
+        | AppState::AddingEnv { .. }
+        | AppState::GoingTo
+        | AppState::NumberingSessions => {
+            visible.len() + app.vanished.len()
+        }
+        AppState::CreatingWindow { .. }
+        | AppState::SavingScrollback { .. }
+        | AppState::EditingNote { .. } => app.selected_index + 1,
+        _ if app.selected_index >= visible.len() => app.selected_index + app.vanished.len(),
+        _ => app.selected_index,
     };
 
-    let mut state = ListState::default();
+    // Paged, the list starts at the cursor's page rather than following it
+    let offset = if app.config.paginate {
+        app.page() * app.page_size.get()
+    } else {
+        app.scroll_offset.get()
+    };
+    let mut state = ListState::default().with_offset(offset);
     state.select(Some(highlight_index));
 
     frame.render_stateful_widget(list, area, &mut state);
+    app.scroll_offset.set(state.offset());
+
+    if app.sessions.is_empty() && !app.is_filtering() {
+        render_empty_state(frame, area, app);
+    }
+}
+
+/// A session row laid out according to `row_style`. Names too long for
+/// `row_width` next to the rest of their line are truncated in the middle.
+fn session_row<'a>(
+    session: &'a TmuxSession,
+    row: usize,
+    row_style: RowStyle,
+    row_width: usize,
+    app: &'a App,
+) -> Text<'a> {
+    let is_selected = row == app.selected_index;
+    // A bar in the first column picks out attached sessions wherever the
+    // cursor is
+    let lead = match app.config.attached_color().filter(|_| session.attached) {
+        Some(color) => Span::styled("▌", app.theme.fg(color)),
+        None => Span::raw(" "),
+    };
+    let mut name_line = vec![lead];
+    // Distance from the cursor, as a count for j/k; the cursor row shows its
+    // own position like vim's hybrid numbering
+    if app.relative_numbers {
+        let number = if is_selected {
+            row + 1
+        } else {
+            row.abs_diff(app.selected_index)
+        };
+        name_line.push(Span::styled(
+            format!("{:>3} ", number),
+            app.theme.fg(Color::DarkGray),
+        ));
+    }
+    name_line.push(tag_span(app.tags.get(&session.name).copied(), app));
+    // The name goes here once the rest of the line is known
+    let name_at = name_line.len();
+    if app.is_locked(&session.name) {
+        name_line.push(Span::styled(" 🔒", app.theme.fg(Color::Yellow)));
+    }
+    if app.is_zombie(&session.name) {
+        name_line.push(Span::styled(" ⚠ dir gone", app.theme.fg(Color::Red)));
+    }
+    if let Some(group) = &session.group {
+        name_line.push(Span::styled(
+            format!(" ⧉ {}", group),
+            app.theme.fg(Color::Magenta),
+        ));
+    }
+
+    // With several sockets configured, show which server each session is on
+    if !app.config.sockets.is_empty() {
+        name_line.push(Span::styled(
+            format!(" @{}", session.socket.as_deref().unwrap_or("default")),
+            app.theme.fg(Color::Blue),
+        ));
+    }
+
+    let plural = if session.windows == 1 { "" } else { "s" };
+    let meta_style = app.theme.fg(Color::DarkGray);
+    let count_style = app.theme.fg(window_count_color(session.windows, app));
+
+    // Use lighter gray for inactive buttons on highlighted rows for better contrast
+    let inactive_color = if is_selected { Color::Gray } else { Color::DarkGray };
+    let mut buttons = Vec::new();
+    let actions = if is_selected || !app.config.minimal_buttons {
+        app.actions()
+    } else {
+        &[]
+    };
+    for (i, &action) in actions.iter().enumerate() {
+        if i > 0 {
+            buttons.push(Span::raw(" "));
+        }
+        let (label, color) = action_button(action);
+        let style = if is_selected && app.selected_action == action {
+            app.theme.active(color)
+        } else {
+            app.theme.fg(inactive_color)
+        };
+        buttons.push(Span::styled(label, style));
+    }
+
+    match row_style {
+        RowStyle::Inline => {
+            // The badges stay right after the name wherever `{name}` is
+            let badges = name_line.split_off(name_at);
+            let name_at = push_row_format(&mut name_line, badges, session, count_style, app);
+            if !buttons.is_empty() {
+                name_line.push(Span::raw("  "));
+                name_line.extend(buttons);
+            }
+            insert_name(&mut name_line, name_at, &session.name, row_width, app);
+            push_note_hint(&mut name_line, &session.name, row_width, app);
+            Text::from(Line::from(name_line))
+        }
+        RowStyle::Compact => {
+            let attached = if session.attached { "*" } else { "" };
+            name_line.extend([
+                Span::styled(format!(" {}w", session.windows), count_style),
+                Span::styled(attached, meta_style),
+            ]);
+            // Only the action that Enter would run, to keep the row short
+            if is_selected {
+                let (label, color) = action_button(app.selected_action);
+                name_line.push(Span::raw(" "));
+                name_line.push(Span::styled(label, app.theme.active(color)));
+            }
+            insert_name(&mut name_line, name_at, &session.name, row_width, app);
+            push_note_hint(&mut name_line, &session.name, row_width, app);
+            Text::from(Line::from(name_line))
+        }
+        RowStyle::Detailed => {
+            let attached = if session.attached { ", attached" } else { "" };
+            let mut detail_line = vec![
+                Span::raw("     "),
+                Span::styled(format!("{} window{}", session.windows, plural), count_style),
+                Span::styled(format!("{}  ", attached), meta_style),
+            ];
+            detail_line.extend(buttons);
+            insert_name(&mut name_line, name_at, &session.name, row_width, app);
+            push_note_hint(&mut detail_line, &session.name, row_width, app);
+            Text::from(vec![Line::from(name_line), Line::from(detail_line)])
+        }
+    }
+}
+
+/// A token in `row_format`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RowToken {
+    Name,
+    Windows,
+    Path,
+    Activity,
+    Attached,
+}
+
+/// Split `format` into literal text and `{token}`s. Unknown tokens and
+/// unclosed braces stay literal, so a typo shows up in the row as typed.
+fn parse_row_format(format: &str) -> Vec<Result<RowToken, &str>> {
+    let mut parts = Vec::new();
+    let mut rest = format;
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}').map(|i| open + i) else {
+            break;
+        };
+        let token = match &rest[open + 1..close] {
+            "name" => RowToken::Name,
+            "windows" => RowToken::Windows,
+            "path" => RowToken::Path,
+            "activity" => RowToken::Activity,
+            "attached" => RowToken::Attached,
+            _ => {
+                parts.push(Err(&rest[..=close]));
+                rest = &rest[close + 1..];
+                continue;
+            }
+        };
+        if open > 0 {
+            parts.push(Err(&rest[..open]));
+        }
+        parts.push(Ok(token));
+        rest = &rest[close + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(Err(rest));
+    }
+    parts
+}
+
+/// Append the session's `row_format` to `line` and return where the name
+/// goes; `badges` (lock, group, …) follow it. Without a `{name}` token the
+/// name leads the row anyway.
+fn push_row_format<'a>(
+    line: &mut Vec<Span<'a>>,
+    badges: Vec<Span<'a>>,
+    session: &TmuxSession,
+    count_style: Style,
+    app: &App,
+) -> usize {
+    let meta_style = app.theme.fg(Color::DarkGray);
+    let mut parts = parse_row_format(&app.config.row_format);
+    if !parts.contains(&Ok(RowToken::Name)) {
+        parts.insert(0, Ok(RowToken::Name));
+    }
+
+    let start = line.len();
+    let mut name_at = start;
+    let mut badges = Some(badges);
+    for part in parts {
+        let span = match part {
+            Err(text) => Span::styled(text.to_string(), meta_style),
+            Ok(RowToken::Name) => {
+                name_at = line.len();
+                line.extend(badges.take().unwrap_or_default());
+                continue;
+            }
+            Ok(RowToken::Windows) => {
+                let plural = if session.windows == 1 { "" } else { "s" };
+                Span::styled(format!("{} window{}", session.windows, plural), count_style)
+            }
+            Ok(RowToken::Path) => Span::styled(session.path.clone(), meta_style),
+            Ok(RowToken::Activity) => {
+                Span::styled(format_timestamp_age(session.last_activity), meta_style)
+            }
+            Ok(RowToken::Attached) if session.attached => Span::styled("(attached)", meta_style),
+            Ok(RowToken::Attached) => continue,
+        };
+        line.push(span);
+    }
+
+    // An empty token at the end would leave the separator before it dangling
+    if line.len() > start {
+        if let Some(last) = line.last_mut() {
+            let trimmed = last.content.trim_end().to_string();
+            last.content = trimmed.into();
+        }
+    }
+    name_at
+}
+
+/// Full details of the selected session: path, times, clients and windows
+fn render_details(frame: &mut Frame, area: Rect, app: &App) {
+    if app.tailing {
+        render_tail(frame, area, app);
+        return;
+    }
+    let border_color = if app.focus_area == FocusArea::Detail {
+        Color::Cyan
+    } else {
+        Color::DarkGray
+    };
+    let block = Block::default()
+        .title(" Details ")
+        .borders(Borders::ALL)
+        .border_style(app.theme.fg(border_color));
+    let dim = app.theme.fg(Color::DarkGray);
+
+    let Some(session) = app.selected_session() else {
+        let hint = Paragraph::new(Line::from(Span::styled(
+            "Select a session to see its details",
+            dim,
+        )))
+        .block(block);
+        frame.render_widget(hint, area);
+        return;
+    };
+
+    let label = |text: &'static str| Span::styled(format!("{:<10}", text), dim);
+    let mut lines = vec![
+        Line::from(Span::styled(
+            app.display_name(&session.name),
+            app.theme.fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(vec![
+            label("Activity"),
+            Span::raw(format_timestamp_age(session.last_activity)),
+            Span::styled(
+                if app.is_long_idle(&session.name) {
+                    "  (idle)"
+                } else {
+                    ""
+                },
+                dim,
+            ),
+        ]),
+    ];
+    if let Some(note) = app.notes.get(&session.name) {
+        lines.push(Line::from(vec![
+            label("Note"),
+            Span::styled(note.as_str(), app.theme.fg(Color::Yellow)),
+        ]));
+    }
+    if let Some(group) = &session.group {
+        lines.push(Line::from(vec![label("Group"), Span::raw(group.as_str())]));
+    }
+    if !app.config.sockets.is_empty() {
+        lines.push(Line::from(vec![
+            label("Socket"),
+            Span::raw(session.socket.as_deref().unwrap_or("default")),
+        ]));
+    }
+
+    // Names are only unique per server, so the socket has to match too
+    let is_shown =
+        |(socket, name): &SessionTarget| *name == session.name && *socket == session.socket;
+    match &app.details {
+        Some((target, Ok(details))) if is_shown(target) => {
+            lines.extend([
+                Line::from(vec![
+                    label("Path"),
+                    Span::raw(details.path.as_str()),
+                    Span::styled(
+                        if app.is_zombie(&session.name) {
+                            "  (deleted)"
+                        } else {
+                            ""
+                        },
+                        app.theme.fg(Color::Red),
+                    ),
+                ]),
+                Line::from(vec![
+                    label("Created"),
+                    Span::raw(format_timestamp_age(details.created)),
+                ]),
+            ]);
+            let clients = if details.clients.is_empty() {
+                "none".to_string()
+            } else {
+                details.clients.join(", ")
+            };
+            lines.push(Line::from(vec![label("Clients"), Span::raw(clients)]));
+            let synced = details.windows.iter().filter(|w| w.synchronized).count();
+            lines.push(Line::from(vec![
+                label("Sync"),
+                match synced {
+                    0 => Span::styled("off", dim),
+                    n if n == details.windows.len() => Span::styled(
+                        "on",
+                        app.theme.fg(Color::Magenta).add_modifier(Modifier::BOLD),
+                    ),
+                    n => Span::styled(
+                        format!("on in {} of {} windows", n, details.windows.len()),
+                        app.theme.fg(Color::Magenta),
+                    ),
+                },
+            ]));
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                format!("Windows ({})", details.windows.len()),
+                app.theme.fg(Color::Yellow),
+            )));
+            lines.extend(details.windows.iter().map(|window| {
+                let plural = if window.panes == 1 { "" } else { "s" };
+                let mut spans = vec![
+                    Span::raw(format!("  {}: {}", window.index, window.name)),
+                    Span::styled(format!("  {} pane{}", window.panes, plural), dim),
+                ];
+                if window.active {
+                    spans.push(Span::styled("  *", app.theme.fg(Color::Green)));
+                }
+                if window.synchronized {
+                    spans.push(Span::styled("  ⇉ sync", app.theme.fg(Color::Magenta)));
+                }
+                Line::from(spans)
+            }));
+        }
+        Some((target, Err(e))) if is_shown(target) => {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                e.to_string(),
+                app.theme.fg(Color::Red),
+            )));
+        }
+        _ => lines.push(Line::from(Span::styled("Loading…", dim))),
+    }
+
+    // Keep the last line reachable but stop scrolling past it
+    let max_scroll = u16::try_from(lines.len().saturating_sub(1)).unwrap_or(u16::MAX);
+    let scroll = app.detail_scroll.get().min(max_scroll);
+    app.detail_scroll.set(scroll);
+
+    let details = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0));
+    frame.render_widget(details, area);
+}
+
+/// The live capture of the selected session's active pane, cut to its last
+/// lines so the newest output stays in view
+fn render_tail(frame: &mut Frame, area: Rect, app: &App) {
+    let dim = app.theme.fg(Color::DarkGray);
+    let title = match &app.tail {
+        Some(((_, name), _)) => format!(" Live: {} ", app.display_name(name)),
+        None => " Live ".to_string(),
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(app.theme.fg(Color::Green));
+
+    let lines = match &app.tail {
+        Some((_, Ok(text))) => {
+            // The blank rows below the prompt would push the output out of view
+            let text = text.trim_end();
+            let height = usize::from(area.height.saturating_sub(2));
+            let all: Vec<&str> = text.lines().collect();
+            all[all.len().saturating_sub(height)..]
+                .iter()
+                .map(|line| Line::from(*line))
+                .collect()
+        }
+        Some((_, Err(e))) => vec![Line::from(Span::styled(
+            e.to_string(),
+            app.theme.fg(Color::Red),
+        ))],
+        None => vec![Line::from(Span::styled(
+            "Select a session to watch its active pane",
+            dim,
+        ))],
+    };
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Age of a Unix timestamp reported by tmux, e.g. "3h ago"
+fn format_timestamp_age(timestamp: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    format_age(Duration::from_secs(now.saturating_sub(timestamp)))
+}
+
+/// Label and highlight color of a row action's button
+fn action_button(action: SessionAction) -> (&'static str, Color) {
+    match action {
+        SessionAction::Enter => ("[Enter]", Color::Cyan),
+        SessionAction::Rename => ("[Rename]", Color::Yellow),
+        SessionAction::Delete => ("[Delete]", Color::Red),
+    }
+}
+
+/// Gray for a few windows, yellow from `busy_windows`, red from `heavy_windows`
+fn window_count_color(windows: u32, app: &App) -> Color {
+    if windows >= app.config.heavy_windows {
+        Color::Red
+    } else if windows >= app.config.busy_windows {
+        Color::Yellow
+    } else {
+        Color::DarkGray
+    }
+}
+
+/// Friendly hint centered below the create row when there are no sessions yet,
+/// or while they're still being read
+fn render_empty_state(frame: &mut Frame, area: Rect, app: &App) {
+    let inner = Block::default().borders(Borders::ALL).inner(area);
+    // Leave the create row (and its input row) above the message
+    let rows_used = (app.total_items() + app.vanished.len()) as u16;
+    let free = Rect {
+        y: inner.y + rows_used,
+        height: inner.height.saturating_sub(rows_used),
+        ..inner
+    };
+    if free.height == 0 {
+        return;
+    }
+
+    let message_area = Layout::vertical([
+        Constraint::Fill(1),
+        Constraint::Length(2),
+        Constraint::Fill(1),
+    ])
+    .split(free)[1];
+
+    let lines = match &app.list_error {
+        _ if app.is_loading() => vec![Line::from(Span::styled(
+            "Loading sessions…",
+            app.theme.fg(Color::DarkGray),
+        ))],
+        None => vec![
+            Line::from("No tmux sessions yet"),
+            Line::from("Press Enter on \"Create new session\" to start one"),
+        ],
+        Some(TmuxError::NoServer) => vec![
+            Line::from("No tmux server running"),
+            Line::from("It starts when you create your first session"),
+        ],
+        Some(TmuxError::Unreachable { host, message }) => vec![
+            Line::from(format!("Can't reach {}: {}", host, message)),
+            Line::from("Check that `ssh` to it works without a password prompt, then press r"),
+        ],
+        Some(e) => vec![
+            Line::from(format!("Couldn't list sessions: {}", e)),
+            Line::from("Press r to try again"),
+        ],
+    };
+    let message = Paragraph::new(lines)
+        .style(app.theme.fg(Color::DarkGray))
+        .alignment(Alignment::Center);
+    frame.render_widget(message, message_area);
+}
+
+/// Prompt text with the cursor drawn as `_` at the end or as a reversed
+/// character when it sits inside the text
+fn input_spans(input: &TextInput, style: Style) -> Vec<Span<'_>> {
+    let (before, after) = input.split_at_cursor();
+    let mut rest = after.chars();
+    match rest.next() {
+        None => vec![Span::styled(before, style), Span::styled("_", style)],
+        Some(c) => vec![
+            Span::styled(before, style),
+            Span::styled(c.to_string(), style.add_modifier(Modifier::REVERSED)),
+            Span::styled(rest.as_str(), style),
+        ],
+    }
+}
+
+/// ✓ when the typed name would be accepted, ⚠ when it would after a second
+/// Enter, else ✗ with the reason
+fn validation_span(app: &App) -> Option<Span<'static>> {
+    match app.validate_input()? {
+        Ok(None) => Some(Span::styled(" ✓", app.theme.fg(Color::Green))),
+        Ok(Some(warning)) => Some(Span::styled(
+            format!(" ⚠ {}", warning),
+            app.theme.fg(Color::Yellow),
+        )),
+        Err(e) => Some(Span::styled(format!(" ✗ {}", e), app.theme.fg(Color::Red))),
+    }
+}
+
+/// The layout and environment entries the session being created will get
+fn push_create_options(spans: &mut Vec<Span>, app: &App) {
+    let dim = app.theme.fg(Color::DarkGray);
+    if let Some(layout) = app.config.layouts.get(app.layout_index) {
+        spans.push(Span::styled(format!("  [{}]", layout.name), dim));
+    }
+    if !app.create_env.is_empty() {
+        spans.push(Span::styled(format!("  {}", app.create_env.join(" ")), dim));
+    }
+}
+
+/// Colored bullet for a session's tag; without colors the tag's initial is shown
+fn tag_span(tag: Option<Tag>, app: &App) -> Span<'static> {
+    match tag {
+        None => Span::raw("  "),
+        Some(tag) if app.theme.has_color() => Span::styled("● ", app.theme.fg(tag_color(tag))),
+        Some(tag) => Span::raw(format!("{} ", tag.name()[..1].to_uppercase())),
+    }
+}
+
+fn tag_color(tag: Tag) -> Color {
+    match tag {
+        Tag::Red => Color::Red,
+        Tag::Yellow => Color::Yellow,
+        Tag::Green => Color::Green,
+        Tag::Blue => Color::Blue,
+        Tag::Magenta => Color::Magenta,
+        Tag::Cyan => Color::Cyan,
+    }
+}
+
+/// Put the name spans at `index` in `line`, with whatever width the other
+/// spans leave in `row_width`
+fn insert_name<'a>(
+    line: &mut Vec<Span<'a>>,
+    index: usize,
+    name: &'a str,
+    row_width: usize,
+    app: &'a App,
+) {
+    let used: usize = line.iter().map(Span::width).sum();
+    let budget = row_width.saturating_sub(used).max(MIN_NAME_WIDTH);
+    line.splice(index..index, name_spans(name, budget, app));
+}
+
+/// The start of the session's note in whatever room is left on the line;
+/// left out when too little is
+fn push_note_hint(line: &mut Vec<Span>, name: &str, row_width: usize, app: &App) {
+    let Some(note) = app.notes.get(name) else {
+        return;
+    };
+    let used: usize = line.iter().map(Span::width).sum();
+    // "  ✎ " plus a few chars of the note
+    let room = row_width.saturating_sub(used + 4);
+    if room < 4 {
+        return;
+    }
+    let hint = if note.chars().count() > room {
+        let kept: String = note.chars().take(room - 1).collect();
+        format!("{}…", kept)
+    } else {
+        note.clone()
+    };
+    line.push(Span::styled(
+        format!("  ✎ {}", hint),
+        app.theme.fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+    ));
+}
+
+/// The session's display name, fitted to `max_width` chars, with search
+/// matches highlighted against the full name. Sessions that just appeared on
+/// auto-refresh flash green.
+fn name_spans<'a>(session_name: &'a str, max_width: usize, app: &'a App) -> Vec<Span<'a>> {
+    let base_style = if app.appeared.contains_key(session_name) {
+        app.theme.fg(Color::Green).add_modifier(Modifier::BOLD)
+    } else if app.is_long_idle(session_name) || app.is_shown_hidden(session_name) {
+        // Muted, as a hint that it may be time to clean it up or that it's
+        // normally out of sight
+        app.theme.fg(Color::DarkGray).add_modifier(Modifier::DIM)
+    } else {
+        Style::default()
+    };
+    let name = app.display_name(session_name);
+    let len = name.chars().count();
+    let truncation = middle_truncation(len, max_width);
+    let positions = (!app.search_query.is_empty())
+        .then(|| match_positions(name, app.search_query.as_str()))
+        .flatten();
+    if truncation.is_none() && positions.is_none() {
+        return vec![Span::styled(name, base_style)];
+    }
+
+    let positions = positions.unwrap_or_default();
+    let (head, tail) = truncation.unwrap_or((len, 0));
+    let match_style = app
+        .theme
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    let mut spans = Vec::new();
+    for (i, c) in name.chars().enumerate() {
+        if truncation.is_some() && i == head {
+            spans.push(Span::styled("…", app.theme.fg(Color::DarkGray)));
+        }
+        if i >= head && i < len - tail {
+            continue;
+        }
+        let style = if positions.contains(&i) {
+            match_style
+        } else {
+            base_style
+        };
+        spans.push(Span::styled(c.to_string(), style));
+    }
+    spans
+}
+
+/// "/query 3/27 [filter]" segment shown while a search is active, with the
+/// cursor while it's being typed
+fn search_status(app: &App) -> Vec<Span<'_>> {
+    let style = match app.search_style {
+        SearchStyle::Filter => "filter",
+        SearchStyle::Jump => "jump",
+    };
+    let count = format!("{}/{}", app.matches.len(), app.sessions.len());
+
+    let mut spans = vec![Span::styled(" /", app.theme.fg(Color::Cyan))];
+    if app.state == AppState::Searching {
+        spans.extend(input_spans(&app.search_query, app.theme.fg(Color::Cyan)));
+    } else {
+        spans.push(Span::styled(
+            app.search_query.as_str(),
+            app.theme.fg(Color::Cyan),
+        ));
+    }
+    spans.extend([
+        Span::raw(" "),
+        Span::styled(
+            count,
+            app.theme.fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(format!(" [{}]  ", style), app.theme.fg(Color::DarkGray)),
+    ]);
+    spans
+}
+
+/// "● red" segment shown while the list is filtered by tag
+fn tag_filter_status(app: &App) -> Vec<Span<'static>> {
+    let Some(tag) = app.tag_filter else {
+        return Vec::new();
+    };
+    vec![
+        Span::raw(" "),
+        tag_span(Some(tag), app),
+        Span::styled(
+            tag.name(),
+            app.theme.fg(tag_color(tag)).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(" "),
+    ]
 }
 
 fn render_help_bar(frame: &mut Frame, area: Rect, app: &App) {
     let help_text = match app.state {
+        AppState::SessionList if app.focus_area == FocusArea::Detail => {
+            vec![
+                Span::styled(" ↑↓/jk ", app.theme.fg(Color::Yellow)),
+                Span::raw("Scroll  "),
+                Span::styled("% / \" ", app.theme.fg(Color::Yellow)),
+                Span::raw("Split pane  "),
+                Span::styled("S ", app.theme.fg(Color::Yellow)),
+                Span::raw("Sync panes  "),
+                Span::styled("0-9 ", app.theme.fg(Color::Yellow)),
+                Span::raw("Attach to window  "),
+                Span::styled("Tab/Esc ", app.theme.fg(Color::Yellow)),
+                Span::raw("Back to list  "),
+                Span::styled("q ", app.theme.fg(Color::Yellow)),
+                Span::raw("Quit"),
+            ]
+        }
+        AppState::SessionList if !app.search_query.is_empty() => {
+            let mut spans = search_status(app);
+            spans.extend([
+                Span::styled("n/N ", app.theme.fg(Color::Yellow)),
+                Span::raw("Next/Prev match  "),
+                Span::styled("Enter ", app.theme.fg(Color::Yellow)),
+                Span::raw("Confirm  "),
+                Span::styled("/ ", app.theme.fg(Color::Yellow)),
+                Span::raw("Edit  "),
+                Span::styled("Esc ", app.theme.fg(Color::Yellow)),
+                Span::raw("Clear"),
+            ]);
+            spans
+        }
+        AppState::SessionList if app.layout_mode == LayoutMode::Grid => {
+            vec![
+                Span::styled(" ↑↓←→/hjkl ", app.theme.fg(Color::Yellow)),
+                Span::raw("Move  "),
+                Span::styled("Enter ", app.theme.fg(Color::Yellow)),
+                Span::raw("Attach  "),
+                Span::styled("V ", app.theme.fg(Color::Yellow)),
+                Span::raw("List  "),
+                Span::styled("Tab ", app.theme.fg(Color::Yellow)),
+                Span::raw("Details  "),
+                Span::styled("/ ", app.theme.fg(Color::Yellow)),
+                Span::raw("Search  "),
+                Span::styled("q/Esc ", app.theme.fg(Color::Yellow)),
+                Span::raw("Quit"),
+            ]
+        }
         AppState::SessionList => {
             vec![
-                Span::styled(" ↑↓/jk ", Style::default().fg(Color::Yellow)),
+                Span::styled(" ↑↓/jk ", app.theme.fg(Color::Yellow)),
                 Span::raw("Navigate  "),
-                Span::styled("←→/hl ", Style::default().fg(Color::Yellow)),
+                Span::styled("←→/hl ", app.theme.fg(Color::Yellow)),
                 Span::raw("Action  "),
-                Span::styled("Enter ", Style::default().fg(Color::Yellow)),
+                Span::styled("Enter ", app.theme.fg(Color::Yellow)),
                 Span::raw("Confirm  "),
-                Span::styled("r ", Style::default().fg(Color::Yellow)),
+                Span::styled("r ", app.theme.fg(Color::Yellow)),
                 Span::raw("Refresh  "),
-                Span::styled("q/Esc ", Style::default().fg(Color::Yellow)),
+                Span::styled("Tab ", app.theme.fg(Color::Yellow)),
+                Span::raw("Details  "),
+                Span::styled("/ ", app.theme.fg(Color::Yellow)),
+                Span::raw("Search  "),
+                Span::styled("f ", app.theme.fg(Color::Yellow)),
+                Span::raw("Live  "),
+                Span::styled("C ", app.theme.fg(Color::Yellow)),
+                Span::raw("Clean up  "),
+                Span::styled("q/Esc ", app.theme.fg(Color::Yellow)),
                 Span::raw("Quit"),
             ]
         }
+        AppState::GoingTo => {
+            vec![
+                Span::styled("Enter ", app.theme.fg(Color::Yellow)),
+                Span::raw("Go to session  "),
+                Span::styled("Esc ", app.theme.fg(Color::Yellow)),
+                Span::raw("Cancel"),
+            ]
+        }
+        AppState::NumberingSessions => {
+            vec![
+                Span::styled("Enter ", app.theme.fg(Color::Yellow)),
+                Span::raw("Preview  "),
+                Span::styled("Esc ", app.theme.fg(Color::Yellow)),
+                Span::raw("Cancel"),
+            ]
+        }
         AppState::CreatingSession => {
             vec![
-                Span::styled("Enter ", Style::default().fg(Color::Yellow)),
+                Span::styled("Enter ", app.theme.fg(Color::Yellow)),
                 Span::raw("Create  "),
-                Span::styled("Esc ", Style::default().fg(Color::Yellow)),
+                Span::styled("Tab ", app.theme.fg(Color::Yellow)),
+                Span::raw("Complete  "),
+                Span::styled("^L ", app.theme.fg(Color::Yellow)),
+                Span::raw("Layout  "),
+                Span::styled("^V ", app.theme.fg(Color::Yellow)),
+                Span::raw("Env var  "),
+                Span::styled("Esc ", app.theme.fg(Color::Yellow)),
                 Span::raw("Cancel"),
             ]
         }
-        AppState::RenamingSession { .. } => {
+        AppState::AddingEnv { .. } => {
             vec![
-                Span::styled("Enter ", Style::default().fg(Color::Yellow)),
+                Span::styled("Enter ", app.theme.fg(Color::Yellow)),
+                Span::raw("Add KEY=VALUE  "),
+                Span::styled("Esc ", app.theme.fg(Color::Yellow)),
+                Span::raw("Back to name"),
+            ]
+        }
+        AppState::RenamingSession { .. } | AppState::AffixingName { .. } => {
+            vec![
+                Span::styled("Enter ", app.theme.fg(Color::Yellow)),
                 Span::raw("Rename  "),
-                Span::styled("Esc ", Style::default().fg(Color::Yellow)),
+                Span::styled("Esc ", app.theme.fg(Color::Yellow)),
+                Span::raw("Cancel"),
+            ]
+        }
+        AppState::CreatingWindow { .. } => {
+            vec![
+                Span::styled("Enter ", app.theme.fg(Color::Yellow)),
+                Span::raw("Add window  "),
+                Span::styled("Esc ", app.theme.fg(Color::Yellow)),
+                Span::raw("Cancel"),
+            ]
+        }
+        AppState::EditingNote { .. } => {
+            vec![
+                Span::styled("Enter ", app.theme.fg(Color::Yellow)),
+                Span::raw("Save note  "),
+                Span::styled("Esc ", app.theme.fg(Color::Yellow)),
+                Span::raw("Cancel"),
+            ]
+        }
+        AppState::SavingScrollback { .. } => {
+            vec![
+                Span::styled("Enter ", app.theme.fg(Color::Yellow)),
+                Span::raw("Save  "),
+                Span::styled("Esc ", app.theme.fg(Color::Yellow)),
+                Span::raw("Cancel"),
+            ]
+        }
+        AppState::Searching => {
+            let mut spans = search_status(app);
+            spans.extend([
+                Span::styled("Tab ", app.theme.fg(Color::Yellow)),
+                Span::raw("Filter/Jump  "),
+                Span::styled("Enter ", app.theme.fg(Color::Yellow)),
+                Span::raw("Done  "),
+                Span::styled("Esc ", app.theme.fg(Color::Yellow)),
+                Span::raw("Clear"),
+            ]);
+            spans
+        }
+        AppState::ConfirmingBatch { .. } => {
+            vec![
+                Span::styled("↑↓/jk ", app.theme.fg(Color::Yellow)),
+                Span::raw("Move  "),
+                Span::styled("Space ", app.theme.fg(Color::Yellow)),
+                Span::raw("Include/exclude  "),
+                Span::styled("a ", app.theme.fg(Color::Yellow)),
+                Span::raw("All/none  "),
+                Span::styled("Enter/y ", app.theme.fg(Color::Yellow)),
+                Span::raw("Go ahead  "),
+                Span::styled("Esc/n ", app.theme.fg(Color::Yellow)),
                 Span::raw("Cancel"),
             ]
         }
+        AppState::RunningBatch { .. } => {
+            vec![
+                Span::styled("Ctrl-C ", app.theme.fg(Color::Yellow)),
+                Span::raw("Quit"),
+            ]
+        }
+        AppState::ViewingLog => {
+            vec![
+                Span::styled("H/Esc ", app.theme.fg(Color::Yellow)),
+                Span::raw("Close"),
+            ]
+        }
+        AppState::Welcome => {
+            vec![
+                Span::styled("Any key ", app.theme.fg(Color::Yellow)),
+                Span::raw("Get started"),
+            ]
+        }
+        AppState::ConfirmingLocked { .. } => {
+            vec![
+                Span::styled("Enter ", app.theme.fg(Color::Yellow)),
+                Span::raw("Confirm  "),
+                Span::styled("Esc ", app.theme.fg(Color::Yellow)),
+                Span::raw("Cancel"),
+            ]
+        }
+        AppState::ConfirmingAttachedRename { .. } => {
+            vec![
+                Span::styled("Enter/y ", app.theme.fg(Color::Yellow)),
+                Span::raw("Rename  "),
+                Span::styled("Esc/n ", app.theme.fg(Color::Yellow)),
+                Span::raw("Cancel"),
+            ]
+        }
+        AppState::ConfirmingOwnDelete { .. } => {
+            vec![
+                Span::styled("Enter/y ", app.theme.fg(Color::Yellow)),
+                Span::raw("Delete anyway  "),
+                Span::styled("Esc/n ", app.theme.fg(Color::Yellow)),
+                Span::raw("Cancel"),
+            ]
+        }
+    };
+
+    // Letters are commands in vi mode's normal mode, so say it's on
+    let input = if app.state == AppState::Searching {
+        &app.search_query
+    } else {
+        &app.input
+    };
+    let help_text = if input.is_normal_mode() && app.state.takes_text() {
+        let mut spans = vec![Span::styled(
+            " NORMAL ",
+            app.theme.fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        )];
+        spans.extend(help_text);
+        spans
+    } else {
+        help_text
+    };
+
+    let help_text = if app.state == AppState::SessionList {
+        let mut spans = tag_filter_status(app);
+        if let Some((count, _)) = app.pending_count {
+            spans.push(Span::styled(
+                format!(" {} ", count),
+                app.theme.fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            ));
+        }
+        spans.extend(help_text);
+        spans
+    } else {
+        help_text
+    };
+
+    // A status message temporarily replaces the key hints
+    let help_text = match app.status_message {
+        Some(ref message) => vec![Span::styled(
+            format!(" {}", message),
+            app.theme.fg(Color::Green),
+        )],
+        None => {
+            // Hints too wide for the terminal take turns rather than being cut off
+            let mut pages = help_pages(help_text, usize::from(area.width));
+            app.help_pages.set(pages.len());
+            let count = pages.len();
+            let page = app.help_page % count;
+            let mut spans = pages.swap_remove(page);
+            if count > 1 {
+                spans.push(Span::styled(
+                    format!(" [{}/{}]", page + 1, count),
+                    app.theme.fg(Color::DarkGray),
+                ));
+            }
+            spans
+        }
     };
 
     let help = Paragraph::new(Line::from(help_text))
@@ -197,24 +1521,412 @@ fn render_help_bar(frame: &mut Frame, area: Rect, app: &App) {
     frame.render_widget(help, area);
 }
 
-fn render_error_popup(frame: &mut Frame, error: &str) {
-    let area = centered_rect(60, 15, frame.area());
+/// The help bar's spans split into pages that fit `width`, breaking only
+/// between hints (each hint ends in its two-space separator). Anything in
+/// front of the first hint, like the search status, stays with it.
+fn help_pages(spans: Vec<Span>, width: usize) -> Vec<Vec<Span>> {
+    let total: usize = spans.iter().map(Span::width).sum();
+    if total <= width {
+        return vec![spans];
+    }
+
+    let mut hints = Vec::new();
+    let mut hint = Vec::new();
+    for span in spans {
+        let ends_hint = span.content.ends_with("  ");
+        hint.push(span);
+        if ends_hint {
+            hints.push(std::mem::take(&mut hint));
+        }
+    }
+    if !hint.is_empty() {
+        hints.push(hint);
+    }
+
+    let room = width.saturating_sub(HELP_MARKER_WIDTH);
+    let mut pages = Vec::new();
+    let mut page: Vec<Span> = Vec::new();
+    let mut used = 0;
+    for hint in hints {
+        let hint_width: usize = hint.iter().map(Span::width).sum();
+        // A hint wider than a whole page still gets a page of its own
+        if !page.is_empty() && used + hint_width > room {
+            pages.push(std::mem::take(&mut page));
+            used = 0;
+        }
+        used += hint_width;
+        page.extend(hint);
+    }
+    pages.push(page);
+    pages
+}
+
+fn render_error_popup(frame: &mut Frame, error: &str, app: &App) {
+    // Grow the popup to fit multi-line tmux errors once wrapped
+    let width = frame.area().width * 60 / 100;
+    let inner_width = usize::from(width.saturating_sub(2)).max(1);
+    let wrapped_lines: usize = error
+        .lines()
+        .map(|line| line.chars().count().div_ceil(inner_width).max(1))
+        .sum();
+    let height = u16::try_from(wrapped_lines + 2).unwrap_or(u16::MAX);
+    let area = centered_rect_with_height(60, height, frame.area());
 
     frame.render_widget(Clear, area);
 
     let error_block = Block::default()
         .title(" Error ")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Red));
+        .border_style(app.theme.fg(Color::Red));
 
     let inner = error_block.inner(area);
     frame.render_widget(error_block, area);
 
     let error_text = Paragraph::new(error)
-        .style(Style::default().fg(Color::Red));
+        .style(app.theme.fg(Color::Red))
+        .wrap(Wrap { trim: false });
     frame.render_widget(error_text, inner);
 }
 
+/// Toast in the bottom-right corner of `area`
+fn render_toast(frame: &mut Frame, area: Rect, message: &str, app: &App) {
+    let width = (message.chars().count() as u16 + 4).min(area.width);
+    let height = 3.min(area.height);
+    let toast_area = Rect {
+        x: area.right().saturating_sub(width + 1),
+        y: area.bottom().saturating_sub(height + 1),
+        width,
+        height,
+    };
+
+    frame.render_widget(Clear, toast_area);
+    let toast = Paragraph::new(format!(" {}", message))
+        .style(app.theme.fg(Color::Cyan))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(app.theme.fg(Color::Cyan)),
+        );
+    frame.render_widget(toast, toast_area);
+}
+
+/// Every session a bulk operation would touch, each with a checkbox; the
+/// list scrolls to keep the cursor in view
+fn render_batch_popup(
+    frame: &mut Frame,
+    operation: &BatchOperation,
+    items: &[BatchItem],
+    cursor: usize,
+    app: &App,
+) {
+    let area = centered_rect(50, 50, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let included = items.iter().filter(|item| item.included).count();
+    let (title, color, description) = match operation {
+        BatchOperation::Kill(kind) => {
+            let description = match kind {
+                CleanupKind::Empty => "These sessions only have an idle shell:".to_string(),
+                CleanupKind::Detached => {
+                    "No client is attached to these sessions:".to_string()
+                }
+                CleanupKind::Zombie => {
+                    "These sessions were started in directories that no longer exist:"
+                        .to_string()
+                }
+                CleanupKind::Idle => format!(
+                    "Nothing has happened in these sessions for over {}h:",
+                    app.config.idle_hours
+                ),
+            };
+            let title = format!(
+                " Kill {} of {} {} sessions? ",
+                included,
+                items.len(),
+                kind.label()
+            );
+            (title, Color::Red, description)
+        }
+        BatchOperation::Renumber { .. } => (
+            format!(" Rename {} of {} sessions? ", included, items.len()),
+            Color::Yellow,
+            "Numbered in list order; left-out sessions keep their names:".to_string(),
+        ),
+    };
+    // The new names only count the sessions still included
+    let new_names: Vec<Option<String>> = match operation {
+        BatchOperation::Kill(_) => vec![None; items.len()],
+        BatchOperation::Renumber { prefix } => {
+            let mut number = 0;
+            items
+                .iter()
+                .map(|item| {
+                    item.included.then(|| {
+                        number += 1;
+                        format!("{}-{}", prefix, number)
+                    })
+                })
+                .collect()
+        }
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(app.theme.fg(color));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let rows = Layout::vertical([Constraint::Length(1), Constraint::Min(1)]).split(inner);
+    frame.render_widget(
+        Paragraph::new(Span::styled(description, app.theme.fg(Color::DarkGray))),
+        rows[0],
+    );
+
+    let lines: Vec<ListItem> = items
+        .iter()
+        .zip(new_names)
+        .map(|(item, new_name)| {
+            let (mark, style) = if item.included {
+                ("[x] ", Style::default())
+            } else {
+                ("[ ] ", app.theme.fg(Color::DarkGray))
+            };
+            let mut spans = vec![
+                Span::styled(format!("  {}", mark), style),
+                Span::styled(item.name.as_str(), style),
+            ];
+            if let Some(new_name) = new_name {
+                spans.push(Span::styled(
+                    format!(" → {}", new_name),
+                    app.theme.fg(Color::Cyan),
+                ));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let list = List::new(lines).highlight_style(app.theme.highlight());
+    let mut state = ListState::default();
+    state.select(Some(cursor));
+    frame.render_stateful_widget(list, rows[1], &mut state);
+}
+
+/// How far a running batch has got, as a bar and a count of sessions
+fn render_batch_progress_popup(frame: &mut Frame, progress: &BatchProgress, app: &App) {
+    let area = centered_rect_with_height(50, 4, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let (title, verb, color) = match &progress.operation {
+        BatchOperation::Kill(kind) => (
+            format!(" Killing {} sessions ", kind.label()),
+            "killed",
+            Color::Red,
+        ),
+        BatchOperation::Renumber { .. } => {
+            (" Renaming sessions ".to_string(), "renamed", Color::Yellow)
+        }
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(app.theme.fg(color));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let rows = Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).split(inner);
+    let ratio = if progress.total == 0 {
+        1.0
+    } else {
+        progress.done as f64 / progress.total as f64
+    };
+    let gauge = Gauge::default()
+        .gauge_style(app.theme.fg(color))
+        .ratio(ratio)
+        .label(format!("{}/{} {}", progress.done, progress.total, verb));
+    frame.render_widget(gauge, rows[0]);
+
+    // Failures are listed once it's done; until then only counted
+    if !progress.failures.is_empty() {
+        frame.render_widget(
+            Paragraph::new(Span::styled(
+                format!("{} failed so far", progress.failures.len()),
+                app.theme.fg(Color::DarkGray),
+            )),
+            rows[1],
+        );
+    }
+}
+
+/// Typed-name confirmation before renaming or deleting a locked session
+fn render_locked_popup(frame: &mut Frame, name: &str, action: SessionAction, app: &App) {
+    let area = centered_rect_with_height(50, 5, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let verb = match action {
+        SessionAction::Rename => "rename",
+        _ => "delete",
+    };
+    let block = Block::default()
+        .title(" Locked session ")
+        .borders(Borders::ALL)
+        .border_style(app.theme.fg(Color::Yellow));
+
+    let mut input = vec![Span::raw("> ")];
+    input.extend(input_spans(&app.input, app.theme.fg(Color::Yellow)));
+    let lines = vec![
+        Line::from(format!(
+            "'{}' is locked. Type its name to {} it:",
+            name, verb
+        )),
+        Line::from(""),
+        Line::from(input),
+    ];
+
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Confirmation before renaming a session a client is attached to
+fn render_attached_rename_popup(frame: &mut Frame, name: &str, app: &App) {
+    let area = centered_rect_with_height(50, 5, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Rename attached session? ")
+        .borders(Borders::ALL)
+        .border_style(app.theme.fg(Color::Yellow));
+
+    let lines = vec![
+        Line::from(format!("'{}' is attached elsewhere.", name)),
+        Line::from(Span::styled(
+            "Its clients will see the new name right away.",
+            app.theme.fg(Color::DarkGray),
+        )),
+    ];
+
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Confirmation before deleting the session Ursa is running in
+fn render_own_delete_popup(frame: &mut Frame, name: &str, app: &App) {
+    let area = centered_rect_with_height(50, 5, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Delete your own session? ")
+        .borders(Borders::ALL)
+        .border_style(app.theme.fg(Color::Red));
+
+    let lines = vec![
+        Line::from(format!("Ursa is running inside '{}'.", name)),
+        Line::from(Span::styled(
+            "Deleting it closes this window, and Ursa with it.",
+            app.theme.fg(Color::DarkGray),
+        )),
+    ];
+
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Operations performed this run, newest first
+fn render_log_popup(frame: &mut Frame, app: &App) {
+    let area = centered_rect(60, 60, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Action log ")
+        .borders(Borders::ALL)
+        .border_style(app.theme.fg(Color::Cyan));
+
+    let lines: Vec<Line> = if app.action_log.is_empty() {
+        vec![Line::from(Span::styled(
+            "Nothing done yet",
+            app.theme.fg(Color::DarkGray),
+        ))]
+    } else {
+        app.action_log
+            .iter()
+            .rev()
+            .map(|entry| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:>8}  ", format_age(entry.at.elapsed())),
+                        app.theme.fg(Color::DarkGray),
+                    ),
+                    Span::raw(entry.message.as_str()),
+                ])
+            })
+            .collect()
+    };
+
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// One-time overview of the core keys, shown on the first run
+fn render_welcome_popup(frame: &mut Frame, app: &App) {
+    let keys: [(&str, &str); 8] = [
+        ("↑↓ / jk", "Move between sessions"),
+        ("←→ / hl", "Pick Enter, Rename or Delete"),
+        ("Enter", "Run the picked action"),
+        ("/", "Search"),
+        ("Tab", "Session details"),
+        ("g", "Go to a session by name"),
+        ("w", "Add a window"),
+        ("q", "Quit"),
+    ];
+    let area = centered_rect_with_height(60, keys.len() as u16 + 7, frame.area());
+
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Welcome to Ursa ")
+        .borders(Borders::ALL)
+        .border_style(app.theme.fg(Color::Cyan));
+
+    let mut lines = vec![
+        Line::from("Manage tmux sessions without typing tmux commands."),
+        Line::from(""),
+    ];
+    lines.extend(keys.iter().map(|(key, action)| {
+        Line::from(vec![
+            Span::styled(format!("  {:<10}", key), app.theme.fg(Color::Yellow)),
+            Span::raw(*action),
+        ])
+    }));
+    lines.push(Line::from(""));
+    let config = config_path().map_or_else(
+        || "config.toml in your config directory".to_string(),
+        |path| path.display().to_string(),
+    );
+    lines.push(Line::from(Span::styled(
+        format!("Preferences live in {}", config),
+        app.theme.fg(Color::DarkGray),
+    )));
+
+    let popup = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(block);
+    frame.render_widget(popup, area);
+}
+
+/// "12s ago", "3m ago", "2h ago", "4d ago"
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    if secs < 60 {
+        format!("{}s ago", secs)
+    } else if secs < 3600 {
+        format!("{}m ago", secs / 60)
+    } else if secs < 86400 {
+        format!("{}h ago", secs / 3600)
+    } else {
+        format!("{}d ago", secs / 86400)
+    }
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::vertical([
         Constraint::Percentage((100 - percent_y) / 2),
@@ -230,3 +1942,21 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     ])
     .split(popup_layout[1])[1]
 }
+
+/// Horizontally centered rect of a fixed height (clamped to the available area)
+fn centered_rect_with_height(percent_x: u16, height: u16, r: Rect) -> Rect {
+    let height = height.min(r.height);
+    let popup_layout = Layout::vertical([
+        Constraint::Fill(1),
+        Constraint::Length(height),
+        Constraint::Fill(1),
+    ])
+    .split(r);
+
+    Layout::horizontal([
+        Constraint::Percentage((100 - percent_x) / 2),
+        Constraint::Percentage(percent_x),
+        Constraint::Percentage((100 - percent_x) / 2),
+    ])
+    .split(popup_layout[1])[1]
+}
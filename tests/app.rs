@@ -0,0 +1,1940 @@
+//! Headless tests for the `App` state machine, driven by synthetic key events.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::style::Color;
+
+use ursa::app::fuzzy;
+use ursa::app::{
+    branch_session_name, dedupe_name, display_names, renumber_plan, resolve_session_prefix,
+    truncate_middle, App, AppAction, AppState, BatchItem, BatchOperation, CleanupKind, FocusArea,
+    HookEvent, LayoutMode, SearchStyle, SessionAction,
+};
+use ursa::backend::{FakeTmux, TmuxBackend};
+use ursa::config::{ensure_config_file, Config};
+use ursa::input::{History, InputMode};
+use ursa::theme::{ColorScheme, Theme};
+use ursa::tmux::TmuxSession;
+
+fn session(name: &str) -> TmuxSession {
+    TmuxSession {
+        name: name.to_string(),
+        windows: 1,
+        attached: false,
+        last_activity: 0,
+        group: None,
+        path: String::new(),
+        socket: None,
+    }
+}
+
+fn app_with(names: &[&str]) -> App {
+    App::with_sessions(names.iter().map(|name| session(name)).collect())
+}
+
+/// Attaching to `name` on tmux's default server, where test sessions are
+fn attach(name: &str) -> AppAction {
+    AppAction::AttachSession {
+        name: name.to_string(),
+        socket: None,
+    }
+}
+
+/// Point the config dir at a throwaway directory shared by this run's tests,
+/// so saving tags, locks or config never touches the real one
+fn test_config_dir() -> PathBuf {
+    static DIR: OnceLock<PathBuf> = OnceLock::new();
+    DIR.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("ursa-test-config-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("ursa")).unwrap();
+        std::env::set_var("XDG_CONFIG_HOME", &dir);
+        dir.join("ursa")
+    })
+    .clone()
+}
+
+fn press(app: &mut App, code: KeyCode) {
+    app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+}
+
+fn ctrl(app: &mut App, c: char) {
+    app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL));
+}
+
+fn type_str(app: &mut App, text: &str) {
+    for c in text.chars() {
+        press(app, KeyCode::Char(c));
+    }
+}
+
+/// Let a confirmed batch run to the end, a step per tick as in the event loop
+fn finish_batch(app: &mut App) {
+    while matches!(app.state, AppState::RunningBatch { .. }) {
+        app.tick();
+    }
+}
+
+#[test]
+fn down_stops_on_create_row() {
+    let mut app = app_with(&["alpha", "beta"]);
+
+    for _ in 0..5 {
+        press(&mut app, KeyCode::Down);
+    }
+    assert_eq!(app.selected_index, 2);
+    assert!(app.selected_session().is_none());
+}
+
+#[test]
+fn up_from_top_focuses_title_bar_and_down_returns() {
+    let mut app = app_with(&["alpha", "beta"]);
+
+    press(&mut app, KeyCode::Up);
+    assert_eq!(app.focus_area, FocusArea::TitleBar);
+    assert_eq!(app.selected_index, 0);
+
+    press(&mut app, KeyCode::Up);
+    assert_eq!(app.focus_area, FocusArea::TitleBar);
+
+    press(&mut app, KeyCode::Down);
+    assert_eq!(app.focus_area, FocusArea::SessionList);
+    assert_eq!(app.selected_index, 0);
+}
+
+#[test]
+fn vim_keys_move_like_arrows() {
+    let mut app = app_with(&["alpha", "beta", "gamma"]);
+
+    press(&mut app, KeyCode::Char('j'));
+    press(&mut app, KeyCode::Char('j'));
+    press(&mut app, KeyCode::Char('k'));
+    assert_eq!(app.selected_session().unwrap().name, "beta");
+}
+
+#[test]
+fn page_keys_move_by_a_page() {
+    let names: Vec<String> = (1..=10).map(|i| format!("s{i}")).collect();
+    let mut app = app_with(&names.iter().map(String::as_str).collect::<Vec<_>>());
+    // As the renderer would write back for a list four rows tall
+    app.page_size.set(4);
+    assert_eq!(app.page_count(), 3);
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::PageDown);
+    assert_eq!(app.selected_session().unwrap().name, "s6");
+    assert_eq!(app.page(), 1);
+
+    // A count jumps several pages, stopping at the "Create new" row
+    type_str(&mut app, "5");
+    press(&mut app, KeyCode::PageDown);
+    assert_eq!(app.selected_index, 10);
+    assert_eq!(app.page(), 2);
+
+    press(&mut app, KeyCode::PageUp);
+    assert_eq!(app.selected_session().unwrap().name, "s7");
+    type_str(&mut app, "3");
+    press(&mut app, KeyCode::PageUp);
+    assert_eq!(app.selected_index, 0);
+}
+
+#[test]
+fn action_cycling_stops_at_edges() {
+    let mut app = app_with(&["alpha"]);
+
+    press(&mut app, KeyCode::Left);
+    assert_eq!(app.selected_action, SessionAction::Enter);
+
+    press(&mut app, KeyCode::Right);
+    assert_eq!(app.selected_action, SessionAction::Rename);
+    press(&mut app, KeyCode::Right);
+    assert_eq!(app.selected_action, SessionAction::Delete);
+    press(&mut app, KeyCode::Right);
+    assert_eq!(app.selected_action, SessionAction::Delete);
+
+    press(&mut app, KeyCode::Left);
+    assert_eq!(app.selected_action, SessionAction::Rename);
+}
+
+#[test]
+fn action_cycling_wraps_when_configured() {
+    let mut app = app_with(&["alpha"]);
+    app.config.wrap_actions = true;
+
+    press(&mut app, KeyCode::Left);
+    assert_eq!(app.selected_action, SessionAction::Delete);
+    press(&mut app, KeyCode::Right);
+    assert_eq!(app.selected_action, SessionAction::Enter);
+}
+
+#[test]
+fn moving_resets_the_action() {
+    let mut app = app_with(&["alpha", "beta"]);
+
+    press(&mut app, KeyCode::Right);
+    press(&mut app, KeyCode::Down);
+    assert_eq!(app.selected_action, SessionAction::Enter);
+}
+
+#[test]
+fn actions_do_not_cycle_on_create_row() {
+    let mut app = app_with(&[]);
+
+    press(&mut app, KeyCode::Right);
+    assert_eq!(app.selected_action, SessionAction::Enter);
+}
+
+#[test]
+fn create_prompt_opens_and_cancels() {
+    let mut app = app_with(&["alpha"]);
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.state, AppState::CreatingSession);
+
+    type_str(&mut app, "new one!");
+    // Spaces and punctuation aren't valid in session names
+    assert_eq!(app.input.as_str(), "newone");
+
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.state, AppState::SessionList);
+    assert!(app.input.is_empty());
+    assert!(!app.should_quit);
+}
+
+#[test]
+fn q_is_text_in_prompts_and_esc_only_cancels_them() {
+    let mut app = app_with(&["alpha"]);
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    type_str(&mut app, "quiq");
+    assert_eq!(app.input.as_str(), "quiq");
+    press(&mut app, KeyCode::Esc);
+    assert!(!app.should_quit);
+
+    press(&mut app, KeyCode::Up);
+    app.selected_action = SessionAction::Rename;
+    press(&mut app, KeyCode::Enter);
+    type_str(&mut app, "q");
+    assert_eq!(app.input.as_str(), "alphaq");
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.state, AppState::SessionList);
+    assert!(!app.should_quit);
+}
+
+#[test]
+fn ctrl_c_quits_from_every_state() {
+    let name = || "alpha".to_string();
+    let states = [
+        AppState::SessionList,
+        AppState::CreatingSession,
+        AppState::AddingEnv {
+            session_name: name(),
+        },
+        AppState::GoingTo,
+        AppState::NumberingSessions,
+        AppState::RenamingSession {
+            original_name: name(),
+            socket: None,
+        },
+        AppState::AffixingName {
+            original_name: name(),
+            socket: None,
+            prepend: true,
+        },
+        AppState::CreatingWindow {
+            session_name: name(),
+            socket: None,
+        },
+        AppState::SavingScrollback {
+            session_name: name(),
+            socket: None,
+        },
+        AppState::EditingNote {
+            session_name: name(),
+        },
+        AppState::Searching,
+        AppState::ConfirmingBatch {
+            operation: BatchOperation::Kill(CleanupKind::Detached),
+            items: vec![BatchItem {
+                name: name(),
+                socket: None,
+                included: true,
+            }],
+            cursor: 0,
+        },
+        AppState::ViewingLog,
+        AppState::Welcome,
+        AppState::ConfirmingLocked {
+            name: name(),
+            socket: None,
+            action: SessionAction::Delete,
+        },
+        AppState::ConfirmingAttachedRename {
+            name: name(),
+            socket: None,
+        },
+        AppState::ConfirmingOwnDelete { name: name() },
+        // A kill can stop between sessions; a renumber can't (see
+        // quitting_mid_renumber_waits_for_it_to_finish)
+        {
+            let mut app = app_with(&["alpha"]);
+            press(&mut app, KeyCode::Char('K'));
+            press(&mut app, KeyCode::Enter);
+            app.state
+        },
+    ];
+    for state in states {
+        let mut app = app_with(&["alpha"]);
+        app.state = state.clone();
+        // Half-typed text doesn't hold it up
+        type_str(&mut app, "ab");
+        app.state = state.clone();
+        ctrl(&mut app, 'c');
+        assert!(app.should_quit, "Ctrl-C didn't quit from {:?}", state);
+    }
+
+    // Nor does a pending attach, which other keys only cancel
+    let mut app = app_with(&["alpha"]);
+    press(&mut app, KeyCode::Char('L'));
+    ctrl(&mut app, 'c');
+    assert!(app.should_quit);
+}
+
+#[test]
+fn start_creating_prefills_the_prompt() {
+    let mut app = app_with(&["alpha", "beta"]);
+
+    app.start_creating(Some("beta"));
+    assert_eq!(app.state, AppState::CreatingSession);
+    assert_eq!(app.selected_index, 2);
+    assert_eq!(app.input.as_str(), "beta");
+
+    // A taken name keeps the prompt open instead of calling tmux
+    app.confirm_create();
+    assert_eq!(app.state, AppState::CreatingSession);
+    assert_eq!(app.action, AppAction::None);
+    assert!(app.status_message.is_some());
+}
+
+#[test]
+fn rename_prompt_starts_with_current_name() {
+    let mut app = app_with(&["alpha"]);
+
+    press(&mut app, KeyCode::Right);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(
+        app.state,
+        AppState::RenamingSession {
+            original_name: "alpha".to_string(),
+            socket: None,
+        }
+    );
+    assert_eq!(app.input.as_str(), "alpha");
+
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.state, AppState::SessionList);
+    assert_eq!(app.selected_action, SessionAction::Enter);
+}
+
+#[test]
+fn renaming_a_session_closed_elsewhere_says_so() {
+    // A socket with no server behind it, as if the session's server exited
+    let mut ghost = session("ghost");
+    ghost.socket = Some(format!("ursa-test-gone-{}", std::process::id()));
+    let mut app = App::with_sessions(vec![ghost]);
+
+    press(&mut app, KeyCode::Right);
+    press(&mut app, KeyCode::Enter);
+    type_str(&mut app, "-renamed");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.state, AppState::SessionList);
+    assert_eq!(
+        app.toast.as_ref().map(|toast| toast.message.as_str()),
+        Some("'ghost' was closed elsewhere; nothing was changed")
+    );
+    assert!(app.error_message.is_none());
+}
+
+#[test]
+fn create_rename_and_delete_against_a_fake_server() {
+    let tmux = FakeTmux::with_sessions(&["alpha", "beta"]);
+    let mut app = App::with_backend(Box::new(tmux.clone()));
+    assert_eq!(app.sessions.len(), 2);
+
+    // Create from the "Create new" row; Enter then attaches
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    type_str(&mut app, "gamma");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(tmux.session_names(), ["alpha", "beta", "gamma"]);
+    assert_eq!(app.action, attach("gamma"));
+    app.action = AppAction::None;
+    press(&mut app, KeyCode::Esc);
+
+    // Rename the first session
+    app.selected_index = 0;
+    press(&mut app, KeyCode::Right);
+    press(&mut app, KeyCode::Enter);
+    type_str(&mut app, "-2");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(tmux.session_names(), ["alpha-2", "beta", "gamma"]);
+
+    // A name that's taken is refused
+    app.selected_index = 1;
+    app.selected_action = SessionAction::Rename;
+    press(&mut app, KeyCode::Enter);
+    ctrl(&mut app, 'u');
+    type_str(&mut app, "gamma");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(tmux.session_names(), ["alpha-2", "beta", "gamma"]);
+
+    // Delete the middle one; the cursor moves to the session after it
+    press(&mut app, KeyCode::Esc);
+    app.selected_index = 1;
+    app.selected_action = SessionAction::Delete;
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(tmux.session_names(), ["alpha-2", "gamma"]);
+    assert_eq!(app.selected_session().unwrap().name, "gamma");
+}
+
+#[test]
+fn creating_can_stay_in_the_list_instead_of_attaching() {
+    let tmux = FakeTmux::with_sessions(&["alpha", "zeta"]);
+    let mut app = App::with_backend(Box::new(tmux.clone()));
+    app.config.attach_on_create = false;
+
+    for name in ["beta", "gamma"] {
+        app.selected_index = app.visible_sessions().len();
+        press(&mut app, KeyCode::Enter);
+        type_str(&mut app, name);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.action, AppAction::None);
+        assert_eq!(app.state, AppState::SessionList);
+        assert!(app.input.is_empty());
+        assert_eq!(app.selected_session().unwrap().name, name);
+    }
+    assert_eq!(tmux.session_names(), ["alpha", "zeta", "beta", "gamma"]);
+}
+
+#[test]
+fn creating_stops_at_max_sessions_until_some_are_killed() {
+    let tmux = FakeTmux::with_sessions(&["alpha", "zeta"]);
+    let mut app = App::with_backend(Box::new(tmux.clone()));
+    app.config.attach_on_create = false;
+    app.config.max_sessions = 3;
+    assert_eq!(app.session_limit_reached(), None);
+
+    app.selected_index = app.visible_sessions().len();
+    press(&mut app, KeyCode::Enter);
+    type_str(&mut app, "beta");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.session_limit_reached(), Some(3));
+
+    app.selected_index = app.visible_sessions().len();
+    press(&mut app, KeyCode::Enter);
+    type_str(&mut app, "gamma");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.state, AppState::SessionList);
+    assert!(app.input.is_empty());
+    assert!(app.error_message.take().unwrap().contains("max_sessions"));
+
+    // Going to a missing session would create it, so it's refused too
+    press(&mut app, KeyCode::Char('g'));
+    type_str(&mut app, "gamma");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.action, AppAction::None);
+    assert!(app.error_message.take().is_some());
+    assert_eq!(tmux.session_names(), ["alpha", "zeta", "beta"]);
+
+    tmux.kill_session(None, "zeta", false).unwrap();
+    app.refresh_sessions();
+    assert_eq!(app.session_limit_reached(), None);
+    app.selected_index = app.visible_sessions().len();
+    press(&mut app, KeyCode::Enter);
+    type_str(&mut app, "gamma");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.error_message, None);
+    assert_eq!(tmux.session_names(), ["alpha", "beta", "gamma"]);
+}
+
+#[test]
+fn renumbering_names_sessions_in_list_order() {
+    let tmux = FakeTmux::with_sessions(&["web-2", "web-1", "misc"]);
+    let mut app = App::with_backend(Box::new(tmux.clone()));
+    let shown: Vec<String> = app
+        .visible_sessions()
+        .into_iter()
+        .map(|i| app.sessions[i].name.clone())
+        .collect();
+
+    press(&mut app, KeyCode::Char('='));
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.state, AppState::NumberingSessions);
+    type_str(&mut app, "web");
+    press(&mut app, KeyCode::Enter);
+    assert!(matches!(
+        app.state,
+        AppState::ConfirmingBatch {
+            operation: BatchOperation::Renumber { .. },
+            ..
+        }
+    ));
+    press(&mut app, KeyCode::Enter);
+    finish_batch(&mut app);
+    assert_eq!(app.state, AppState::SessionList);
+
+    // Swapping web-1 and web-2 goes through temporary names, so nothing clashes
+    let plan = renumber_plan("web", &shown);
+    let expected: Vec<String> = ["web-2", "web-1", "misc"]
+        .iter()
+        .map(|old| plan.iter().find(|(o, _)| o == old).unwrap().1.clone())
+        .collect();
+    assert_eq!(tmux.session_names(), expected);
+    assert!(app.error_message.is_none());
+}
+
+#[test]
+fn quitting_mid_renumber_waits_for_it_to_finish() {
+    let tmux = FakeTmux::with_sessions(&["web-2", "web-1", "misc"]);
+    let mut app = App::with_backend(Box::new(tmux.clone()));
+
+    press(&mut app, KeyCode::Char('='));
+    press(&mut app, KeyCode::Enter);
+    type_str(&mut app, "web");
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Enter);
+    app.tick();
+    assert!(tmux
+        .session_names()
+        .iter()
+        .any(|name| name.starts_with("ursa-renumber-")));
+
+    ctrl(&mut app, 'c');
+    assert!(!app.should_quit);
+    assert!(app.is_renumbering());
+    finish_batch(&mut app);
+    assert!(app.should_quit);
+    assert!(tmux
+        .session_names()
+        .iter()
+        .all(|name| name.starts_with("web-")));
+}
+
+#[test]
+fn the_same_name_on_two_servers_is_acted_on_where_it_was_selected() {
+    let tmux = FakeTmux::with_sessions(&[]);
+    tmux.add_session(Some("work"), "api");
+    tmux.add_session(Some("play"), "api");
+    let mut app = App::with_backend(Box::new(tmux.clone()));
+    let select = |app: &mut App, socket: &str| {
+        app.selected_index = app
+            .visible_sessions()
+            .iter()
+            .position(|&i| app.sessions[i].socket.as_deref() == Some(socket))
+            .unwrap();
+    };
+
+    select(&mut app, "play");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(
+        app.action,
+        AppAction::AttachSession {
+            name: "api".to_string(),
+            socket: Some("play".to_string()),
+        }
+    );
+    app.action = AppAction::None;
+
+    select(&mut app, "play");
+    press(&mut app, KeyCode::Right);
+    press(&mut app, KeyCode::Enter);
+    ctrl(&mut app, 'u');
+    type_str(&mut app, "web");
+    press(&mut app, KeyCode::Enter);
+    assert!(tmux.session_on(Some("play"), "web").is_some());
+    assert!(tmux.session_on(Some("work"), "api").is_some());
+
+    // Renumbering parks and renames each on its own server
+    press(&mut app, KeyCode::Char('='));
+    press(&mut app, KeyCode::Enter);
+    type_str(&mut app, "s");
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Enter);
+    finish_batch(&mut app);
+    let work = tmux.session_names_on(Some("work"));
+    let play = tmux.session_names_on(Some("play"));
+    assert_eq!(work.len() + play.len(), 2);
+    assert!(work.iter().chain(&play).all(|name| name.starts_with("s-")));
+    assert!(app.error_message.is_none());
+
+    select(&mut app, "play");
+    app.selected_action = SessionAction::Delete;
+    press(&mut app, KeyCode::Enter);
+    assert!(tmux.session_names_on(Some("play")).is_empty());
+    assert_eq!(tmux.session_names_on(Some("work")), work);
+}
+
+#[test]
+fn deleting_the_session_ursa_runs_in_asks_first() {
+    let tmux = FakeTmux::with_sessions(&["alpha", "beta"]);
+    tmux.set_current_session(Some(("default", "alpha")));
+    let mut app = App::with_backend(Box::new(tmux.clone()));
+
+    app.selected_action = SessionAction::Delete;
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(
+        app.state,
+        AppState::ConfirmingOwnDelete {
+            name: "alpha".to_string()
+        }
+    );
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(app.state, AppState::SessionList);
+    assert_eq!(tmux.session_names(), ["alpha", "beta"]);
+
+    app.selected_action = SessionAction::Delete;
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Char('y'));
+    assert_eq!(tmux.session_names(), ["beta"]);
+
+    // Other sessions go without asking
+    app.selected_action = SessionAction::Delete;
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.state, AppState::SessionList);
+    assert!(tmux.session_names().is_empty());
+}
+
+#[test]
+fn the_same_name_on_another_server_is_deleted_without_asking() {
+    let tmux = FakeTmux::with_sessions(&[]);
+    tmux.add_session(Some("work"), "api");
+    tmux.add_session(Some("other"), "api");
+    tmux.set_current_session(Some(("work", "api")));
+    let mut app = App::with_backend(Box::new(tmux.clone()));
+    let select = |app: &mut App, socket: &str| {
+        app.selected_index = app
+            .visible_sessions()
+            .iter()
+            .position(|&i| app.sessions[i].socket.as_deref() == Some(socket))
+            .unwrap();
+    };
+
+    select(&mut app, "other");
+    app.selected_action = SessionAction::Delete;
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.state, AppState::SessionList);
+    assert!(tmux.session_names_on(Some("other")).is_empty());
+
+    select(&mut app, "work");
+    app.selected_action = SessionAction::Delete;
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(
+        app.state,
+        AppState::ConfirmingOwnDelete {
+            name: "api".to_string()
+        }
+    );
+}
+
+#[test]
+fn details_load_in_the_background_only_while_shown() {
+    let tmux = FakeTmux::with_sessions(&["alpha", "beta"]);
+    let mut app = App::with_backend(Box::new(tmux));
+
+    // Too narrow for the pane, and the focus is on the list
+    app.tick();
+    assert!(app.details.is_none());
+
+    app.detail_pane_fits.set(true);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while app.details.is_none() {
+        assert!(Instant::now() < deadline, "details never loaded");
+        app.tick();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let (target, details) = app.details.as_ref().unwrap();
+    assert_eq!(*target, (None, "alpha".to_string()));
+    assert!(details.is_ok());
+}
+
+#[test]
+fn digits_in_the_detail_pane_attach_to_that_window() {
+    let tmux = FakeTmux::with_sessions(&["alpha"]);
+    tmux.set_base_index(1);
+    let mut app = App::with_backend(Box::new(tmux.clone()));
+    app.details = Some((
+        (None, "alpha".to_string()),
+        app.backend.session_details(None, "alpha"),
+    ));
+    app.focus_area = FocusArea::Detail;
+
+    // Numbered from 1, so there's no window 0
+    press(&mut app, KeyCode::Char('0'));
+    assert_eq!(app.action, AppAction::None);
+    assert!(app
+        .status_message
+        .as_deref()
+        .is_some_and(|m| m.contains("numbered from 1")));
+
+    press(&mut app, KeyCode::Char('1'));
+    assert_eq!(app.action, attach("alpha"));
+}
+
+#[test]
+fn splits_and_sync_from_the_detail_pane_reach_the_backend() {
+    let tmux = FakeTmux::with_sessions(&["alpha"]);
+    let mut app = App::with_backend(Box::new(tmux.clone()));
+    app.focus_area = FocusArea::Detail;
+
+    press(&mut app, KeyCode::Char('%'));
+    press(&mut app, KeyCode::Char('"'));
+    assert_eq!(tmux.pane_count(None, "alpha"), 3);
+    assert!(app.details.is_none());
+
+    app.details = Some((
+        (None, "alpha".to_string()),
+        app.backend.session_details(None, "alpha"),
+    ));
+    press(&mut app, KeyCode::Char('S'));
+    let details = tmux.session_details(None, "alpha").unwrap();
+    assert!(details.windows.iter().all(|w| w.synchronized));
+    assert_eq!(details.windows[0].panes, 3);
+}
+
+#[test]
+fn sessions_at_a_bare_shell_are_offered_as_empty() {
+    let tmux = FakeTmux::with_sessions(&["idle", "editing", "gone"]);
+    tmux.set_command(None, "editing", "vim");
+    let app = App::with_backend(Box::new(tmux.clone()));
+    tmux.kill_session(None, "gone", false).unwrap();
+
+    let candidates = app.empty_session_candidates();
+    let names: Vec<_> = candidates.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["idle"]);
+}
+
+#[test]
+fn switching_to_a_session_types_its_macro() {
+    let tmux = FakeTmux::with_sessions(&["api", "web"]);
+    let mut app = App::with_backend(Box::new(tmux.clone()));
+    let keys = vec!["nvim .".to_string(), "Enter".to_string()];
+    app.config.macros.insert("api".to_string(), keys.clone());
+
+    app.switch_in_place(None, "api");
+    assert_eq!(tmux.sent_keys(), [("api".to_string(), keys)]);
+
+    // Nothing for sessions without one
+    app.switch_in_place(None, "web");
+    assert_eq!(tmux.sent_keys().len(), 1);
+    assert!(app.error_message.is_none());
+}
+
+#[test]
+fn renaming_an_attached_session_can_need_confirmation() {
+    let mut shared = session("shared");
+    shared.attached = true;
+    let mut app = App::with_sessions(vec![shared]);
+
+    // Off by default
+    press(&mut app, KeyCode::Right);
+    press(&mut app, KeyCode::Enter);
+    assert!(matches!(app.state, AppState::RenamingSession { .. }));
+    press(&mut app, KeyCode::Esc);
+
+    app.config.confirm_attached_rename = true;
+    press(&mut app, KeyCode::Right);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(
+        app.state,
+        AppState::ConfirmingAttachedRename {
+            name: "shared".to_string(),
+            socket: None,
+        }
+    );
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(app.state, AppState::SessionList);
+
+    press(&mut app, KeyCode::Right);
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Char('y'));
+    assert_eq!(
+        app.state,
+        AppState::RenamingSession {
+            original_name: "shared".to_string(),
+            socket: None,
+        }
+    );
+}
+
+#[test]
+fn rename_validation_allows_own_name_only() {
+    let mut app = app_with(&["alpha", "beta"]);
+    assert_eq!(app.validate_input(), None);
+
+    press(&mut app, KeyCode::Right);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.validate_input(), Some(Ok(None)));
+
+    ctrl(&mut app, 'u');
+    type_str(&mut app, "beta");
+    assert!(matches!(app.validate_input(), Some(Err(_))));
+
+    // Enter on an invalid name explains instead of calling tmux
+    press(&mut app, KeyCode::Enter);
+    assert!(matches!(app.state, AppState::RenamingSession { .. }));
+    assert_eq!(
+        app.status_message.as_deref(),
+        Some("A session named 'beta' already exists")
+    );
+}
+
+#[test]
+fn affix_prompt_builds_and_validates_the_new_name() {
+    let mut app = app_with(&["api", "api-done"]);
+
+    press(&mut app, KeyCode::Char(']'));
+    assert_eq!(
+        app.state,
+        AppState::AffixingName {
+            original_name: "api".to_string(),
+            socket: None,
+            prepend: false
+        }
+    );
+    assert!(matches!(app.validate_input(), Some(Err(_))));
+    type_str(&mut app, "-done");
+    assert_eq!(app.affixed_name().as_deref(), Some("api-done"));
+    press(&mut app, KeyCode::Enter);
+    assert!(matches!(app.state, AppState::AffixingName { .. }));
+    assert_eq!(
+        app.status_message.as_deref(),
+        Some("A session named 'api-done' already exists")
+    );
+
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('['));
+    type_str(&mut app, "wip-");
+    assert_eq!(app.affixed_name().as_deref(), Some("wip-api"));
+    assert_eq!(app.validate_input(), Some(Ok(None)));
+}
+
+#[test]
+fn prompt_editing_keys() {
+    let mut app = app_with(&["my-project"]);
+
+    press(&mut app, KeyCode::Right);
+    press(&mut app, KeyCode::Enter);
+    ctrl(&mut app, 'w');
+    assert_eq!(app.input.as_str(), "my-");
+
+    ctrl(&mut app, 'a');
+    press(&mut app, KeyCode::Char('x'));
+    assert_eq!(app.input.as_str(), "xmy-");
+
+    ctrl(&mut app, 'e');
+    press(&mut app, KeyCode::Backspace);
+    assert_eq!(app.input.as_str(), "xmy");
+
+    press(&mut app, KeyCode::Left);
+    press(&mut app, KeyCode::Left);
+    ctrl(&mut app, 'k');
+    assert_eq!(app.input.as_str(), "x");
+
+    ctrl(&mut app, 'u');
+    assert!(app.input.is_empty());
+
+    // The search query takes the deleting ones too
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('/'));
+    type_str(&mut app, "my-pro");
+    ctrl(&mut app, 'w');
+    assert_eq!(app.search_query.as_str(), "my-");
+    ctrl(&mut app, 'u');
+    assert_eq!(app.search_query.as_str(), "");
+}
+
+#[test]
+fn vi_mode_prompts_have_a_normal_mode() {
+    let mut app = app_with(&["alpha"]);
+    app.config.input_mode = InputMode::Vi;
+
+    press(&mut app, KeyCode::Char('g'));
+    assert_eq!(app.state, AppState::GoingTo);
+    type_str(&mut app, "web-api-v2");
+
+    // Esc leaves typing rather than the prompt; letters are motions now
+    press(&mut app, KeyCode::Esc);
+    assert!(app.input.is_normal_mode());
+    assert_eq!(app.state, AppState::GoingTo);
+    type_str(&mut app, "bb");
+    assert_eq!(app.input.split_at_cursor(), ("web-", "api-v2"));
+    press(&mut app, KeyCode::Char('x'));
+    assert_eq!(app.input.as_str(), "web-pi-v2");
+    type_str(&mut app, "0w");
+    assert_eq!(app.input.split_at_cursor(), ("web-", "pi-v2"));
+
+    press(&mut app, KeyCode::Char('i'));
+    type_str(&mut app, "a");
+    assert_eq!(app.input.as_str(), "web-api-v2");
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('A'));
+    type_str(&mut app, "x");
+    assert_eq!(app.input.as_str(), "web-api-v2x");
+
+    // Esc in normal mode cancels, and the next prompt starts out typing
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.state, AppState::SessionList);
+    press(&mut app, KeyCode::Char('g'));
+    assert!(!app.input.is_normal_mode());
+    type_str(&mut app, "h");
+    assert_eq!(app.input.as_str(), "h");
+}
+
+#[test]
+fn vi_mode_edits_the_search_query_too() {
+    let mut app = app_with(&["api", "web", "worker"]);
+    app.config.input_mode = InputMode::Vi;
+
+    press(&mut app, KeyCode::Char('/'));
+    type_str(&mut app, "wx");
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.state, AppState::Searching);
+    assert!(app.search_query.is_normal_mode());
+    press(&mut app, KeyCode::Char('x'));
+    assert_eq!(app.search_query.as_str(), "w");
+    press(&mut app, KeyCode::Char('a'));
+    type_str(&mut app, "o");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.search_query.as_str(), "wo");
+    assert_eq!(app.matches.len(), 1);
+    assert_eq!(app.selected_session().unwrap().name, "worker");
+
+    // The next search starts out typing, and Esc from normal mode clears it
+    press(&mut app, KeyCode::Char('/'));
+    assert!(!app.search_query.is_normal_mode());
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.state, AppState::SessionList);
+    assert!(app.search_query.is_empty());
+}
+
+#[test]
+fn search_filters_and_esc_clears_before_quitting() {
+    let mut app = app_with(&["api", "web", "worker"]);
+
+    press(&mut app, KeyCode::Char('/'));
+    assert_eq!(app.state, AppState::Searching);
+    type_str(&mut app, "wr");
+    press(&mut app, KeyCode::Enter);
+
+    let visible: Vec<&str> = app
+        .visible_sessions()
+        .into_iter()
+        .map(|i| app.sessions[i].name.as_str())
+        .collect();
+    assert_eq!(visible, vec!["worker"]);
+
+    press(&mut app, KeyCode::Esc);
+    assert!(app.search_query.is_empty());
+    assert!(!app.should_quit);
+    assert_eq!(app.selected_session().unwrap().name, "worker");
+
+    press(&mut app, KeyCode::Esc);
+    assert!(app.should_quit);
+}
+
+#[test]
+fn clearing_a_search_keeps_the_cursor_on_the_create_row() {
+    let mut app = app_with(&["api", "web", "worker"]);
+
+    press(&mut app, KeyCode::Char('/'));
+    type_str(&mut app, "wr");
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Down);
+    assert!(app.selected_session().is_none());
+
+    press(&mut app, KeyCode::Esc);
+    assert!(app.search_query.is_empty());
+    assert_eq!(app.selected_index, app.visible_sessions().len());
+    assert!(app.selected_session().is_none());
+}
+
+#[test]
+fn search_matches_follow_once_typing_pauses() {
+    let mut app = app_with(&["api", "web", "worker"]);
+
+    press(&mut app, KeyCode::Char('/'));
+    type_str(&mut app, "w");
+    assert_eq!(app.visible_sessions().len(), 2);
+
+    // Later keystrokes only mark the filter dirty until the debounce passes
+    type_str(&mut app, "o");
+    assert!(app.filter_dirty);
+    app.tick();
+    assert_eq!(app.visible_sessions().len(), 2);
+
+    std::thread::sleep(Duration::from_millis(100));
+    app.tick();
+    assert!(!app.filter_dirty);
+    assert_eq!(app.selected_session().unwrap().name, "worker");
+    assert_eq!(app.visible_sessions().len(), 1);
+
+    // Enter applies a pending query straight away
+    press(&mut app, KeyCode::Backspace);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.visible_sessions().len(), 2);
+}
+
+#[test]
+fn jump_search_keeps_every_row() {
+    let mut app = app_with(&["api", "web", "worker"]);
+
+    press(&mut app, KeyCode::Char('/'));
+    press(&mut app, KeyCode::Tab);
+    assert_eq!(app.search_style, SearchStyle::Jump);
+    type_str(&mut app, "wo");
+    std::thread::sleep(Duration::from_millis(100));
+    app.tick();
+
+    assert_eq!(app.visible_sessions().len(), 3);
+    assert_eq!(app.selected_session().unwrap().name, "worker");
+}
+
+#[test]
+fn fuzzy_scores_rank_the_likely_session_first() {
+    let ranked = |query: &str, names: &[&str]| -> Vec<String> {
+        let mut scored: Vec<(i32, &str)> = names
+            .iter()
+            .filter_map(|&name| fuzzy::score(query, name).map(|score| (score, name)))
+            .collect();
+        scored.sort_by_key(|&(score, name)| (std::cmp::Reverse(score), name.len()));
+        scored.into_iter().map(|(_, name)| name.to_string()).collect()
+    };
+
+    // Not a subsequence, in either case
+    assert_eq!(fuzzy::score("xyz", "api"), None);
+    assert_eq!(fuzzy::score("apis", "api"), None);
+    assert!(fuzzy::score("API", "my-api").is_some());
+
+    // A prefix beats the same letters mid-word
+    assert_eq!(ranked("api", &["rapid", "api-server"]), ["api-server", "rapid"]);
+    // A run beats letters spread out
+    assert_eq!(ranked("web", &["w-e-b", "my-web"]), ["my-web", "w-e-b"]);
+    // Word starts beat letters inside words, camelCase humps included
+    assert_eq!(ranked("fb", &["fabric", "foo-bar"]), ["foo-bar", "fabric"]);
+    assert_eq!(ranked("fb", &["fabric", "fooBar"]), ["fooBar", "fabric"]);
+    // The best alignment counts, not the leftmost one
+    assert!(fuzzy::score("api", "a-proj-api") > fuzzy::score("api", "a-project-pi"));
+}
+
+#[test]
+fn search_selects_the_best_ranked_match() {
+    let mut app = app_with(&["rapid", "scratch-api", "api"]);
+
+    press(&mut app, KeyCode::Char('/'));
+    type_str(&mut app, "api");
+    press(&mut app, KeyCode::Enter);
+
+    let visible: Vec<&str> = app
+        .visible_sessions()
+        .into_iter()
+        .map(|i| app.sessions[i].name.as_str())
+        .collect();
+    assert_eq!(visible, ["api", "scratch-api", "rapid"]);
+    assert_eq!(app.selected_session().unwrap().name, "api");
+
+    // Enter attaches to it
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.action, attach("api"));
+}
+
+#[test]
+fn prompts_recall_earlier_entries() {
+    let mut app = app_with(&["api", "web", "worker"]);
+
+    for query in ["wo", "wo", "api"] {
+        press(&mut app, KeyCode::Char('/'));
+        type_str(&mut app, query);
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Esc);
+    }
+    assert_eq!(app.search_history.entries(), ["wo", "api"]);
+
+    // ↑ walks back from the newest and stops at the oldest; ↓ past the
+    // newest gives back what was typed
+    press(&mut app, KeyCode::Char('/'));
+    type_str(&mut app, "w");
+    press(&mut app, KeyCode::Up);
+    assert_eq!(app.search_query.as_str(), "api");
+    press(&mut app, KeyCode::Up);
+    press(&mut app, KeyCode::Up);
+    assert_eq!(app.search_query.as_str(), "wo");
+    assert_eq!(app.selected_session().unwrap().name, "worker");
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Down);
+    assert_eq!(app.search_query.as_str(), "w");
+    press(&mut app, KeyCode::Esc);
+
+    app.create_history = History::new(vec!["scratch".to_string(), "notes".to_string()]);
+    for _ in 0..4 {
+        press(&mut app, KeyCode::Down);
+    }
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.state, AppState::CreatingSession);
+    press(&mut app, KeyCode::Up);
+    press(&mut app, KeyCode::Up);
+    assert_eq!(app.input.as_str(), "scratch");
+    press(&mut app, KeyCode::Down);
+    assert_eq!(app.input.as_str(), "notes");
+
+    let saved = app.saved_state();
+    assert_eq!(saved.search_history, ["wo", "api"]);
+    assert_eq!(saved.create_history, ["scratch", "notes"]);
+}
+
+#[test]
+fn session_prefixes_resolve_like_tmux_targets() {
+    let sessions: Vec<TmuxSession> = ["api", "app", "web", "web-old"]
+        .iter()
+        .map(|name| session(name))
+        .collect();
+
+    assert_eq!(resolve_session_prefix(&sessions, "api"), Ok("api".to_string()));
+    assert_eq!(
+        resolve_session_prefix(&sessions, "we"),
+        Err(vec!["web".to_string(), "web-old".to_string()])
+    );
+    // An exact name wins over longer names it starts
+    assert_eq!(resolve_session_prefix(&sessions, "web"), Ok("web".to_string()));
+    assert_eq!(resolve_session_prefix(&sessions, "web-"), Ok("web-old".to_string()));
+    assert_eq!(
+        resolve_session_prefix(&sessions, "a"),
+        Err(vec!["api".to_string(), "app".to_string()])
+    );
+    assert_eq!(resolve_session_prefix(&sessions, "db"), Err(Vec::new()));
+}
+
+#[test]
+fn branch_names_become_session_names() {
+    let dir = std::env::temp_dir().join(format!("ursa-test-branch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    assert_eq!(branch_session_name(&dir), None);
+
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(&dir)
+            .args(["-c", "user.name=ursa", "-c", "user.email=ursa@example.com"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?}", args);
+    };
+    git(&["init", "-q", "-b", "feature/login.v2"]);
+    git(&["commit", "-q", "--allow-empty", "-m", "start"]);
+    assert_eq!(branch_session_name(&dir).as_deref(), Some("feature-login-v2"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dot_shows_hidden_sessions_for_a_while() {
+    let mut app = app_with(&["api", "infra-dns", "infra-vpn", "web"]);
+    app.config.hidden = vec!["infra-*".to_string(), "nope".to_string()];
+    let names = |app: &App| -> Vec<String> {
+        app.visible_sessions()
+            .into_iter()
+            .map(|i| app.sessions[i].name.clone())
+            .collect()
+    };
+    assert_eq!(names(&app), ["api", "web"]);
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Char('.'));
+    assert_eq!(app.status_message.as_deref(), Some("Showing 2 hidden sessions"));
+    assert_eq!(names(&app), ["api", "infra-dns", "infra-vpn", "web"]);
+    // The cursor stays on the session it was on
+    assert_eq!(app.selected_session().unwrap().name, "web");
+    assert!(app.is_shown_hidden("infra-vpn"));
+
+    press(&mut app, KeyCode::Up);
+    press(&mut app, KeyCode::Char('.'));
+    assert_eq!(names(&app), ["api", "web"]);
+    // Its session went out of sight, so the cursor is kept within the rows
+    assert_eq!(app.selected_index, 2);
+}
+
+#[test]
+fn attaching_to_grouped_session_warns_first() {
+    let mut sessions: Vec<TmuxSession> = ["base", "linked", "solo"]
+        .iter()
+        .map(|name| session(name))
+        .collect();
+    sessions[0].group = Some("base".to_string());
+    sessions[1].group = Some("base".to_string());
+    let mut app = App::with_sessions(sessions);
+
+    assert_eq!(app.group_peers("base"), vec!["linked"]);
+    assert!(app.group_peers("solo").is_empty());
+
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.action, AppAction::None);
+    assert_eq!(app.pending_attach, Some((None, "base".to_string())));
+    assert!(app.toast.is_some());
+
+    // Any key cancels the pending attach
+    press(&mut app, KeyCode::Down);
+    assert!(app.pending_attach.is_none());
+}
+
+#[test]
+fn attaching_to_ungrouped_session_is_immediate() {
+    let mut app = app_with(&["solo"]);
+
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.action, attach("solo"));
+}
+
+#[test]
+fn detail_pane_focus_scrolls_instead_of_moving() {
+    let mut app = app_with(&["alpha", "beta"]);
+
+    press(&mut app, KeyCode::Tab);
+    assert_eq!(app.focus_area, FocusArea::Detail);
+
+    press(&mut app, KeyCode::Char('j'));
+    press(&mut app, KeyCode::Char('j'));
+    assert_eq!(app.detail_scroll.get(), 2);
+    assert_eq!(app.selected_index, 0);
+
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.focus_area, FocusArea::SessionList);
+    assert!(!app.should_quit);
+
+    // Nothing to detail on the create row
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Tab);
+    assert_eq!(app.focus_area, FocusArea::SessionList);
+}
+
+#[test]
+fn attached_color_accepts_names_indices_and_hex() {
+    let mut app = app_with(&["alpha"]);
+    assert_eq!(app.config.attached_color(), Some(Color::Green));
+
+    for (value, color) in [
+        ("lightblue", Some(Color::LightBlue)),
+        ("208", Some(Color::Indexed(208))),
+        ("#ff8800", Some(Color::Rgb(0xff, 0x88, 0x00))),
+        ("", None),
+        ("not-a-color", None),
+    ] {
+        app.config.attached_color = value.to_string();
+        assert_eq!(app.config.attached_color(), color, "{value}");
+    }
+}
+
+#[test]
+fn y_copies_the_attach_command() {
+    let copied = test_config_dir().join("copied.txt");
+    let mut app = app_with(&["alpha", "my work"]);
+    app.config.clipboard = Some(format!("cat > '{}'", copied.display()));
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Char('y'));
+    let command = std::fs::read_to_string(&copied).unwrap();
+    assert!(command.ends_with("attach-session -t 'my work'"), "{command}");
+    assert_eq!(app.toast.as_ref().unwrap().message, format!("Copied: {command}"));
+
+    app.config.clipboard = Some("exit 1".to_string());
+    press(&mut app, KeyCode::Char('y'));
+    assert!(app.error_message.as_ref().unwrap().starts_with("Clipboard command failed"));
+    std::fs::remove_file(&copied).unwrap();
+}
+
+#[test]
+fn quits_after_the_idle_timeout() {
+    let mut app = app_with(&["alpha"]);
+    app.last_input = Instant::now() - Duration::from_secs(60);
+    app.tick();
+    assert!(!app.should_quit, "off by default");
+
+    app.config.quit_after_idle_secs = 30;
+    // A key press starts the wait over
+    press(&mut app, KeyCode::Down);
+    app.tick();
+    assert!(!app.should_quit);
+
+    app.last_input = Instant::now() - Duration::from_secs(30);
+    app.tick();
+    assert!(app.should_quit);
+}
+
+#[test]
+fn ctrl_t_cycles_color_schemes() {
+    let mut app = app_with(&["alpha"]);
+    assert_eq!(app.theme.color(Color::DarkGray), Color::DarkGray);
+
+    ctrl(&mut app, 't');
+    assert_eq!(app.theme.scheme, ColorScheme::HighContrast);
+    assert_eq!(app.theme.color(Color::DarkGray), Color::Gray);
+    assert_eq!(app.toast.as_ref().unwrap().message, "Theme: high-contrast");
+    assert_eq!(app.saved_state().color_scheme, ColorScheme::HighContrast);
+
+    ctrl(&mut app, 't');
+    ctrl(&mut app, 't');
+    assert_eq!(app.theme.scheme, ColorScheme::Monochrome);
+    assert!(!app.theme.has_color());
+    ctrl(&mut app, 't');
+    assert_eq!(app.theme.scheme, ColorScheme::Default);
+
+    // With colors turned off there's nothing to cycle
+    app.theme = Theme::monochrome();
+    ctrl(&mut app, 't');
+    assert_eq!(app.theme.scheme, ColorScheme::Default);
+    assert!(app.status_message.is_some());
+}
+
+#[test]
+fn reload_config_applies_changes_and_keeps_old_on_error() {
+    let config = test_config_dir().join("config.toml");
+    let mut app = app_with(&[]);
+
+    std::fs::write(&config, "wrap_actions = true\n").unwrap();
+    press(&mut app, KeyCode::Char('R'));
+    assert!(app.config.wrap_actions);
+    assert_eq!(app.toast.as_ref().unwrap().message, "Config reloaded");
+
+    std::fs::write(&config, "wrap_actions = \n").unwrap();
+    ctrl(&mut app, 'r');
+    assert!(app.config.wrap_actions);
+    let message = &app.toast.as_ref().unwrap().message;
+    assert!(
+        message.starts_with("Config not reloaded: Invalid config"),
+        "{}",
+        message
+    );
+    std::fs::remove_file(&config).unwrap();
+}
+
+#[test]
+fn e_opens_the_config_file_creating_it_from_the_template() {
+    let config = test_config_dir().join("config.toml");
+    let mut app = app_with(&[]);
+
+    press(&mut app, KeyCode::Char('E'));
+    assert_eq!(app.action, AppAction::EditConfig(config));
+
+    // Everything in the template is commented out
+    let fresh = test_config_dir().join("edit-test").join("config.toml");
+    ensure_config_file(&fresh).unwrap();
+    let template: Config = toml::from_str(&std::fs::read_to_string(&fresh).unwrap()).unwrap();
+    assert_eq!(template, Config::default());
+
+    // An existing file is left as it is
+    std::fs::write(&fresh, "paginate = true\n").unwrap();
+    ensure_config_file(&fresh).unwrap();
+    assert_eq!(std::fs::read_to_string(&fresh).unwrap(), "paginate = true\n");
+}
+
+#[test]
+fn focus_pauses_auto_refresh_and_refreshes_on_return() {
+    let mut app = app_with(&["alpha"]);
+    app.config.auto_refresh_ms = 1;
+    let stale = Instant::now() - Duration::from_secs(1);
+
+    app.set_focused(false);
+    app.last_refresh = stale;
+    app.tick();
+    assert_eq!(app.last_refresh, stale);
+
+    app.set_focused(true);
+    assert!(app.last_refresh > stale);
+
+    // With the preference off, focus reports are ignored
+    app.config.refresh_on_focus = false;
+    app.set_focused(false);
+    assert!(app.focused);
+}
+
+#[test]
+fn overflowing_help_bar_turns_its_pages() {
+    let mut app = app_with(&["alpha"]);
+    let stale = Instant::now() - Duration::from_secs(5);
+
+    app.help_paged_at = stale;
+    app.tick();
+    assert_eq!(app.help_page, 0);
+
+    app.help_pages.set(2);
+    app.help_paged_at = stale;
+    app.tick();
+    assert_eq!(app.help_page, 1);
+    app.help_paged_at = stale;
+    app.tick();
+    assert_eq!(app.help_page, 0);
+
+    // New hints start over on their first page
+    app.help_page = 1;
+    press(&mut app, KeyCode::Char('/'));
+    assert_eq!(app.help_page, 0);
+}
+
+#[test]
+fn kill_detached_spares_attached_and_locked_sessions() {
+    test_config_dir();
+    let mut sessions: Vec<_> = ["current", "stale", "pinned"]
+        .iter()
+        .map(|name| session(name))
+        .collect();
+    sessions[0].attached = true;
+    let mut app = App::with_sessions(sessions);
+    app.dry_run = true;
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Char('P'));
+    press(&mut app, KeyCode::Char('K'));
+    assert_eq!(
+        app.state,
+        AppState::ConfirmingBatch {
+            operation: BatchOperation::Kill(CleanupKind::Detached),
+            items: vec![BatchItem {
+                name: "stale".to_string(),
+                socket: None,
+                included: true
+            }],
+            cursor: 0,
+        }
+    );
+
+    press(&mut app, KeyCode::Enter);
+    finish_batch(&mut app);
+    assert_eq!(app.state, AppState::SessionList);
+    assert_eq!(
+        app.toast.as_ref().unwrap().message,
+        "Dry run: Killed 1 detached session"
+    );
+}
+
+#[test]
+fn batch_confirmation_runs_only_included_sessions() {
+    let mut app = app_with(&["one", "two", "three"]);
+    app.dry_run = true;
+
+    press(&mut app, KeyCode::Char('K'));
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Char(' '));
+    let AppState::ConfirmingBatch { items, cursor, .. } = &app.state else {
+        panic!("expected the batch confirmation, got {:?}", app.state);
+    };
+    assert_eq!(*cursor, 1);
+    let included: Vec<_> = items.iter().map(|item| item.included).collect();
+    assert_eq!(included, [true, false, true]);
+
+    // Leaving everything out keeps the overlay open
+    press(&mut app, KeyCode::Char('a'));
+    press(&mut app, KeyCode::Char('a'));
+    press(&mut app, KeyCode::Enter);
+    assert!(matches!(app.state, AppState::ConfirmingBatch { .. }));
+
+    press(&mut app, KeyCode::Char('a'));
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Enter);
+    finish_batch(&mut app);
+    assert_eq!(app.state, AppState::SessionList);
+    assert_eq!(
+        app.toast.as_ref().unwrap().message,
+        "Dry run: Killed 2 detached sessions"
+    );
+}
+
+#[test]
+fn batches_run_a_session_per_tick_and_report_failures_at_the_end() {
+    let tmux = FakeTmux::with_sessions(&["one", "two", "three"]);
+    let mut app = App::with_backend(Box::new(tmux.clone()));
+
+    press(&mut app, KeyCode::Char('K'));
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.poll_timeout(), Duration::ZERO);
+    // Gone before the batch gets to it
+    tmux.kill_session(None, "two", false).unwrap();
+
+    let mut done = Vec::new();
+    while let AppState::RunningBatch { progress } = &app.state {
+        assert_eq!(progress.total, 3);
+        done.push(progress.done);
+        app.tick();
+    }
+    // Each session is drawn as done before the summary replaces the bar
+    assert_eq!(done, [0, 1, 2, 3]);
+    assert!(tmux.session_names().is_empty());
+    let error = app.error_message.as_deref().unwrap();
+    assert!(
+        error.starts_with("Killed 2 detached sessions; 1 failed:\ntwo: "),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn long_idle_sessions_are_offered_for_cleanup() {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut busy = session("busy");
+    busy.last_activity = now - 60;
+    let mut stale = session("stale");
+    stale.last_activity = now - 3 * 86400;
+    let mut watched = session("watched");
+    watched.last_activity = now - 3 * 86400;
+    watched.attached = true;
+    let mut app = App::with_sessions(vec![busy, stale, watched, session("unknown")]);
+
+    assert!(!app.is_long_idle("busy"));
+    assert!(app.is_long_idle("stale"));
+    // tmux always reports activity, so a zero is taken as unknown
+    assert!(!app.is_long_idle("unknown"));
+    let candidates = app.idle_session_candidates();
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].name, "stale");
+
+    app.config.idle_hours = 0;
+    assert!(!app.is_long_idle("stale"));
+    press(&mut app, KeyCode::Char('I'));
+    assert_eq!(app.state, AppState::SessionList);
+    assert_eq!(
+        app.status_message.as_deref(),
+        Some("No idle sessions to clean up")
+    );
+}
+
+#[test]
+fn sessions_whose_directory_is_gone_are_zombies() {
+    let mut gone = session("gone");
+    gone.path = "/nonexistent/ursa-deleted-project".to_string();
+    let mut home = session("home");
+    home.path = std::env::temp_dir().to_string_lossy().into_owned();
+    let mut app = App::with_sessions(vec![gone, home, session("unknown")]);
+
+    assert!(app.is_zombie("gone"));
+    assert!(!app.is_zombie("home"));
+    // tmux didn't say, so nothing to flag
+    assert!(!app.is_zombie("unknown"));
+
+    press(&mut app, KeyCode::Char('Z'));
+    assert_eq!(
+        app.state,
+        AppState::ConfirmingBatch {
+            operation: BatchOperation::Kill(CleanupKind::Zombie),
+            items: vec![BatchItem {
+                name: "gone".to_string(),
+                socket: None,
+                included: true
+            }],
+            cursor: 0,
+        }
+    );
+}
+
+#[test]
+fn notes_are_edited_with_n_unless_searching() {
+    test_config_dir();
+    let mut app = app_with(&["migrate", "other"]);
+
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(
+        app.state,
+        AppState::EditingNote {
+            session_name: "migrate".to_string()
+        }
+    );
+    type_str(&mut app, "flaky one");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(
+        app.notes.get("migrate").map(String::as_str),
+        Some("flaky one")
+    );
+
+    // Reopening starts from the current note; clearing it removes it
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(app.input.as_str(), "flaky one");
+    ctrl(&mut app, 'u');
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.notes.get("migrate"), None);
+
+    // While searching, n moves between matches instead
+    press(&mut app, KeyCode::Char('/'));
+    type_str(&mut app, "o");
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(app.state, AppState::SessionList);
+}
+
+#[test]
+fn go_to_attaches_to_an_existing_session() {
+    let mut app = app_with(&["alpha", "beta"]);
+
+    press(&mut app, KeyCode::Char('g'));
+    assert_eq!(app.state, AppState::GoingTo);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.state, AppState::GoingTo);
+
+    // Unlike create, an existing name is accepted
+    type_str(&mut app, "beta");
+    assert_eq!(app.validate_input(), Some(Ok(None)));
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.action, attach("beta"));
+}
+
+#[test]
+fn colliding_display_names_are_disambiguated() {
+    let names = display_names(["a\u{1}b", "a?b", "a\tb", "plain", "c\u{7}"]);
+    assert_eq!(names.get("a\u{1}b").map(String::as_str), Some("a?b #1"));
+    assert_eq!(names.get("a\tb").map(String::as_str), Some("a?b #3"));
+    assert_eq!(names.get("c\u{7}").map(String::as_str), Some("c?"));
+    // Names shown as they are keep no entry
+    assert_eq!(names.get("a?b"), None);
+    assert_eq!(names.get("plain"), None);
+
+    // The rows differ, and attaching still targets the raw name
+    let mut app = app_with(&["x\u{1}", "x\u{2}"]);
+    assert_ne!(app.display_name("x\u{1}"), app.display_name("x\u{2}"));
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.action, attach("x\u{2}"));
+}
+
+#[test]
+fn counts_move_several_rows() {
+    let mut app = app_with(&["a", "b", "c", "d", "e", "f"]);
+
+    type_str(&mut app, "4j");
+    assert_eq!(app.selected_index, 4);
+    type_str(&mut app, "2k");
+    assert_eq!(app.selected_index, 2);
+    // Counts past the end stop on the "Create new" row
+    type_str(&mut app, "12j");
+    assert_eq!(app.selected_index, 6);
+
+    // Any other key drops a pending count
+    type_str(&mut app, "3");
+    assert_eq!(app.pending_count.map(|(count, _)| count), Some(3));
+    press(&mut app, KeyCode::Char('#'));
+    assert!(app.relative_numbers);
+    assert_eq!(app.pending_count, None);
+    press(&mut app, KeyCode::Char('k'));
+    assert_eq!(app.selected_index, 5);
+}
+
+#[test]
+fn grid_arrows_move_between_neighbors() {
+    // Two columns: a b / c d / e +
+    let mut app = app_with(&["a", "b", "c", "d", "e"]);
+    press(&mut app, KeyCode::Char('V'));
+    assert_eq!(app.layout_mode, LayoutMode::Grid);
+    app.grid_columns.set(2);
+
+    press(&mut app, KeyCode::Right);
+    assert_eq!(app.selected_index, 1);
+    assert_eq!(app.selected_action, SessionAction::Enter);
+    press(&mut app, KeyCode::Down);
+    assert_eq!(app.selected_index, 3);
+    press(&mut app, KeyCode::Char('j'));
+    assert_eq!(app.selected_index, 5); // The create card
+    press(&mut app, KeyCode::Down);
+    assert_eq!(app.selected_index, 5);
+    press(&mut app, KeyCode::Left);
+    press(&mut app, KeyCode::Up);
+    assert_eq!(app.selected_index, 2);
+
+    press(&mut app, KeyCode::Up);
+    press(&mut app, KeyCode::Up);
+    assert_eq!(app.focus_area, FocusArea::TitleBar);
+    press(&mut app, KeyCode::Down);
+    assert_eq!(app.focus_area, FocusArea::SessionList);
+    assert_eq!(app.selected_index, 0);
+
+    press(&mut app, KeyCode::Char('V'));
+    assert_eq!(app.layout_mode, LayoutMode::List);
+}
+
+#[test]
+fn enter_on_a_grid_card_attaches_whatever_action_was_selected() {
+    let tmux = FakeTmux::with_sessions(&["alpha", "beta"]);
+    let mut app = App::with_backend(Box::new(tmux.clone()));
+    app.config.actions = vec![SessionAction::Delete, SessionAction::Enter];
+
+    press(&mut app, KeyCode::Char('V'));
+    app.grid_columns.set(2);
+    press(&mut app, KeyCode::Right);
+    assert_eq!(app.selected_action, SessionAction::Enter);
+
+    app.selected_action = SessionAction::Delete;
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.action, attach("beta"));
+    assert_eq!(tmux.session_names(), ["alpha", "beta"]);
+}
+
+#[test]
+fn case_only_collision_needs_a_second_enter() {
+    let mut app = app_with(&["Foo"]);
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    type_str(&mut app, "foo");
+    assert!(matches!(app.validate_input(), Some(Ok(Some(_)))));
+
+    // The first Enter only shows the warning
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.state, AppState::CreatingSession);
+    assert_eq!(
+        app.status_message.as_deref(),
+        Some("A session 'Foo' already exists; press Enter again to go ahead")
+    );
+}
+
+#[test]
+fn pasting_a_directory_names_the_session_after_it() {
+    let dir = std::env::temp_dir()
+        .join(format!("ursa-drop-{}", std::process::id()))
+        .join("my.proj");
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut app = app_with(&["alpha"]);
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    type_str(&mut app, &format!("{}/", dir.display()));
+    let dropped = app.dropped_directory();
+    let validation = app.validate_input();
+    let _ = std::fs::remove_dir_all(dir.parent().unwrap());
+
+    let (name, path) = dropped.unwrap();
+    assert_eq!(name, "my-proj");
+    assert!(path.ends_with("my.proj"));
+    assert_eq!(validation, Some(Ok(None)));
+
+    // Plain names are left alone
+    app.input.set("alpha2");
+    assert_eq!(app.dropped_directory(), None);
+}
+
+#[test]
+fn env_entries_are_added_from_the_create_prompt() {
+    let mut app = app_with(&["alpha"]);
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    type_str(&mut app, "api");
+    ctrl(&mut app, 'v');
+    assert_eq!(
+        app.state,
+        AppState::AddingEnv {
+            session_name: "api".to_string()
+        }
+    );
+
+    // Malformed entries keep the prompt open
+    type_str(&mut app, "FOO");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.status_message.as_deref(), Some("Use KEY=VALUE"));
+    type_str(&mut app, "=bar");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.state, AppState::CreatingSession);
+    assert_eq!(app.input.as_str(), "api");
+
+    // Giving a key again replaces it; Esc drops an unfinished entry
+    ctrl(&mut app, 'v');
+    type_str(&mut app, "FOO=baz");
+    press(&mut app, KeyCode::Enter);
+    ctrl(&mut app, 'v');
+    type_str(&mut app, "X=1");
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.create_env, vec!["FOO=baz".to_string()]);
+    assert_eq!(app.input.as_str(), "api");
+}
+
+#[test]
+fn hooks_get_the_session_and_report_failures() {
+    let out = std::env::temp_dir().join(format!("ursa-hook-{}.txt", std::process::id()));
+    let mut app = app_with(&[]);
+    app.config.hooks.on_create = Some(format!("echo \"$URSA_EVENT $1\" > '{}'", out.display()));
+    app.config.hooks.on_delete = Some("exit 3".to_string());
+
+    app.run_hook(HookEvent::Create, "api");
+    app.run_hook(HookEvent::Delete, "api");
+    // Unconfigured events do nothing
+    app.run_hook(HookEvent::Attach, "api");
+
+    // Both run in the background
+    let written = || std::fs::read_to_string(&out).unwrap_or_default();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while (app.toast.is_none() || !written().ends_with('\n')) && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(20));
+        app.tick();
+    }
+    assert_eq!(
+        app.toast.as_ref().map(|toast| toast.message.as_str()),
+        Some("on_delete hook failed (exit status: 3)")
+    );
+    let written = written();
+    let _ = std::fs::remove_file(&out);
+    assert_eq!(written, "on_create api\n");
+}
+
+#[test]
+fn welcome_overlay_closes_on_any_key() {
+    let dir = test_config_dir();
+    let mut app = app_with(&["alpha"]);
+    app.state = AppState::Welcome;
+
+    press(&mut app, KeyCode::Char('x'));
+    assert_eq!(app.state, AppState::SessionList);
+    assert_eq!(app.selected_index, 0);
+    assert!(dir.exists());
+}
+
+#[test]
+fn actions_follow_the_configured_order() {
+    let mut app = app_with(&["alpha", "beta"]);
+    app.config.actions = vec![SessionAction::Rename, SessionAction::Enter];
+
+    // Moving to a row starts on Enter wherever it's listed
+    press(&mut app, KeyCode::Down);
+    assert_eq!(app.selected_action, SessionAction::Enter);
+    press(&mut app, KeyCode::Left);
+    assert_eq!(app.selected_action, SessionAction::Rename);
+    press(&mut app, KeyCode::Right);
+    assert_eq!(app.selected_action, SessionAction::Enter);
+    // Delete is hidden, so this is the end
+    press(&mut app, KeyCode::Right);
+    assert_eq!(app.selected_action, SessionAction::Enter);
+
+    app.config.wrap_actions = true;
+    press(&mut app, KeyCode::Right);
+    assert_eq!(app.selected_action, SessionAction::Rename);
+    press(&mut app, KeyCode::Left);
+    assert_eq!(app.selected_action, SessionAction::Enter);
+}
+
+#[test]
+fn sessions_load_in_the_background() {
+    test_config_dir();
+    let mut app = App::new();
+    app.wait_for_sessions();
+    assert!(!app.is_loading());
+}
+
+#[test]
+fn locked_session_needs_its_name_typed() {
+    test_config_dir();
+    let mut app = app_with(&["prod", "scratch"]);
+
+    press(&mut app, KeyCode::Char('P'));
+    assert!(app.is_locked("prod"));
+
+    press(&mut app, KeyCode::Right);
+    press(&mut app, KeyCode::Right);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(
+        app.state,
+        AppState::ConfirmingLocked {
+            name: "prod".to_string(),
+            socket: None,
+            action: SessionAction::Delete
+        }
+    );
+
+    // A wrong name keeps asking
+    type_str(&mut app, "pro");
+    press(&mut app, KeyCode::Enter);
+    assert!(matches!(app.state, AppState::ConfirmingLocked { .. }));
+
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.state, AppState::SessionList);
+
+    // Renaming asks too, then opens the usual prompt
+    press(&mut app, KeyCode::Right);
+    press(&mut app, KeyCode::Enter);
+    type_str(&mut app, "prod");
+    press(&mut app, KeyCode::Enter);
+    assert!(matches!(app.state, AppState::RenamingSession { .. }));
+    assert_eq!(app.input.as_str(), "prod");
+
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('P'));
+    assert!(!app.is_locked("prod"));
+}
+
+#[test]
+fn save_scrollback_prompt_suggests_a_file() {
+    let mut app = app_with(&["build"]);
+
+    press(&mut app, KeyCode::Char('>'));
+    assert_eq!(
+        app.state,
+        AppState::SavingScrollback {
+            session_name: "build".to_string(),
+            socket: None,
+        }
+    );
+    assert_eq!(app.input.as_str(), "build-scrollback.txt");
+
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.state, AppState::SessionList);
+    assert!(app.input.is_empty());
+}
+
+#[test]
+fn action_log_overlay_toggles() {
+    let mut app = app_with(&["alpha"]);
+
+    press(&mut app, KeyCode::Char('H'));
+    assert_eq!(app.state, AppState::ViewingLog);
+    assert!(app.action_log.is_empty());
+
+    // q closes the overlay rather than quitting
+    press(&mut app, KeyCode::Char('q'));
+    assert_eq!(app.state, AppState::SessionList);
+    assert!(!app.should_quit);
+}
+
+#[test]
+fn truncate_middle_keeps_both_ends() {
+    assert_eq!(truncate_middle("short", 8), "short");
+    assert_eq!(truncate_middle("exactly8", 8), "exactly8");
+    assert_eq!(
+        truncate_middle("feature/very-long-branch-name", 12),
+        "featur…-name"
+    );
+    // Multibyte names are cut on char boundaries, not bytes
+    assert_eq!(truncate_middle("日本語のセッション名", 5), "日本…ン名");
+    assert_eq!(truncate_middle("ééééééé", 4), "éé…é");
+    assert_eq!(truncate_middle("abc", 1), "…");
+}
+
+#[test]
+fn dedupe_name_picks_first_free_suffix() {
+    assert_eq!(dedupe_name("api", ["web"]), "api");
+    assert_eq!(dedupe_name("api", ["api", "api-2", "web"]), "api-3");
+}
+
+#[test]
+fn quit_keys() {
+    let mut app = app_with(&["alpha"]);
+    press(&mut app, KeyCode::Char('q'));
+    assert!(app.should_quit);
+
+    let mut app = app_with(&["alpha"]);
+    ctrl(&mut app, 'c');
+    assert!(app.should_quit);
+}
@@ -0,0 +1,83 @@
+//! The `--server` protocol, answered against an in-memory tmux server.
+
+use ursa::backend::FakeTmux;
+use ursa::server::Server;
+
+fn serve(tmux: &FakeTmux, input: &str) -> Vec<String> {
+    let mut output = Vec::new();
+    Server::new(tmux, &[])
+        .serve(input.as_bytes(), &mut output)
+        .unwrap();
+    String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn requests_get_one_response_line_each() {
+    let tmux = FakeTmux::with_sessions(&["api"]);
+    let input = r#"{"cmd":"create","name":"web","dir":"/src/web"}
+
+{"cmd":"list"}
+{"cmd":"create","name":"web"}
+{"cmd":"create","name":"bad name"}
+"#;
+
+    let responses = serve(&tmux, input);
+    assert_eq!(responses.len(), 4, "{:?}", responses);
+    assert_eq!(responses[0], r#"{"ok":true}"#);
+    assert_eq!(tmux.session_names(), ["api", "web"]);
+    assert_eq!(
+        responses[1],
+        r#"{"ok":true,"sessions":[{"name":"api","windows":1,"attached":false,"last_activity":0,"path":""},{"name":"web","windows":1,"attached":false,"last_activity":0,"path":"/src/web"}]}"#
+    );
+    assert!(
+        responses[2].starts_with(r#"{"ok":false,"error":"#),
+        "{}",
+        responses[2]
+    );
+    assert!(
+        responses[3].starts_with(r#"{"ok":false,"error":"#),
+        "{}",
+        responses[3]
+    );
+}
+
+#[test]
+fn attach_switches_inside_tmux_and_returns_the_command_outside() {
+    let tmux = FakeTmux::with_sessions(&["api", "web"]);
+
+    // Outside tmux there's no client to switch
+    let responses = serve(&tmux, "{\"cmd\":\"attach\",\"name\":\"web\"}\n");
+    assert!(
+        responses[0].starts_with(r#"{"ok":true,"command":["#),
+        "{}",
+        responses[0]
+    );
+    assert!(responses[0].contains(r#""attach-session","-t","web"]"#));
+    assert!(!tmux.session("web").unwrap().attached);
+
+    tmux.set_current_session(Some(("default", "api")));
+    let responses = serve(&tmux, "{\"cmd\":\"attach\",\"name\":\"web\"}\n");
+    assert_eq!(responses, [r#"{"ok":true}"#]);
+    assert!(tmux.session("web").unwrap().attached);
+
+    let responses = serve(&tmux, "{\"cmd\":\"attach\",\"name\":\"nope\"}\n");
+    assert_eq!(
+        responses,
+        [r#"{"ok":false,"error":"No session named 'nope'"}"#]
+    );
+}
+
+#[test]
+fn bad_lines_are_answered_without_stopping() {
+    let tmux = FakeTmux::with_sessions(&[]);
+    let responses = serve(&tmux, "not json\n{\"cmd\":\"fly\"}\n{\"cmd\":\"list\"}\n");
+
+    assert_eq!(responses.len(), 3);
+    assert!(responses[0].starts_with(r#"{"ok":false,"error":"Invalid request: "#));
+    assert!(responses[1].starts_with(r#"{"ok":false,"error":"Invalid request: "#));
+    assert_eq!(responses[2], r#"{"ok":true,"sessions":[]}"#);
+}
@@ -0,0 +1,33 @@
+//! Signal handling, in a test binary of its own since the handler is
+//! installed for the whole process
+
+#![cfg(unix)]
+
+use std::process::Command;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::low_level::raise;
+use ursa::signals;
+
+#[test]
+fn sigint_is_left_to_a_program_in_the_foreground() {
+    let (sender, receiver) = mpsc::channel();
+    signals::handle(move |signal| {
+        let _ = sender.send(signal);
+    })
+    .unwrap();
+
+    // As Ctrl-C in a pager interrupts Ursa too
+    let status =
+        signals::run_child(Command::new("sh").args(["-c", "kill -INT $PPID; sleep 0.5"])).unwrap();
+    assert!(status.success());
+    assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+
+    // Once it's exited, signals stop Ursa again, and not just the first one
+    raise(SIGINT).unwrap();
+    assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(SIGINT));
+    raise(SIGTERM).unwrap();
+    assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(SIGTERM));
+}
@@ -0,0 +1,857 @@
+//! Integration tests against a real tmux server on an isolated socket.
+//!
+//! Each test starts its own server on `ursa-test-<pid>-<n>` and kills it when
+//! done. The tmux binary and remote are process-wide, so tests hold a lock while
+//! their server runs.
+//! Tests that need tmux are skipped when it isn't installed.
+
+use std::os::unix::fs::PermissionsExt;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ursa::app::{App, SessionAction};
+use ursa::config::LayoutPreset;
+use ursa::events::{Changes, EventWatcher};
+use ursa::tmux::{self, TmuxError};
+
+static SERVER_LOCK: Mutex<()> = Mutex::new(());
+static NEXT_SERVER: AtomicUsize = AtomicUsize::new(0);
+
+/// Session created with the server so it stays up between commands
+const KEEPALIVE: &str = "keepalive";
+
+struct TestServer {
+    socket: String,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl TestServer {
+    /// Start a server with an empty config, or None when tmux isn't installed
+    fn start() -> Option<Self> {
+        let lock = SERVER_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // A fresh socket per test, since a killed server can take a moment to exit
+        let socket = format!(
+            "ursa-test-{}-{}",
+            std::process::id(),
+            NEXT_SERVER.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let started = Command::new("tmux")
+            .args([
+                "-L",
+                &socket,
+                "-f",
+                "/dev/null",
+                "new-session",
+                "-d",
+                "-s",
+                KEEPALIVE,
+            ])
+            .status();
+        match started {
+            Ok(status) if status.success() => {}
+            Ok(status) => panic!("failed to start test tmux server: {}", status),
+            Err(_) => {
+                eprintln!("tmux not installed; skipping");
+                return None;
+            }
+        }
+
+        Some(Self {
+            socket,
+            _lock: lock,
+        })
+    }
+
+    /// The socket to aim tmux commands at this server
+    fn socket(&self) -> Option<&str> {
+        Some(&self.socket)
+    }
+
+    /// Run a raw tmux command against this server
+    fn tmux(&self, args: &[&str]) {
+        let status = Command::new("tmux")
+            .args(["-L", &self.socket])
+            .args(args)
+            .status()
+            .expect("run tmux");
+        assert!(status.success(), "tmux {:?} failed", args);
+    }
+
+    fn session_names(&self) -> Vec<String> {
+        tmux::list_sessions(self.socket())
+            .expect("list sessions")
+            .into_iter()
+            .map(|s| s.name)
+            .collect()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = Command::new("tmux")
+            .args(["-L", &self.socket, "kill-server"])
+            .status();
+    }
+}
+
+#[test]
+fn create_rename_and_kill_round_trip() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    tmux::create_session(server.socket(), "alpha", None, &[]).unwrap();
+    assert!(server.session_names().contains(&"alpha".to_string()));
+
+    tmux::rename_session(server.socket(), "alpha", "beta", false).unwrap();
+    let names = server.session_names();
+    assert!(!names.contains(&"alpha".to_string()));
+    assert!(names.contains(&"beta".to_string()));
+
+    tmux::kill_session(server.socket(), "beta", false).unwrap();
+    assert_eq!(server.session_names(), vec![KEEPALIVE.to_string()]);
+}
+
+#[test]
+fn deleting_keeps_the_cursor_on_a_neighbor() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+    for name in ["a", "b", "c", "z"] {
+        tmux::create_session(server.socket(), name, None, &[]).unwrap();
+    }
+    let mut app = App::with_sessions(tmux::list_sessions(server.socket()).unwrap());
+    app.config.sockets = vec![server.socket.clone()];
+    let delete_row = |app: &mut App, row: usize| {
+        app.selected_index = row;
+        app.selected_action = SessionAction::Delete;
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        app.selected_session().unwrap().name.clone()
+    };
+
+    // First and middle rows: the next session moves into the deleted one's row
+    assert_eq!(delete_row(&mut app, 0), "b");
+    assert_eq!(app.selected_index, 0);
+    assert_eq!(delete_row(&mut app, 1), KEEPALIVE);
+    assert_eq!(app.selected_index, 1);
+    // Last row: the one above it
+    assert_eq!(delete_row(&mut app, 2), KEEPALIVE);
+    assert_eq!(app.selected_index, 1);
+}
+
+#[test]
+fn create_session_sets_environment() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    let env = vec!["URSA_TEST=hello world".to_string(), "EMPTY=".to_string()];
+    tmux::create_session(server.socket(), "env", None, &env).unwrap();
+    let output = Command::new("tmux")
+        .args(["-L", &server.socket, "show-environment", "-t", "env"])
+        .output()
+        .unwrap();
+    let shown = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        shown.lines().any(|line| line == "URSA_TEST=hello world"),
+        "{}",
+        shown
+    );
+    assert!(shown.lines().any(|line| line == "EMPTY="), "{}", shown);
+}
+
+#[test]
+fn list_sessions_reports_windows_and_attachment() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    let dir = std::env::temp_dir().to_string_lossy().into_owned();
+    tmux::create_session(server.socket(), "work", Some(&dir), &[]).unwrap();
+    tmux::new_window(server.socket(), "work", Some("logs"), None).unwrap();
+
+    let sessions = tmux::list_sessions(server.socket()).unwrap();
+    let work = sessions.iter().find(|s| s.name == "work").unwrap();
+    assert_eq!(work.windows, 2);
+    assert!(!work.attached);
+    assert!(work.last_activity > 0);
+    assert_eq!(work.path, dir);
+}
+
+#[test]
+fn list_sessions_reports_groups() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    tmux::create_session(server.socket(), "base", None, &[]).unwrap();
+    server.tmux(&["new-session", "-d", "-t", "base", "-s", "linked"]);
+
+    let sessions = tmux::list_sessions(server.socket()).unwrap();
+    let group_of = |name: &str| {
+        sessions
+            .iter()
+            .find(|s| s.name == name)
+            .and_then(|s| s.group.clone())
+    };
+    assert_eq!(group_of("base"), Some("base".to_string()));
+    assert_eq!(group_of("linked"), Some("base".to_string()));
+    assert_eq!(group_of(KEEPALIVE), None);
+}
+
+#[test]
+fn list_sessions_across_merges_servers() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+    let other = format!("{}-other", server.socket);
+    let absent = format!("{}-absent", server.socket);
+    let started = Command::new("tmux")
+        .args(["-L", &other, "-f", "/dev/null"])
+        .args(["new-session", "-d", "-s", "remote"])
+        .status()
+        .expect("run tmux");
+    assert!(started.success());
+
+    let sockets = [server.socket.clone(), absent, other.clone()];
+    let sessions = tmux::list_sessions_across(&sockets);
+    let _ = Command::new("tmux")
+        .args(["-L", &other, "kill-server"])
+        .status();
+
+    let sessions = sessions.unwrap();
+    let socket_of = |name: &str| {
+        sessions
+            .iter()
+            .find(|s| s.name == name)
+            .and_then(|s| s.socket.clone())
+    };
+    assert_eq!(socket_of(KEEPALIVE), Some(server.socket.clone()));
+    assert_eq!(socket_of("remote"), Some(other));
+}
+
+#[test]
+fn has_session_matches_exact_names() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    tmux::create_session(server.socket(), "api-old", None, &[]).unwrap();
+    assert_eq!(tmux::has_session(server.socket(), "api-old"), Ok(true));
+    assert_eq!(tmux::has_session(server.socket(), "api"), Ok(false));
+}
+
+#[test]
+fn create_if_missing_reuses_existing_sessions() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    assert!(tmux::create_if_missing(server.socket(), "work", Some("/tmp")).unwrap());
+    assert!(!tmux::create_if_missing(server.socket(), "work", None).unwrap());
+    let names = server.session_names();
+    assert_eq!(names.iter().filter(|name| *name == "work").count(), 1);
+    assert_eq!(
+        tmux::session_details(server.socket(), "work").unwrap().path,
+        "/tmp"
+    );
+}
+
+#[test]
+fn session_details_lists_windows() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    tmux::create_session(server.socket(), "work", None, &[]).unwrap();
+    tmux::new_window(server.socket(), "work", Some("logs"), None).unwrap();
+
+    let details = tmux::session_details(server.socket(), "work").unwrap();
+    assert!(!details.path.is_empty());
+    assert!(details.created > 0);
+    assert!(details.clients.is_empty());
+    let names: Vec<&str> = details.windows.iter().map(|w| w.name.as_str()).collect();
+    assert_eq!(names.len(), 2);
+    assert_eq!(names[1], "logs");
+    assert_eq!(details.windows.iter().filter(|w| w.active).count(), 1);
+
+    assert!(tmux::session_details(server.socket(), "missing").is_err());
+}
+
+#[test]
+fn capture_scrollback_writes_pane_history() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    tmux::create_session(server.socket(), "build", None, &[]).unwrap();
+    server.tmux(&[
+        "send-keys",
+        "-t",
+        "build:",
+        "echo scrollback-marker",
+        "Enter",
+    ]);
+    std::thread::sleep(std::time::Duration::from_millis(300));
+
+    let path = std::env::temp_dir().join(format!("{}-scrollback.txt", server.socket));
+    let result = tmux::capture_scrollback(server.socket(), "build", &path);
+    let contents = std::fs::read_to_string(&path).unwrap_or_default();
+    let _ = std::fs::remove_file(&path);
+
+    result.unwrap();
+    assert!(contents.contains("scrollback-marker"), "{}", contents);
+
+    // A failed capture leaves an existing file as it was
+    std::fs::write(&path, "kept").unwrap();
+    let failed = tmux::capture_scrollback(server.socket(), "missing", &path);
+    let contents = std::fs::read_to_string(&path).unwrap_or_default();
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(
+        failed.unwrap_err(),
+        TmuxError::NotFound("missing".to_string())
+    );
+    assert_eq!(contents, "kept");
+}
+
+#[test]
+fn capture_pane_returns_visible_text() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    tmux::create_session(server.socket(), "build", None, &[]).unwrap();
+    server.tmux(&["send-keys", "-t", "build:", "echo tail-marker", "Enter"]);
+    std::thread::sleep(std::time::Duration::from_millis(300));
+
+    let text = tmux::capture_pane(server.socket(), "build").unwrap();
+    assert!(text.contains("tail-marker"), "{}", text);
+    assert_eq!(
+        tmux::capture_pane(server.socket(), "missing").unwrap_err(),
+        TmuxError::NotFound("missing".to_string())
+    );
+}
+
+#[test]
+fn errors_are_classified() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    tmux::create_session(server.socket(), "dup", None, &[]).unwrap();
+    assert_eq!(
+        tmux::create_session(server.socket(), "dup", None, &[]).unwrap_err(),
+        TmuxError::DuplicateName("dup".to_string())
+    );
+    assert_eq!(
+        tmux::rename_session(server.socket(), KEEPALIVE, "dup", false).unwrap_err(),
+        TmuxError::DuplicateName("dup".to_string())
+    );
+    assert_eq!(
+        tmux::kill_session(server.socket(), "missing", false).unwrap_err(),
+        TmuxError::NotFound("missing".to_string())
+    );
+
+    // Anything unrecognized keeps tmux's message behind the context; a
+    // window can only be split so far ("no space for new pane")
+    let layout = LayoutPreset {
+        name: "crowded".to_string(),
+        panes: 100,
+        layout: None,
+    };
+    let err = tmux::apply_layout(server.socket(), "dup", &layout).unwrap_err();
+    assert!(
+        matches!(err, TmuxError::CommandFailed { ref stderr, .. } if !stderr.is_empty()),
+        "{:?}",
+        err
+    );
+    assert!(err.to_string().starts_with("Failed to split tmux window: "));
+}
+
+#[test]
+fn dry_run_leaves_sessions_alone() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    tmux::create_session(server.socket(), "keep", None, &[]).unwrap();
+    tmux::rename_session(server.socket(), "keep", "renamed", true).unwrap();
+    tmux::kill_session(server.socket(), "keep", true).unwrap();
+    assert!(server.session_names().contains(&"keep".to_string()));
+}
+
+#[test]
+fn apply_layout_splits_first_window() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    tmux::create_session(server.socket(), "dev", None, &[]).unwrap();
+    let layout = LayoutPreset {
+        name: "dev".to_string(),
+        panes: 3,
+        layout: Some("main-vertical".to_string()),
+    };
+    tmux::apply_layout(server.socket(), "dev", &layout).unwrap();
+
+    let panes = tmux::list_panes(server.socket());
+    assert_eq!(panes.iter().filter(|p| p.session_name == "dev").count(), 3);
+}
+
+#[test]
+fn split_window_adds_a_pane_in_either_direction() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    tmux::create_session(server.socket(), "dev", None, &[]).unwrap();
+    tmux::split_window(
+        server.socket(),
+        "dev",
+        tmux::SplitDirection::Horizontal,
+        Some("/tmp"),
+    )
+    .unwrap();
+    let layout = tmux::window_layout(server.socket(), "dev").unwrap();
+    assert_eq!(layout.panes, 2);
+    // tmux writes side-by-side panes in braces, stacked ones in brackets
+    assert!(layout.layout.contains('{'), "{}", layout.layout);
+
+    tmux::create_session(server.socket(), "logs", None, &[]).unwrap();
+    tmux::split_window(
+        server.socket(),
+        "logs",
+        tmux::SplitDirection::Vertical,
+        None,
+    )
+    .unwrap();
+    let layout = tmux::window_layout(server.socket(), "logs").unwrap();
+    assert!(layout.layout.contains('['), "{}", layout.layout);
+}
+
+#[test]
+fn synchronize_panes_covers_every_window() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+    tmux::create_session(server.socket(), "demo", None, &[]).unwrap();
+    server.tmux(&["new-window", "-d", "-t", "demo"]);
+    let synchronized = || -> Vec<bool> {
+        tmux::session_details(server.socket(), "demo")
+            .unwrap()
+            .windows
+            .iter()
+            .map(|w| w.synchronized)
+            .collect()
+    };
+    assert_eq!(synchronized(), [false, false]);
+
+    tmux::set_synchronize_panes(server.socket(), "demo", true).unwrap();
+    assert_eq!(synchronized(), [true, true]);
+    tmux::set_synchronize_panes(server.socket(), "demo", false).unwrap();
+    assert_eq!(synchronized(), [false, false]);
+}
+
+#[test]
+fn window_targets_follow_base_index() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+    assert_eq!(tmux::base_index(server.socket()).unwrap(), 0);
+    server.tmux(&["set-option", "-g", "base-index", "1"]);
+    assert_eq!(tmux::base_index(server.socket()).unwrap(), 1);
+
+    tmux::create_session(server.socket(), "demo", None, &[]).unwrap();
+    tmux::new_window(server.socket(), "demo", Some("logs"), None).unwrap();
+    let windows = tmux::session_details(server.socket(), "demo")
+        .unwrap()
+        .windows;
+    let indices: Vec<u32> = windows.iter().map(|w| w.index).collect();
+    assert_eq!(indices, [1, 2]);
+
+    // The listed index is the target; position 0 isn't a window here
+    tmux::select_window(server.socket(), "demo", 2).unwrap();
+    let active = tmux::session_details(server.socket(), "demo")
+        .unwrap()
+        .windows;
+    assert_eq!(active.iter().find(|w| w.active).unwrap().name, "logs");
+    assert!(tmux::select_window(server.socket(), "demo", 0).is_err());
+}
+
+#[test]
+fn send_keys_types_into_a_detached_session() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+    tmux::create_session(server.socket(), "demo", None, &[]).unwrap();
+
+    // Typed but not run, so the test doesn't depend on the shell
+    let keys = ["echo".to_string(), "Space".to_string(), "ursa-macro".to_string()];
+    tmux::send_keys(server.socket(), "demo", &keys).unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !tmux::capture_pane(server.socket(), "demo")
+        .unwrap()
+        .contains("echo ursa-macro")
+    {
+        assert!(Instant::now() < deadline, "the keys never arrived");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn zoom_active_pane_only_zooms_once() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    tmux::create_session(server.socket(), "dev", None, &[]).unwrap();
+    server.tmux(&["split-window", "-d", "-t", "dev:"]);
+    let zoomed = || {
+        let output = Command::new("tmux")
+            .args(["-L", &server.socket, "display-message", "-p", "-t", "dev:"])
+            .arg("#{window_zoomed_flag}")
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).trim() == "1"
+    };
+
+    tmux::zoom_active_pane(server.socket(), "dev").unwrap();
+    assert!(zoomed());
+    // A second call must not toggle the zoom back off
+    tmux::zoom_active_pane(server.socket(), "dev").unwrap();
+    assert!(zoomed());
+}
+
+#[test]
+fn restore_layout_round_trips_a_snapshot() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    tmux::create_session(server.socket(), "dev", None, &[]).unwrap();
+    server.tmux(&["split-window", "-d", "-t", "dev:"]);
+    server.tmux(&["split-window", "-d", "-t", "dev:"]);
+    server.tmux(&["select-layout", "-t", "dev:", "main-vertical"]);
+    let saved = tmux::window_layout(server.socket(), "dev").unwrap();
+    assert_eq!(saved.panes, 3);
+
+    tmux::create_session(server.socket(), "fresh", None, &[]).unwrap();
+    assert!(tmux::restore_layout(server.socket(), "fresh", &saved).unwrap());
+    let restored = tmux::window_layout(server.socket(), "fresh").unwrap();
+    assert_eq!(restored.panes, 3);
+
+    // A window that already has more panes than the snapshot is left alone
+    let single = tmux::window_layout(server.socket(), KEEPALIVE).unwrap();
+    assert!(!tmux::restore_layout(server.socket(), "fresh", &single).unwrap());
+    assert_eq!(
+        tmux::window_layout(server.socket(), "fresh").unwrap().panes,
+        3
+    );
+}
+
+#[test]
+fn list_sessions_without_server_is_no_server() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    let absent = format!("{}-absent", server.socket);
+    assert_eq!(
+        tmux::list_sessions(Some(&absent)).unwrap_err(),
+        TmuxError::NoServer
+    );
+}
+
+#[test]
+fn control_clients_report_changes_without_counting_as_attached() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+    let wait_for = |watcher: &mut EventWatcher, wanted: Changes| {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while watcher.changes() != wanted {
+            assert!(Instant::now() < deadline, "never saw {:?}", wanted);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    };
+
+    // It starts in the background
+    let mut watcher = EventWatcher::start(std::slice::from_ref(&server.socket));
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !watcher.is_watching() {
+        assert_eq!(watcher.changes(), Changes::None);
+        assert!(Instant::now() < deadline, "never started");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    // It's attached to the only session, which still reads as detached
+    let sessions = tmux::list_sessions(server.socket()).unwrap();
+    assert!(!sessions[0].attached, "{:?}", sessions);
+    assert_eq!(watcher.changes(), Changes::None);
+
+    tmux::create_session(server.socket(), "new", None, &[]).unwrap();
+    wait_for(&mut watcher, Changes::Some);
+    tmux::rename_session(server.socket(), "new", "renamed", false).unwrap();
+    wait_for(&mut watcher, Changes::Some);
+
+    // Closing the session it's attached to ends it
+    server.tmux(&["kill-session", "-t", KEEPALIVE]);
+    wait_for(&mut watcher, Changes::Lost);
+
+    // Nothing to attach to on a server without sessions
+    let absent = format!("{}-absent", server.socket);
+    let mut watcher = EventWatcher::start(&[absent]);
+    wait_for(&mut watcher, Changes::Unavailable);
+    assert!(!watcher.is_watching());
+}
+
+#[test]
+fn switch_client_without_a_client_fails() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    tmux::create_session(server.socket(), "target", None, &[]).unwrap();
+    assert!(tmux::switch_client(server.socket(), "target").is_err());
+}
+
+#[test]
+fn attach_args_name_the_server() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    assert_eq!(
+        tmux::attach_args(server.socket(), "my session"),
+        vec![
+            "-L",
+            server.socket.as_str(),
+            "attach-session",
+            "-t",
+            "my session"
+        ]
+    );
+    assert_eq!(
+        tmux::attach_command(server.socket(), "my session"),
+        format!("tmux -L {} attach-session -t 'my session'", server.socket)
+    );
+}
+
+/// Set for a test run by [`run_with_fake_ssh`]
+const FAKE_SSH: &str = "URSA_TEST_FAKE_SSH";
+
+/// Run `test` from this file in a child process whose PATH starts with a
+/// stand-in ssh. It runs the command line locally, like a remote shell would,
+/// and fails to connect to the host "down".
+fn run_with_fake_ssh(test: &str) {
+    let bin = std::env::temp_dir().join(format!("ursa-test-{}-ssh", std::process::id()));
+    std::fs::create_dir_all(&bin).unwrap();
+    let ssh = bin.join("ssh");
+    std::fs::write(
+        &ssh,
+        "#!/bin/sh\n\
+         while [ \"$1\" != -- ]; do host=$1; shift; done; shift\n\
+         [ \"$host\" = down ] && { echo 'ssh: connect to host down: Connection refused' >&2; exit 255; }\n\
+         exec sh -c \"$*\"\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&ssh, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let mut dirs = vec![bin.clone()];
+    dirs.extend(std::env::split_paths(&path));
+
+    let status = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", test, "--test-threads=1"])
+        .env("PATH", std::env::join_paths(dirs).unwrap())
+        .env(FAKE_SSH, "1")
+        .status();
+    let _ = std::fs::remove_dir_all(&bin);
+    assert!(
+        status.unwrap().success(),
+        "{} failed with the stand-in ssh",
+        test
+    );
+}
+
+#[test]
+fn remote_commands_go_through_ssh() {
+    // ssh is looked up on PATH, so the stand-in is put first on it for a
+    // copy of this test in a child process rather than for every test here
+    if std::env::var_os(FAKE_SSH).is_none() {
+        run_with_fake_ssh("remote_commands_go_through_ssh");
+        return;
+    }
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    tmux::set_remote(Some("me@box"));
+    let created = tmux::create_session(server.socket(), "over ssh", None, &[]);
+    let names = tmux::list_sessions(server.socket())
+        .map(|sessions| sessions.into_iter().map(|s| s.name).collect::<Vec<_>>());
+    let argv = tmux::attach_argv(server.socket(), "over ssh");
+    tmux::set_remote(Some("down"));
+    let unreachable = tmux::list_sessions(server.socket()).unwrap_err();
+    tmux::set_remote(None);
+
+    created.unwrap();
+    assert!(names.unwrap().contains(&"over ssh".to_string()));
+    assert_eq!(
+        argv,
+        vec![
+            "ssh",
+            "-t",
+            "me@box",
+            "--",
+            "tmux",
+            "-L",
+            server.socket.as_str(),
+            "attach-session",
+            "-t",
+            "'over ssh'"
+        ]
+    );
+    assert_eq!(
+        unreachable,
+        TmuxError::Unreachable {
+            host: "down".to_string(),
+            message: "ssh: connect to host down: Connection refused".to_string(),
+        }
+    );
+}
+
+#[test]
+fn debug_log_records_commands_and_failures() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    let path = std::env::temp_dir().join(format!("{}.log", server.socket));
+    ursa::logging::init(&path).unwrap();
+    tmux::create_session(server.socket(), "logged", None, &[]).unwrap();
+    assert!(tmux::kill_session(server.socket(), "missing", false).is_err());
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let created = log
+        .lines()
+        .find(|line| line.contains("\"new-session\""))
+        .unwrap();
+    assert!(created.contains("DEBUG"), "{}", created);
+    let failed = log
+        .lines()
+        .find(|line| line.contains("\"kill-session\""))
+        .unwrap();
+    assert!(failed.contains("WARN"), "{}", failed);
+    assert!(failed.contains("can't find session"), "{}", failed);
+}
+
+#[test]
+fn missing_binary_is_reported_by_path() {
+    let Some(server) = TestServer::start() else {
+        return;
+    };
+
+    assert!(tmux::check_binary().unwrap().starts_with("tmux "));
+
+    tmux::set_binary(Some("/nonexistent/tmux"));
+    let err = tmux::check_binary().unwrap_err();
+    let message = err.to_string();
+    let listed = tmux::list_sessions(server.socket()).unwrap_err();
+    // A path that exists but can't run says which one was tried
+    let dir = std::env::temp_dir();
+    tmux::set_binary(Some(&dir.to_string_lossy()));
+    let unrunnable = tmux::check_binary().unwrap_err().to_string();
+    tmux::set_binary(None);
+
+    assert_eq!(err, TmuxError::NotInstalled);
+    assert_eq!(listed, TmuxError::NotInstalled);
+    assert!(message.contains("/nonexistent/tmux"), "{}", message);
+    assert!(
+        unrunnable.contains(&*dir.to_string_lossy()),
+        "{}",
+        unrunnable
+    );
+}
+
+#[test]
+fn validate_name_rules() {
+    let existing = ["alpha", "beta"];
+    assert!(tmux::validate_name("gamma", existing).is_ok());
+    assert!(tmux::validate_name("", existing).is_err());
+    assert!(tmux::validate_name("  ", existing).is_err());
+    assert!(tmux::validate_name("a:b", existing).is_err());
+    assert!(tmux::validate_name("a.b", existing).is_err());
+    assert_eq!(
+        tmux::validate_name("beta", existing).unwrap_err(),
+        "A session named 'beta' already exists"
+    );
+    assert_eq!(tmux::validate_name("gamma", existing), Ok(None));
+    assert_eq!(
+        tmux::validate_name("Beta", existing),
+        Ok(Some("A session 'beta' already exists".to_string()))
+    );
+}
+
+#[test]
+fn validate_env_rules() {
+    assert_eq!(tmux::validate_env("FOO=bar"), Ok(()));
+    assert_eq!(tmux::validate_env("_PATH2=a=b"), Ok(()));
+    assert_eq!(tmux::validate_env("FOO="), Ok(()));
+    assert!(tmux::validate_env("FOO").is_err());
+    assert!(tmux::validate_env("=bar").is_err());
+    assert!(tmux::validate_env("2FOO=bar").is_err());
+    assert!(tmux::validate_env("FOO BAR=1").is_err());
+}
+
+#[test]
+fn popup_attaches_without_the_nesting_check() {
+    let args = tmux::popup_args(None, "my session");
+    assert_eq!(&args[..2], ["display-popup", "-E"]);
+    let command = args.last().unwrap();
+    assert!(command.starts_with("TMUX= "), "{}", command);
+    assert!(
+        command.ends_with("attach-session -t 'my session'"),
+        "{}",
+        command
+    );
+}
+
+#[test]
+fn parse_version_reads_major_and_minor() {
+    assert_eq!(tmux::parse_version("tmux 3.3a"), Some((3, 3)));
+    assert_eq!(tmux::parse_version("tmux 3.2\n"), Some((3, 2)));
+    assert_eq!(tmux::parse_version("tmux 2.9a"), Some((2, 9)));
+    assert_eq!(tmux::parse_version("tmux next-3.4"), Some((3, 4)));
+    assert_eq!(tmux::parse_version("tmux master"), None);
+}
+
+#[test]
+fn inside_tmux_requires_env_and_client() {
+    assert!(!tmux::detect_inside_tmux(None, || true));
+    assert!(!tmux::detect_inside_tmux(Some(""), || true));
+    assert!(!tmux::detect_inside_tmux(Some("  "), || true));
+    assert!(!tmux::detect_inside_tmux(
+        Some("/tmp/tmux-1000/default,123,0"),
+        || false
+    ));
+    assert!(tmux::detect_inside_tmux(
+        Some("/tmp/tmux-1000/default,123,0"),
+        || true
+    ));
+}
+
+#[test]
+fn inside_tmux_skips_client_check_without_env() {
+    let mut checked = false;
+    tmux::detect_inside_tmux(None, || {
+        checked = true;
+        true
+    });
+    assert!(!checked);
+}